@@ -0,0 +1,180 @@
+//! Timings for the hot paths: filtering the list as a query is typed and
+//! scanning `project_dirs`. Run with `cargo bench`; criterion prints each
+//! time and how it moved since the last run. Set `PL_BENCH_BUDGET` to also
+//! check the medians against fixed budgets and fail the run when one goes
+//! over; its value scales them, so `PL_BENCH_BUDGET=2` doubles them for a
+//! slower machine.
+
+use criterion::Criterion;
+use pl::app::App;
+use pl::config::{MatchBackend, ProjectDir, ProjectDirSettings, UserConfig};
+use pl::project::{Project, get_all_projects};
+use pl::state::State;
+use std::fs;
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tui_input::Input;
+
+const NAME_COUNT: usize = 10_000;
+const BUDGET_RUNS: usize = 15;
+
+/// Budgets for the medians, several times what they take on a laptop so
+/// that only a real slowdown trips them.
+const FILTER_BUDGET: Duration = Duration::from_millis(150);
+const DISCOVER_BUDGET: Duration = Duration::from_secs(5);
+
+const WORDS: [&str; 16] = [
+    "api", "web", "core", "cli", "data", "infra", "auth", "docs", "mobile", "sdk", "tools",
+    "billing", "search", "worker", "proxy", "ui",
+];
+
+fn main() {
+    let mut criterion = Criterion::default().configure_from_args();
+    let budget_scale: Option<f64> = std::env::var("PL_BENCH_BUDGET")
+        .ok()
+        .and_then(|scale| scale.parse().ok());
+    let mut over_budget = 0;
+
+    let projects = projects(NAME_COUNT);
+    let mut group = criterion.benchmark_group("filter_results");
+    for backend in [
+        MatchBackend::Substring,
+        MatchBackend::Fuzzy,
+        MatchBackend::Layered,
+    ] {
+        let mut app = app(backend, projects.clone());
+        for query in ["a", "api", "bill-wkr", "search-proxy-ui"] {
+            let label = format!("{backend:?} {} chars", query.len());
+            app.input = Input::new(query.to_string());
+            group.bench_function(&label, |b| b.iter(|| app.filter_results()));
+            if let Some(scale) = budget_scale
+                && !within_budget(&label, FILTER_BUDGET.mul_f64(scale), || {
+                    app.filter_results()
+                })
+            {
+                over_budget += 1;
+            }
+        }
+    }
+    group.finish();
+
+    let root = synthetic_tree();
+    let config = UserConfig {
+        project_dirs: vec![ProjectDir::Table(ProjectDirSettings {
+            path: root.to_string_lossy().into_owned(),
+            max_depth: Some(2),
+            editor_command: None,
+            require_git: None,
+        })],
+        ..UserConfig::default()
+    };
+    let label = "discover 1000 projects";
+    criterion
+        .benchmark_group("scan")
+        .sample_size(10)
+        .bench_function(label, |b| b.iter(|| get_all_projects(&config)));
+    if let Some(scale) = budget_scale
+        && !within_budget(label, DISCOVER_BUDGET.mul_f64(scale), || {
+            get_all_projects(&config)
+        })
+    {
+        over_budget += 1;
+    }
+    let _ = fs::remove_dir_all(&root);
+
+    criterion.final_summary();
+    if over_budget > 0 {
+        eprintln!("{over_budget} benchmarks over budget");
+        std::process::exit(1);
+    }
+}
+
+/// The app over `projects`, ranking with `backend`. The state has seen
+/// every project already, so building it doesn't write the state file.
+fn app(backend: MatchBackend, projects: Vec<Project>) -> App {
+    let config = UserConfig {
+        match_backend: backend,
+        ..UserConfig::default()
+    };
+    let state = State {
+        seen_projects: projects
+            .iter()
+            .map(|project| project.project_path.clone())
+            .collect(),
+        ..State::default()
+    };
+    App::from_projects(config, state, projects)
+}
+
+/// `count` made-up projects with names like `billing-proxy-42`, the same
+/// every run. Filtering never reads their paths, so nothing is on disk.
+fn projects(count: usize) -> Vec<Project> {
+    let mut seed: u64 = 0x9e37_79b9_7f4a_7c15;
+    let mut next = move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed as usize
+    };
+
+    (0..count)
+        .map(|index| {
+            let first = WORDS[next() % WORDS.len()];
+            let second = WORDS[next() % WORDS.len()];
+            let name = format!("{first}-{second}-{index}");
+            Project {
+                project_path: Path::new("/pl-bench").join(&name),
+                project_name: name.into(),
+                project_remote: String::new(),
+                project_root: None,
+                bare: false,
+                worktree: None,
+                marker: None,
+            }
+        })
+        .collect()
+}
+
+/// Ten groups of a hundred repositories each, two levels deep, under a
+/// fresh temporary directory.
+fn synthetic_tree() -> PathBuf {
+    let root = std::env::temp_dir().join(format!("pl-bench-{}", std::process::id()));
+    for group in 0..10 {
+        for repo in 0..100 {
+            make_repo(
+                &root
+                    .join(format!("group-{group}"))
+                    .join(format!("repo-{repo}")),
+            );
+        }
+    }
+    root
+}
+
+fn make_repo(path: &Path) {
+    fs::create_dir_all(path.join(".git")).expect("can't create the bench tree");
+    fs::write(path.join("README.md"), "# Bench\n").expect("can't create the bench tree");
+}
+
+/// Runs `f` `BUDGET_RUNS` times after a warm-up and prints the median
+/// against `budget`. Returns whether it stayed within it.
+fn within_budget<T>(label: &str, budget: Duration, mut f: impl FnMut() -> T) -> bool {
+    black_box(f());
+    let mut times: Vec<Duration> = (0..BUDGET_RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    let median = times[BUDGET_RUNS / 2];
+    let verdict = if median <= budget {
+        ""
+    } else {
+        "  OVER BUDGET"
+    };
+    println!("budget {label:<32} {median:>10.3?}  (budget {budget:.0?}){verdict}");
+    median <= budget
+}
@@ -0,0 +1,180 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+
+const ESC: char = '\x1b';
+
+/// Turns command output colored with ANSI escape codes, such as
+/// `git log --color=always`, into styled text. SGR sequences (`ESC [ ... m`)
+/// set the style; other control sequences, and any cut off at the end, are
+/// dropped rather than shown as raw escapes.
+pub fn to_text(raw: &str) -> Text<'static> {
+    let mut style = Style::new();
+    raw.lines()
+        .map(|line| parse_line(line, &mut style))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Parses one line. `style` carries over from the previous line, since
+/// tools don't always reset before a newline.
+fn parse_line(line: &str, style: &mut Style) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != ESC {
+            if !ch.is_control() || ch == '\t' {
+                current.push(ch);
+            }
+            continue;
+        }
+
+        match chars.next() {
+            Some('[') => {
+                // Parameters and intermediates, up to the final byte.
+                let mut params = String::new();
+                let mut terminator = None;
+                for ch in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&ch) {
+                        terminator = Some(ch);
+                        break;
+                    }
+                    params.push(ch);
+                }
+                if terminator == Some('m') {
+                    let next = apply_sgr(*style, &params);
+                    if next != *style && !current.is_empty() {
+                        spans.push(Span::styled(std::mem::take(&mut current), *style));
+                    }
+                    *style = next;
+                }
+            }
+            Some(']') => {
+                // An OSC string, such as a hyperlink, ends at BEL or `ESC \`.
+                while let Some(ch) = chars.next() {
+                    if ch == '\x07' || (ch == ESC && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Two-character escapes; the second one goes with it.
+            Some(_) | None => {}
+        }
+    }
+
+    if !current.is_empty() {
+        spans.push(Span::styled(current, *style));
+    }
+    Line::from(spans)
+}
+
+/// Applies the `;`-separated SGR parameters in `params` to `style`. Unknown
+/// codes are skipped; an empty list means reset.
+fn apply_sgr(mut style: Style, params: &str) -> Style {
+    let codes: Vec<u16> = params
+        .split([';', ':'])
+        .map(|code| code.parse().unwrap_or(0))
+        .collect();
+    let mut codes = codes.into_iter();
+
+    while let Some(code) = codes.next() {
+        style = match code {
+            0 => Style::new(),
+            1 => style.add_modifier(Modifier::BOLD),
+            2 => style.add_modifier(Modifier::DIM),
+            3 => style.add_modifier(Modifier::ITALIC),
+            4 => style.add_modifier(Modifier::UNDERLINED),
+            5 => style.add_modifier(Modifier::SLOW_BLINK),
+            7 => style.add_modifier(Modifier::REVERSED),
+            9 => style.add_modifier(Modifier::CROSSED_OUT),
+            22 => style.remove_modifier(Modifier::BOLD | Modifier::DIM),
+            23 => style.remove_modifier(Modifier::ITALIC),
+            24 => style.remove_modifier(Modifier::UNDERLINED),
+            25 => style.remove_modifier(Modifier::SLOW_BLINK),
+            27 => style.remove_modifier(Modifier::REVERSED),
+            29 => style.remove_modifier(Modifier::CROSSED_OUT),
+            30..=37 => style.fg(Color::Indexed((code - 30) as u8)),
+            39 => style.fg(Color::Reset),
+            40..=47 => style.bg(Color::Indexed((code - 40) as u8)),
+            49 => style.bg(Color::Reset),
+            90..=97 => style.fg(Color::Indexed((code - 90 + 8) as u8)),
+            100..=107 => style.bg(Color::Indexed((code - 100 + 8) as u8)),
+            38 => match extended_color(&mut codes) {
+                Some(color) => style.fg(color),
+                None => style,
+            },
+            48 => match extended_color(&mut codes) {
+                Some(color) => style.bg(color),
+                None => style,
+            },
+            _ => style,
+        };
+    }
+    style
+}
+
+/// Reads the rest of a `38` / `48` code: `5;n` for the 256-color palette or
+/// `2;r;g;b` for true color.
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    let byte = |code: Option<u16>| code.and_then(|code| u8::try_from(code).ok());
+    match codes.next()? {
+        5 => byte(codes.next()).map(Color::Indexed),
+        2 => Some(Color::Rgb(
+            byte(codes.next())?,
+            byte(codes.next())?,
+            byte(codes.next())?,
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(raw: &str) -> Vec<(String, Style)> {
+        to_text(raw).lines[0]
+            .spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style))
+            .collect()
+    }
+
+    #[test]
+    fn a_cut_off_sequence_is_dropped() {
+        let red = Style::new().fg(Color::Indexed(1));
+        assert_eq!(spans("\x1b[31mred\x1b[0"), [("red".to_string(), red)]);
+        assert_eq!(spans("plain\x1b["), [("plain".to_string(), Style::new())]);
+        assert_eq!(spans("plain\x1b"), [("plain".to_string(), Style::new())]);
+    }
+
+    #[test]
+    fn unknown_codes_are_skipped() {
+        let bold_red = Style::new()
+            .add_modifier(Modifier::BOLD)
+            .fg(Color::Indexed(1));
+        assert_eq!(
+            spans("\x1b[1;53;31;999mtext"),
+            [("text".to_string(), bold_red)]
+        );
+        assert_eq!(spans("\x1b[53mtext"), [("text".to_string(), Style::new())]);
+    }
+
+    #[test]
+    fn true_color_needs_all_three_components() {
+        assert_eq!(
+            spans("\x1b[38;2;10;20;30mrgb"),
+            [("rgb".to_string(), Style::new().fg(Color::Rgb(10, 20, 30)))]
+        );
+        assert_eq!(
+            spans("\x1b[38;2;10;20mrgb"),
+            [("rgb".to_string(), Style::new())]
+        );
+        assert_eq!(spans("\x1b[48;2mrgb"), [("rgb".to_string(), Style::new())]);
+        assert_eq!(
+            spans("\x1b[38;2;300;0;0mrgb"),
+            [("rgb".to_string(), Style::new())]
+        );
+    }
+}
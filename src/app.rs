@@ -1,11 +1,33 @@
-use crate::config::{UserConfig, load_user_config};
-use crate::project::{Project, get_all_projects};
+use crate::cli::Cli;
+use crate::config::{
+    Action, FilterSort, Group, OnEmpty, OpenMode, ProjectDir, SortBy, StartMode, UserConfig,
+    config_path, load_user_config,
+};
+use crate::git::{self, GitStatus, Metadata, head_commit};
+use crate::glob::glob_match;
+use crate::input::Keymap;
+use crate::markdown::{self, Heading};
+use crate::matcher::{self, Matcher};
+use crate::project::{
+    Project, ScanError, archive_project, create_project, detect_language, dir_size, display_path,
+    find_file, format_age, format_size, get_all_projects, history_project, language_breakdown,
+    manifest_summary, natural_cmp, parse_dir, recent_files_preview, rename_project, tree_preview,
+};
+use crate::state::{State, load_scan_cache, load_state, save_scan_cache, save_state};
+use crate::worker::{
+    CommitTimeWorker, ContentMatch, ContentSearchWorker, GitStatusWorker, ScanUpdate, ScanWorker,
+};
+use crate::{clipboard, launch};
 use ratatui::widgets::ListState;
-use std::collections::HashMap;
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::io;
-use std::path::PathBuf;
-use tui_input::Input;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tui_input::{Input, InputRequest};
 
 #[derive(Debug)]
 pub struct App {
@@ -14,19 +36,338 @@ pub struct App {
     pub input: Input,
     pub input_mode: InputMode,
     user_config: UserConfig,
+    matcher: Box<dyn Matcher>,
     projects: Vec<Project>,
     exit: bool,
-    open_target: Option<PathBuf>,
+    open_targets: Vec<PathBuf>,
+    /// With `--print`, chosen projects are printed instead of opened.
+    print_selection: bool,
+    launch_mode: OpenMode,
+    foreground_launches: Vec<PathBuf>,
+    /// The search that found the projects being launched.
+    launch_query: Option<String>,
+    /// A project to start a shell in, once the TUI loop has stepped aside.
+    shell_request: Option<PathBuf>,
+    /// A `wait` action for the TUI loop to run while it steps aside.
+    action_request: Option<(String, Command)>,
     readme_cache: HashMap<PathBuf, Option<String>>,
+    word_counts: HashMap<PathBuf, usize>,
+    preview_paths_cache: HashMap<PathBuf, Vec<PathBuf>>,
+    preview_project: Option<PathBuf>,
+    preview_index: usize,
+    /// How far the preview is scrolled with `Ctrl-d` / `Ctrl-u` while the
+    /// list has focus. Back to the top whenever another file is previewed.
+    preview_scroll: usize,
+    size_cache: HashMap<PathBuf, u64>,
+    tree_cache: HashMap<PathBuf, String>,
+    recent_files_cache: HashMap<PathBuf, (Option<String>, String)>,
+    content_matches: HashMap<PathBuf, ContentMatch>,
+    /// The term `content_matches` were found for, and when it was started.
+    content_term: Option<(String, Instant)>,
+    /// The content search, while it is still running.
+    content_worker: Option<ContentSearchWorker>,
+    scores: HashMap<PathBuf, i64>,
+    /// The characters of each matching project's name that the query
+    /// matched, for highlighting.
+    match_indices: HashMap<PathBuf, Vec<usize>>,
+    git_status_cache: HashMap<PathBuf, Option<GitStatus>>,
+    /// Started on first use: by `show_git_status`, or for the previewed
+    /// project's header line.
+    git_status_worker: Option<GitStatusWorker>,
+    /// Paths on screen without a cached status, and when that set last
+    /// changed. They're handed to the worker once it has held still for
+    /// `git_status_debounce_ms`.
+    git_status_wanted: Vec<PathBuf>,
+    git_status_wanted_at: Instant,
+    git_status_requested: HashSet<PathBuf>,
+    language_cache: HashMap<PathBuf, Vec<(&'static str, u64)>>,
+    diff_stat_cache: HashMap<PathBuf, Option<String>>,
+    manifest_cache: HashMap<PathBuf, Option<(&'static str, String)>>,
+    no_preview_cache: HashMap<PathBuf, bool>,
+    /// The project the preview stays on while locked with `L`.
+    preview_locked_path: Option<PathBuf>,
+    activity_cache: HashMap<PathBuf, Option<Vec<usize>>>,
+    metadata_cache: HashMap<PathBuf, Metadata>,
+    git_descriptions: HashMap<PathBuf, Option<String>>,
+    preview_mode: PreviewMode,
+    absolute_paths: bool,
+    preview_show_hidden: bool,
     sort_state: SortState,
+    commit_time_cache: HashMap<PathBuf, Option<u64>>,
+    /// Started by the first `Committed` sort; the list is sorted again once
+    /// every requested time is in.
+    commit_time_worker: Option<CommitTimeWorker>,
+    commit_time_requested: HashSet<PathBuf>,
+    branch_cache: HashMap<PathBuf, Option<String>>,
+    commit_subject_cache: HashMap<PathBuf, Option<String>>,
+    roots: Vec<PathBuf>,
+    history_mode: bool,
+    /// The projects not being listed: the live scan while browsing history,
+    /// and the history otherwise (empty until first shown).
+    stashed_projects: Vec<Project>,
+    missing_projects: HashSet<PathBuf>,
+    root_index: Option<usize>,
+    sort_reverse: bool,
+    state_store: State,
+    history_index: Option<usize>,
     pub show_help: bool,
+    /// How far the help is scrolled, kept within its length when drawn.
+    pub help_scroll: usize,
+    pub show_all: bool,
+    /// Only list repositories with uncommitted changes, toggled with `D`.
+    dirty_only: bool,
+    /// Only list projects without any of the `preview_files`, toggled with
+    /// `N`.
+    no_readme_only: bool,
+    /// Whether each project has one of the `preview_files`.
+    has_readme_cache: HashMap<PathBuf, bool>,
+    match_count: usize,
+    /// The query `max_results` is lifted for with `Ctrl-l`, until it changes.
+    uncapped_query: Option<String>,
+    pub scan_errors: Vec<ScanError>,
+    pub show_scan_errors: bool,
+    /// Facts about the selected project shown by the info panel, as
+    /// label/value rows. `None` while the panel is closed.
+    pub info: Option<Vec<(&'static str, String)>>,
+    status: Option<Status>,
+    last_scan: Instant,
+    /// The background scan, while it is still running.
+    scan_worker: Option<(ScanWorker, ScanKind)>,
+    pub pending_action: Option<PendingAction>,
+    pub new_project: Option<NewProject>,
+    pub preview_cursor: Option<PreviewCursor>,
+    preview_folds: Option<PreviewFolds>,
+    /// The project selected before the query stopped matching anything, with
+    /// `keep_selection_on_empty`.
+    frozen_selection: Option<PathBuf>,
+    /// Projects marked with `v` to open together, in the order marked.
+    marked: Vec<PathBuf>,
+    /// The selected row of the recently opened list shown with `M`, or
+    /// `None` while it is closed.
+    pub recent_selection: Option<usize>,
+    /// The branches offered by `b`, or `None` while the picker is closed.
+    pub branch_picker: Option<BranchPicker>,
+    /// The new name typed into the rename prompt opened with `R`.
+    pub rename: Option<Input>,
+    /// The tags typed into the prompt opened with `#`.
+    pub tag_editor: Option<Input>,
+    pub keymap: Keymap,
+    select_hook: SelectHook,
+    /// The direction, time and length of the current run of `j` / `k`
+    /// presses, for `scroll_acceleration`.
+    motion_streak: Option<(isize, Instant, usize)>,
+    /// The directory typed into the prompt shown when nothing was found,
+    /// with `on_empty = "prompt_add"`.
+    pub add_dir: Option<Input>,
+    /// The leader key was pressed and the next key picks a `LEADER_ACTIONS`
+    /// entry.
+    pub leader_pending: bool,
+    last_archive: Option<(Project, PathBuf)>,
+}
+
+/// An action waiting for the user to confirm it.
+#[derive(Debug)]
+pub enum PendingAction {
+    Archive(Project),
+    OpenAll(Vec<PathBuf>),
+    /// Remove a project that no longer exists from the all-time history.
+    Forget(PathBuf),
+    /// Rename the project directory at the path to the new name.
+    Rename(PathBuf, String),
+}
+
+/// The prompt for creating a project, opened with `n`.
+#[derive(Debug)]
+pub enum NewProject {
+    Name(Input),
+    /// Picking the `project_dirs` root to create `name` under.
+    Root {
+        name: String,
+        selected: usize,
+    },
+    /// Picking a template for `name`. Index 0 stands for no template.
+    Template {
+        name: String,
+        root: PathBuf,
+        selected: usize,
+    },
+    /// Answering the picked template's `prompt`.
+    Prompt {
+        name: String,
+        root: PathBuf,
+        template: String,
+        input: Input,
+    },
+}
+
+/// The branch picker: the local branches of the repository at `path`.
+#[derive(Debug)]
+pub struct BranchPicker {
+    pub path: PathBuf,
+    pub branches: Vec<git::Branch>,
+    pub selected: usize,
+}
+
+impl BranchPicker {
+    /// Starts on the branch checked out now.
+    fn new(path: PathBuf, branches: Vec<git::Branch>) -> Self {
+        let selected = branches
+            .iter()
+            .position(|branch| branch.current)
+            .unwrap_or(0);
+        Self {
+            path,
+            branches,
+            selected,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Status {
+    message: String,
+    is_error: bool,
+    set_at: Instant,
+}
+
+/// Presses of the same motion key closer together than this count as the
+/// key being held, for `scroll_acceleration`.
+const REPEAT_WINDOW: Duration = Duration::from_millis(150);
+/// Held repeats before the step grows by another row, up to `MAX_STEP`.
+const ACCELERATION_RATE: usize = 5;
+const MAX_STEP: usize = 10;
+
+/// How long the selection has to stay put before `on_select_command` runs.
+const ON_SELECT_DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Tracks `on_select_command`: which project it last ran for, and the one
+/// run that may still be going, so scrolling never piles up processes.
+#[derive(Debug)]
+struct SelectHook {
+    seen: Option<PathBuf>,
+    changed_at: Instant,
+    last_run: Option<PathBuf>,
+    running: Option<std::process::Child>,
+}
+
+/// The longest wait between checks for finished git status.
+const GIT_STATUS_POLL: Duration = Duration::from_millis(50);
+
+const STATUS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a background scan is running, which decides what its results do.
+#[derive(Debug, Clone, Copy)]
+enum ScanKind {
+    /// The first scan, whose projects are listed as they are found, or
+    /// all at once when it started from the scan cache.
+    Startup { cached: bool },
+    /// `Ctrl-r`, which reports the new count.
+    Manual,
+    /// `auto_rescan_secs`, which only speaks up when the count moved from
+    /// `before`.
+    Auto { before: usize },
 }
 
 #[derive(Debug, Default)]
 enum SortState {
     #[default]
+    Frecent,
     Alphabetical,
     RecentlyModified,
+    RecentlyCommitted,
+}
+
+/// A command on the selected project run by pressing the leader key and
+/// then `key`.
+pub struct LeaderAction {
+    pub key: char,
+    pub description: &'static str,
+    pub run: fn(&mut App),
+}
+
+/// The leader key's actions, in the order the hint popup lists them.
+pub const LEADER_ACTIONS: &[LeaderAction] = &[
+    LeaderAction {
+        key: 'o',
+        description: "open",
+        run: App::open_selected_project,
+    },
+    LeaderAction {
+        key: 'y',
+        description: "copy path",
+        run: App::copy_selected_path,
+    },
+    LeaderAction {
+        key: 'r',
+        description: "reveal in file manager",
+        run: App::reveal_selected,
+    },
+    LeaderAction {
+        key: 's',
+        description: "shell in project",
+        run: App::open_shell,
+    },
+    LeaderAction {
+        key: 'd',
+        description: "archive",
+        run: App::request_archive,
+    },
+    LeaderAction {
+        key: 'n',
+        description: "rename",
+        run: App::start_rename,
+    },
+    LeaderAction {
+        key: 'm',
+        description: "pin / unpin",
+        run: App::toggle_pin,
+    },
+    LeaderAction {
+        key: 'i',
+        description: "info",
+        run: App::toggle_info,
+    },
+];
+
+/// The line cursor in the preview while it has focus, toggled with `Ctrl-f`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PreviewCursor {
+    pub line: usize,
+    /// Where the `v` selection started, if one is active.
+    pub anchor: Option<usize>,
+    /// The first line on screen, moved to keep the cursor in view.
+    pub scroll: usize,
+}
+
+impl PreviewCursor {
+    /// The selected lines, or just the cursor line without a selection.
+    pub fn selection(&self) -> std::ops::RangeInclusive<usize> {
+        let anchor = self.anchor.unwrap_or(self.line);
+        anchor.min(self.line)..=anchor.max(self.line)
+    }
+}
+
+/// The headings of a focused markdown preview and which of them are folded,
+/// with `fold_markdown`.
+#[derive(Debug)]
+struct PreviewFolds {
+    headings: Vec<Heading>,
+    collapsed: HashSet<usize>,
+}
+
+/// The position of `line` among the preview rows on screen, or of the row
+/// above it when it is folded away.
+fn row_of(rows: &[(usize, bool)], line: usize) -> usize {
+    rows.iter().rposition(|&(row, _)| row <= line).unwrap_or(0)
+}
+
+#[derive(Debug, Default)]
+enum PreviewMode {
+    #[default]
+    Readme,
+    RecentFiles,
+    Languages,
+    Changes,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -37,167 +378,4012 @@ pub enum InputMode {
 }
 
 impl App {
-    pub fn new() -> io::Result<Self> {
-        let user_config = load_user_config()?;
-        let projects = get_all_projects(&user_config.project_dirs);
-        let filtered_projects = projects.clone();
+    pub fn new(cli: &Cli) -> io::Result<Self> {
+        let mut user_config = load_user_config()?;
+        if !cli.dirs.is_empty() {
+            if !cli.extend_dirs {
+                user_config.project_dirs.clear();
+                user_config.project_source_command = None;
+                user_config.use_ghq = false;
+            }
+            user_config
+                .project_dirs
+                .extend(cli.dirs.iter().cloned().map(ProjectDir::Path));
+        }
 
-        let mut state = ListState::default();
-        if filtered_projects.is_empty() {
-            state.select(None);
-        } else {
-            state.select_first();
+        // The TUI scans in the background so it can draw straight away;
+        // `--list` has nothing to draw and needs the whole list, as does
+        // `--select-1` to know whether there is only one match.
+        // The last scan's projects fill the list until it is done.
+        let background = !cli.list && !cli.select_one;
+        let cached = background
+            .then(|| load_scan_cache(&user_config.scan_key()))
+            .flatten();
+        let scan_worker = background.then(|| {
+            let kind = ScanKind::Startup {
+                cached: cached.is_some(),
+            };
+            (ScanWorker::spawn(user_config.clone()), kind)
+        });
+        let (projects, scan_errors) = match (&scan_worker, cached) {
+            (Some(_), cached) => (cached.unwrap_or_default(), Vec::new()),
+            (None, _) => {
+                let (projects, errors) = get_all_projects(&user_config);
+                save_cache(&user_config, &projects);
+                (projects, errors)
+            }
+        };
+        Ok(Self::with_projects(
+            cli,
+            user_config,
+            load_state(),
+            projects,
+            scan_errors,
+            scan_worker,
+        ))
+    }
+
+    /// The app over `projects` as given, without scanning or reading the
+    /// state file, as the benchmarks build it. Projects missing from
+    /// `state_store.seen_projects` are still recorded in the state file.
+    pub fn from_projects(
+        user_config: UserConfig,
+        state_store: State,
+        projects: Vec<Project>,
+    ) -> Self {
+        Self::with_projects(
+            &Cli::default(),
+            user_config,
+            state_store,
+            projects,
+            Vec::new(),
+            None,
+        )
+    }
+
+    /// The app around a loaded config and state and the projects found so
+    /// far, with `scan_worker` still finding the rest when there is one.
+    fn with_projects(
+        cli: &Cli,
+        user_config: UserConfig,
+        state_store: State,
+        mut projects: Vec<Project>,
+        scan_errors: Vec<ScanError>,
+        scan_worker: Option<(ScanWorker, ScanKind)>,
+    ) -> Self {
+        let (keymap, invalid_keys) = Keymap::new(&user_config.keys);
+        projects.retain(|project| !state_store.ignored.contains(&project.project_path));
+        let preview_show_hidden = user_config.preview_show_hidden;
+        let sort_reverse = user_config.sort_reverse;
+        let sort_by = user_config.sort_by;
+        let absolute_paths = user_config.absolute_paths;
+        // Projects from a source command or ghq don't belong to any root.
+        let mut roots: Vec<PathBuf> = Vec::new();
+        for root in user_config
+            .project_dirs
+            .iter()
+            .filter(|_| user_config.project_source_command.is_none() && !user_config.use_ghq)
+            .map(|dir| parse_dir(dir.path()))
+        {
+            if !roots.contains(&root) {
+                roots.push(root);
+            }
         }
 
-        Ok(Self {
-            filtered_projects,
-            state,
+        let mut app = Self {
+            filtered_projects: Vec::new(),
+            state: ListState::default(),
             input: Input::default(),
             input_mode: InputMode::default(),
+            matcher: matcher::backend(user_config.match_backend),
             user_config,
             projects,
             exit: false,
-            open_target: None,
+            open_targets: Vec::new(),
+            print_selection: cli.print,
+            launch_mode: OpenMode::default(),
+            foreground_launches: Vec::new(),
+            launch_query: None,
+            shell_request: None,
+            action_request: None,
             readme_cache: HashMap::new(),
-            sort_state: SortState::default(),
+            word_counts: HashMap::new(),
+            preview_paths_cache: HashMap::new(),
+            preview_project: None,
+            preview_index: 0,
+            preview_scroll: 0,
+            size_cache: HashMap::new(),
+            tree_cache: HashMap::new(),
+            recent_files_cache: HashMap::new(),
+            content_matches: HashMap::new(),
+            content_term: None,
+            content_worker: None,
+            scores: HashMap::new(),
+            match_indices: HashMap::new(),
+            git_status_cache: HashMap::new(),
+            git_status_worker: None,
+            git_status_wanted: Vec::new(),
+            git_status_wanted_at: Instant::now(),
+            git_status_requested: HashSet::new(),
+            language_cache: HashMap::new(),
+            diff_stat_cache: HashMap::new(),
+            manifest_cache: HashMap::new(),
+            no_preview_cache: HashMap::new(),
+            preview_locked_path: None,
+            activity_cache: HashMap::new(),
+            metadata_cache: HashMap::new(),
+            git_descriptions: HashMap::new(),
+            preview_mode: PreviewMode::default(),
+            absolute_paths,
+            preview_show_hidden,
+            sort_state: match sort_by {
+                SortBy::Frecency => SortState::Frecent,
+                SortBy::Name => SortState::Alphabetical,
+                SortBy::Modified => SortState::RecentlyModified,
+                SortBy::Committed => SortState::RecentlyCommitted,
+            },
+            commit_time_cache: HashMap::new(),
+            commit_time_worker: None,
+            commit_time_requested: HashSet::new(),
+            branch_cache: HashMap::new(),
+            commit_subject_cache: HashMap::new(),
+            roots,
+            root_index: None,
+            history_mode: false,
+            stashed_projects: Vec::new(),
+            missing_projects: HashSet::new(),
+            sort_reverse,
+            state_store,
+            history_index: None,
             show_help: false,
-        })
+            help_scroll: 0,
+            show_all: false,
+            dirty_only: false,
+            no_readme_only: false,
+            has_readme_cache: HashMap::new(),
+            match_count: 0,
+            uncapped_query: None,
+            scan_errors,
+            show_scan_errors: false,
+            info: None,
+            status: None,
+            last_scan: Instant::now(),
+            scan_worker,
+            pending_action: None,
+            new_project: None,
+            preview_cursor: None,
+            preview_folds: None,
+            frozen_selection: None,
+            recent_selection: None,
+            branch_picker: None,
+            marked: Vec::new(),
+            leader_pending: false,
+            rename: None,
+            tag_editor: None,
+            keymap,
+            add_dir: None,
+            motion_streak: None,
+            select_hook: SelectHook {
+                seen: None,
+                changed_at: Instant::now(),
+                last_run: None,
+                running: None,
+            },
+            last_archive: None,
+        };
+
+        if let Some(query) = &cli.query {
+            app.input = Input::new(query.clone());
+        }
+        app.apply_sort();
+        app.filter_results();
+        if app.user_config.start_mode == StartMode::Search {
+            app.start_editing();
+        }
+        if app.scan_worker.is_none() {
+            app.finish_first_scan();
+        }
+        if !invalid_keys.is_empty() {
+            app.set_error(format!(
+                "unknown keys in [keys]: {}",
+                invalid_keys.join(", ")
+            ));
+        }
+        app
     }
 
-    pub fn should_exit(&self) -> bool {
-        self.exit
+    /// What follows the startup scan: recording what it found, falling
+    /// back when it found nothing, and reporting scan errors.
+    fn finish_first_scan(&mut self) {
+        self.record_seen_projects();
+        if self.projects.is_empty() {
+            self.fall_back_on_empty();
+        }
+        if !self.scan_errors.is_empty() {
+            self.set_error(format!(
+                "{} scan errors (Ctrl-e to view)",
+                self.scan_errors.len()
+            ));
+        }
     }
 
-    pub fn exit(&mut self) {
-        self.exit = true;
+    /// Whether a background scan is running.
+    pub fn scanning(&self) -> bool {
+        self.scan_worker.is_some()
     }
 
-    pub fn start_editing(&mut self) {
-        self.input_mode = InputMode::Editing;
+    /// How long the current scan has been running, for the spinner.
+    pub fn scan_elapsed(&self) -> Duration {
+        self.last_scan.elapsed()
     }
 
-    pub fn stop_editing(&mut self) {
-        self.input_mode = InputMode::Normal;
+    /// Takes in what the background scan has sent since the last tick.
+    /// The startup scan adds projects as they are found; a rescan keeps the
+    /// old list until it has the whole new one. Returns whether the list
+    /// changed.
+    fn receive_scan(&mut self) -> bool {
+        let Some((worker, kind)) = &self.scan_worker else {
+            return false;
+        };
+        let kind = *kind;
+        let mut found = Vec::new();
+        let mut finished = None;
+        for update in worker.updates() {
+            match update {
+                ScanUpdate::Found(projects) => found.extend(projects),
+                ScanUpdate::Finished(projects, errors) => finished = Some((projects, errors)),
+            }
+        }
+
+        let Some((projects, errors)) = finished else {
+            if found.is_empty() || !matches!(kind, ScanKind::Startup { cached: false }) {
+                return false;
+            }
+            let selected = self
+                .selected_project()
+                .map(|project| project.project_path.clone());
+            let ignored = &self.state_store.ignored;
+            let projects = if self.history_mode {
+                &mut self.stashed_projects
+            } else {
+                &mut self.projects
+            };
+            projects.extend(
+                found
+                    .into_iter()
+                    .filter(|project| !ignored.contains(&project.project_path)),
+            );
+            self.apply_sort();
+            self.filter_results();
+            self.reselect(selected);
+            return true;
+        };
+
+        log::debug!("background scan took {:?}", self.last_scan.elapsed());
+        self.scan_worker = None;
+        self.last_scan = Instant::now();
+        let count = self.apply_scan(projects, errors);
+        match kind {
+            ScanKind::Startup { .. } => self.finish_first_scan(),
+            ScanKind::Manual => self.report_rescan(count),
+            ScanKind::Auto { before } if count != before => {
+                self.set_status(format!("Rescanned ({count} projects)"))
+            }
+            ScanKind::Auto { .. } => {}
+        }
+        true
     }
 
-    pub fn filter_results(&mut self) {
-        let query = self.input.value().to_lowercase();
+    /// Handles a scan that found nothing, as set by `on_empty`. The list
+    /// explains how to add projects either way.
+    fn fall_back_on_empty(&mut self) {
+        match self.user_config.on_empty {
+            OnEmpty::Message => {}
+            OnEmpty::ScanHome => {
+                self.scan_extra_dir("~".to_string());
+                if !self.projects.is_empty() {
+                    self.set_status(format!(
+                        "Nothing in project_dirs, showing {} repositories in ~",
+                        self.projects.len()
+                    ));
+                }
+            }
+            OnEmpty::PromptAdd => self.add_dir = Some(Input::default()),
+        }
+    }
 
-        if query.is_empty() {
-            self.filtered_projects = self.projects.clone();
-        } else {
-            self.filtered_projects = self
-                .projects
+    /// Scans `dir` as an extra `project_dirs` entry for this session, in
+    /// place of a source command or ghq that came up empty.
+    fn scan_extra_dir(&mut self, dir: String) {
+        let root = parse_dir(&dir);
+        if !self.roots.contains(&root) {
+            self.roots.push(root);
+        }
+        self.user_config.project_source_command = None;
+        self.user_config.use_ghq = false;
+        self.user_config.project_dirs.push(ProjectDir::Path(dir));
+        self.rescan_projects();
+    }
+
+    /// Scans the directory typed into the add prompt for this session. The
+    /// config file is left alone, since rewriting it would lose its comments
+    /// and layout, so the status says where to add it for good.
+    pub fn submit_add_dir(&mut self) {
+        let Some(input) = self.add_dir.take() else {
+            return;
+        };
+        let dir = input.value().trim().to_string();
+        if dir.is_empty() {
+            return;
+        }
+        if !parse_dir(&dir).is_dir() {
+            self.add_dir = Some(input);
+            return self.set_error(format!("{dir} is not a directory"));
+        }
+
+        self.scan_extra_dir(dir.clone());
+        match config_path() {
+            Some(path) => self.set_status(format!(
+                "Scanning {dir} for this session; add it to project_dirs in {} to keep it",
+                path.display()
+            )),
+            None => self.set_status(format!("Scanning {dir} for this session")),
+        }
+    }
+
+    pub fn cancel_add_dir(&mut self) {
+        self.add_dir = None;
+    }
+
+    /// Adds newly discovered projects to the all-time history.
+    fn record_seen_projects(&mut self) {
+        let seen = &mut self.state_store.seen_projects;
+        let known: HashSet<PathBuf> = seen.iter().cloned().collect();
+        let before = seen.len();
+        for project in &self.projects {
+            if !known.contains(&project.project_path) {
+                seen.push(project.project_path.clone());
+            }
+        }
+
+        if seen.len() != before {
+            let _ = save_state(&self.state_store);
+        }
+    }
+
+    /// Switches between the live scan and every project ever discovered.
+    pub fn toggle_history_mode(&mut self) {
+        if !self.history_mode {
+            self.stashed_projects = self
+                .state_store
+                .seen_projects
                 .iter()
-                .filter(|project| {
-                    project
-                        .project_name
-                        .to_string_lossy()
-                        .to_lowercase()
-                        .contains(&query)
-                })
                 .cloned()
+                .map(history_project)
+                .collect();
+            self.missing_projects = self
+                .stashed_projects
+                .iter()
+                .filter(|project| !project.project_path.is_dir())
+                .map(|project| project.project_path.clone())
                 .collect();
         }
 
-        if self.filtered_projects.is_empty() {
-            self.state.select(None);
-        } else {
-            self.state.select_first();
-        }
+        std::mem::swap(&mut self.projects, &mut self.stashed_projects);
+        self.history_mode = !self.history_mode;
+        self.show_all = false;
+        self.apply_sort();
+        self.filter_results();
     }
 
-    pub fn open_selected_project(&mut self) {
-        self.open_target = self
-            .selected_project()
-            .map(|project| project.project_path.clone());
-        self.exit = true;
+    pub fn history_mode(&self) -> bool {
+        self.history_mode
     }
 
-    pub fn selected_project(&self) -> Option<&Project> {
-        self.state
-            .selected()
-            .and_then(|index| self.filtered_projects.get(index))
+    /// Whether a history entry's directory is gone.
+    pub fn is_missing(&self, project: &Project) -> bool {
+        self.history_mode && self.missing_projects.contains(&project.project_path)
+    }
+
+    /// Drops a path from the all-time history.
+    fn forget_project(&mut self, path: PathBuf) {
+        self.state_store.seen_projects.retain(|seen| *seen != path);
+        if let Err(err) = save_state(&self.state_store) {
+            self.set_error(format!("failed to save history: {err}"));
+            return;
+        }
+
+        self.remove_project(&path);
+        self.set_status(format!("Removed {} from history", path.display()));
     }
 
-    pub fn take_open_target(&mut self) -> Option<PathBuf> {
-        self.open_target.take()
+    /// How long to wait for input before ticking. Shorter while git status
+    /// is on its way, so placeholders fill in soon after it arrives.
+    pub fn tick_rate(&self) -> Duration {
+        let tick_rate = Duration::from_millis(self.user_config.tick_rate_ms);
+        let hook = &self.select_hook;
+        if self.git_status_wanted.is_empty()
+            && self.git_status_requested.is_empty()
+            && self.commit_time_requested.is_empty()
+            && hook.seen == hook.last_run
+            && self.scan_worker.is_none()
+        {
+            tick_rate
+        } else {
+            tick_rate.min(GIT_STATUS_POLL)
+        }
     }
 
-    pub fn editor_command(&self) -> &str {
-        &self.user_config.editor_command
+    /// Advances time-based state. Returns whether anything visible changed.
+    pub fn on_tick(&mut self) -> bool {
+        let scanned = self.receive_scan();
+        let received = self.receive_git_status();
+        if received && self.dirty_only {
+            let selected = self
+                .selected_project()
+                .map(|project| project.project_path.clone());
+            self.filter_results();
+            self.reselect(selected);
+        }
+        let searched = self.receive_content_matches();
+        let sorted = self.receive_commit_times();
+        self.request_git_status();
+        self.run_on_select_command();
+        // While scanning or searching, every tick moves the spinner.
+        if scanned
+            || received
+            || searched
+            || sorted
+            || self.scanning()
+            || self.content_worker.is_some()
+        {
+            return true;
+        }
+
+        if self.auto_rescan_due() {
+            let before = self.project_count();
+            self.scan_in_background(ScanKind::Auto { before });
+            return true;
+        }
+
+        if self
+            .status
+            .as_ref()
+            .is_some_and(|status| status.set_at.elapsed() >= STATUS_TIMEOUT)
+        {
+            self.status = None;
+            return true;
+        }
+
+        false
     }
 
-    pub fn open_project_remote(&self) {
-        let Some(project) = self.selected_project() else {
+    /// Runs the `on_select` hook, or `on_select_command`, for the selected
+    /// project once the selection has settled, waiting for the previous run
+    /// to exit first.
+    fn run_on_select_command(&mut self) {
+        let Some(template) = self
+            .user_config
+            .hooks
+            .on_select
+            .as_ref()
+            .or(self.user_config.on_select_command.as_ref())
+        else {
             return;
         };
+        let query = self.search_query().unwrap_or_default();
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
 
-        let remote = normalize_url(&project.project_remote);
-        if remote.is_empty() {
+        let hook = &mut self.select_hook;
+        if selected != hook.seen {
+            hook.seen = selected;
+            hook.changed_at = Instant::now();
+        }
+        if hook.seen == hook.last_run || hook.changed_at.elapsed() < ON_SELECT_DEBOUNCE {
             return;
         }
+        if let Some(child) = &mut hook.running {
+            if let Ok(None) = child.try_wait() {
+                return;
+            }
+            hook.running = None;
+        }
 
-        if let Err(err) = open::that(&remote) {
-            eprintln!("failed to open remote '{remote}': {err}");
+        hook.last_run = hook.seen.clone();
+        let Some(path) = &hook.seen else {
+            return;
+        };
+        match launch::spawn_hook(template, Some(path), &query) {
+            Ok(child) => hook.running = Some(child),
+            Err(err) => {
+                let message = format!("on_select hook failed: {err}");
+                self.set_error(message);
+            }
         }
     }
 
-    pub fn selected_readme(&mut self) -> Option<&str> {
-        let project = self.selected_project()?;
-        let readme_path = project.project_path.join("README.md");
-
-        match self.readme_cache.entry(readme_path) {
-            Entry::Occupied(entry) => entry.into_mut().as_deref(),
-            Entry::Vacant(entry) => {
-                let contents = std::fs::read_to_string(entry.key()).ok();
-                entry.insert(contents).as_deref()
+    /// Starts the `[hooks]` command for `event` without waiting for it. A
+    /// thread reaps it, so finished hooks don't linger as zombies.
+    fn run_hook(&mut self, event: &str, template: Option<String>, path: Option<&Path>) {
+        let Some(template) = template else {
+            return;
+        };
+        let query = self.search_query().unwrap_or_default();
+        match launch::spawn_hook(&template, path, &query) {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+            }
+            Err(err) => {
+                log::warn!("{event} hook failed: {err}");
+                self.set_error(format!("{event} hook failed: {err}"));
             }
         }
     }
 
-    pub fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
+    pub fn run_start_hook(&mut self) {
+        let template = self.user_config.hooks.on_start.clone();
+        self.run_hook("on_start", template, None);
     }
 
-    pub fn sort_label(&self) -> &str {
-        match self.sort_state {
-            SortState::Alphabetical => "A-Z",
-            SortState::RecentlyModified => "Recent",
+    /// Runs the `on_quit` hook for the project selected when `pl` exits.
+    pub fn run_quit_hook(&mut self) {
+        let template = self.user_config.hooks.on_quit.clone();
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+        self.run_hook("on_quit", template, selected.as_deref());
+    }
+
+    /// Whether `auto_rescan_secs` have passed since the last scan. Waits
+    /// while a prompt is open, since those hold on to projects by path.
+    fn auto_rescan_due(&self) -> bool {
+        let interval = self.user_config.auto_rescan_secs;
+        interval > 0
+            && self.last_scan.elapsed() >= Duration::from_secs(interval)
+            && self.scan_worker.is_none()
+            && self.pending_action.is_none()
+            && self.new_project.is_none()
+            && self.rename.is_none()
+            && self.tag_editor.is_none()
+            && self.add_dir.is_none()
+            && self.recent_selection.is_none()
+            && self.branch_picker.is_none()
+    }
+
+    fn project_count(&self) -> usize {
+        if self.history_mode {
+            self.stashed_projects.len()
+        } else {
+            self.projects.len()
         }
     }
 
-    pub fn next_sort(&mut self) {
-        match self.sort_state {
-            SortState::Alphabetical => {
-                self.sort_state = SortState::RecentlyModified;
-                self.projects.sort_by(|a, b| {
-                    let a_modified = a.project_path.metadata().and_then(|m| m.modified()).ok();
-                    let b_modified = b.project_path.metadata().and_then(|m| m.modified()).ok();
+    /// The current status message and whether it reports an error.
+    pub fn status(&self) -> Option<(&str, bool)> {
+        self.status
+            .as_ref()
+            .map(|status| (status.message.as_str(), status.is_error))
+    }
 
-                    b_modified.cmp(&a_modified)
-                });
-            }
-            SortState::RecentlyModified => {
-                self.sort_state = SortState::Alphabetical;
-                self.projects
-                    .sort_by(|a, b| a.project_name.cmp(&b.project_name));
-            }
+    pub fn set_status(&mut self, message: String) {
+        self.status = Some(Status {
+            message,
+            is_error: false,
+            set_at: Instant::now(),
+        });
+    }
+
+    pub fn set_error(&mut self, message: String) {
+        self.status = Some(Status {
+            message,
+            is_error: true,
+            set_at: Instant::now(),
+        });
+    }
+
+    pub fn clear_status(&mut self) {
+        self.status = None;
+    }
+
+    pub fn should_exit(&self) -> bool {
+        self.exit
+    }
+
+    /// Quits without launching anything. Opening goes through `launch_paths`,
+    /// which sets the launch targets and exits in one step, so a quit never
+    /// picks up a project requested earlier.
+    pub fn exit(&mut self) {
+        self.open_targets.clear();
+        self.foreground_launches.clear();
+        self.exit = true;
+    }
+
+    pub fn start_editing(&mut self) {
+        self.input_mode = InputMode::Editing;
+    }
+
+    pub fn stop_editing(&mut self) {
+        self.record_search();
+        let _ = save_state(&self.state_store);
+        self.input_mode = InputMode::Normal;
+
+        if self.user_config.collapse_input && self.user_config.collapse_clears_query {
+            self.input = Input::default();
+            self.filter_results();
+        }
+    }
+
+    /// Whether the search box is hidden: `collapse_input` is set and the
+    /// user isn't typing.
+    pub fn input_collapsed(&self) -> bool {
+        self.user_config.collapse_input && self.input_mode == InputMode::Normal
+    }
+
+    /// Inserts pasted text into the search at the cursor, dropping newlines
+    /// and other control characters, and filters once. Pasting outside of
+    /// search starts a search; pastes into an open popup are ignored.
+    pub fn paste(&mut self, text: &str) {
+        if self.show_help
+            || self.new_project.is_some()
+            || self.rename.is_some()
+            || self.tag_editor.is_some()
+            || self.add_dir.is_some()
+            || self.recent_selection.is_some()
+            || self.branch_picker.is_some()
+            || self.leader_pending
+            || self.preview_cursor.is_some()
+            || self.show_scan_errors
+            || self.info.is_some()
+            || self.pending_action.is_some()
+        {
+            return;
         }
 
+        self.start_editing();
+        for ch in text.chars().filter(|ch| !ch.is_control()) {
+            self.input.handle(InputRequest::InsertChar(ch));
+        }
+        self.show_all = false;
+        self.stop_browsing_history();
         self.filter_results();
     }
-}
 
-fn normalize_url(value: &str) -> String {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return String::new();
+    /// History is navigated from an empty input, and keeps being navigated
+    /// until the user types something.
+    pub fn browsing_history(&self) -> bool {
+        self.history_index.is_some() || self.input.value().is_empty()
     }
 
-    if trimmed.contains("://") {
-        return trimmed.to_string();
+    /// Whether an entry from the history is in the input. Only then does
+    /// `Ctrl-n` step forward through it; otherwise it moves the selection.
+    pub fn in_history(&self) -> bool {
+        self.history_index.is_some()
     }
 
-    format!("https://{trimmed}")
+    pub fn history_previous(&mut self) {
+        let history = &self.state_store.search_history;
+        if history.is_empty() {
+            return;
+        }
+
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None => history.len() - 1,
+        };
+        self.history_index = Some(index);
+        self.input = Input::new(history[index].clone());
+        self.filter_results();
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+
+        if let Some(query) = self.state_store.search_history.get(index + 1) {
+            self.history_index = Some(index + 1);
+            self.input = Input::new(query.clone());
+        } else {
+            self.history_index = None;
+            self.input = Input::default();
+        }
+        self.filter_results();
+    }
+
+    pub fn stop_browsing_history(&mut self) {
+        self.history_index = None;
+    }
+
+    fn record_search(&mut self) {
+        const HISTORY_LIMIT: usize = 50;
+
+        self.history_index = None;
+        let query = self.input.value().trim();
+        if query.is_empty() {
+            return;
+        }
+
+        let history = &mut self.state_store.search_history;
+        history.retain(|entry| entry != query);
+        history.push(query.to_string());
+        if history.len() > HISTORY_LIMIT {
+            history.drain(..history.len() - HISTORY_LIMIT);
+        }
+    }
+
+    /// Filters projects by the query. A query starting with `>` searches the
+    /// contents of each project's preview file instead of its name, and one
+    /// starting with `#` keeps projects with a matching `pl.tags` tag.
+    pub fn filter_results(&mut self) {
+        let previous = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+        let selected = previous
+            .clone()
+            .filter(|_| self.user_config.sticky_selection);
+        let query = self.input.value().to_lowercase();
+        if !query.starts_with('>') {
+            self.stop_content_search();
+        }
+        self.scores.clear();
+        self.match_indices.clear();
+
+        if let Some(term) = query.strip_prefix('>') {
+            self.content_search(term.trim());
+        } else if let Some(tag) = query.strip_prefix('#') {
+            self.tag_search(tag.trim());
+        } else if query.is_empty() {
+            self.filtered_projects = self.projects.clone();
+        } else {
+            let raw = self.input.value();
+            let ignore_case = matcher::ignores_case(self.user_config.case, raw);
+            let query = if ignore_case { query } else { raw.to_string() };
+            let normalize = self.user_config.normalize_diacritics;
+            let query = if normalize {
+                matcher::fold_diacritics(&query)
+            } else {
+                query
+            };
+
+            if self.user_config.use_git_description {
+                for project in &self.projects {
+                    self.git_descriptions
+                        .entry(project.project_path.clone())
+                        .or_insert_with_key(|path| git::description(path));
+                }
+            }
+
+            let mut scored: Vec<(i64, &Project)> = self
+                .projects
+                .iter()
+                .filter_map(|project| {
+                    let mut text = project.search_text(self.user_config.search_scope);
+                    if let Some(Some(description)) =
+                        self.git_descriptions.get(&project.project_path)
+                    {
+                        text = format!("{text} {description}");
+                    }
+                    if normalize && ignore_case {
+                        text = matcher::fold_diacritics(&text.to_lowercase());
+                    } else if normalize {
+                        text = matcher::fold_diacritics(&text);
+                    }
+                    let score = matcher::score_ignoring_separators(
+                        self.matcher.as_ref(),
+                        &query,
+                        &text,
+                        ignore_case,
+                    )?;
+                    Some((score, project))
+                })
+                .collect();
+
+            // Highlighting needs the query matched against the name alone;
+            // a match that spans the remote or description, or only works
+            // with separators removed, highlights nothing.
+            for (_, project) in &scored {
+                let name = project.project_name.to_string_lossy();
+                let mut text = name.to_string();
+                if normalize && ignore_case {
+                    text = matcher::fold_diacritics(&text.to_lowercase());
+                } else if normalize {
+                    text = matcher::fold_diacritics(&text);
+                }
+                // Folding can turn one character into two, after which the
+                // indices no longer line up with the name.
+                if text.chars().count() != name.chars().count() {
+                    continue;
+                }
+                if let Some((_, indices)) = self.matcher.score(&query, &text, ignore_case) {
+                    self.match_indices
+                        .insert(project.project_path.clone(), indices);
+                }
+            }
+
+            // Equal scores prefer the project opened more, then the shorter,
+            // more specific name, then alphabetical order, so results are
+            // stable across runs.
+            if let FilterSort::Score = self.user_config.filter_sort {
+                let now = unix_now();
+                let frecency = |project: &Project| {
+                    frecency(&self.state_store, &project.project_path, now).unwrap_or(0)
+                };
+                let name_len =
+                    |project: &Project| project.project_name.to_string_lossy().chars().count();
+                scored.sort_by(|(a_score, a), (b_score, b)| {
+                    b_score
+                        .cmp(a_score)
+                        .then_with(|| frecency(b).cmp(&frecency(a)))
+                        .then_with(|| name_len(a).cmp(&name_len(b)))
+                        .then_with(|| natural_cmp(&a.project_name, &b.project_name))
+                });
+            }
+
+            self.filtered_projects = scored
+                .iter()
+                .map(|(_, project)| (*project).clone())
+                .collect();
+            self.scores = scored
+                .into_iter()
+                .map(|(score, project)| (project.project_path.clone(), score))
+                .collect();
+        }
+
+        // History entries don't know their root, so scoping doesn't apply.
+        if let Some(root) = self.current_root().filter(|_| !self.history_mode) {
+            let root = root.to_path_buf();
+            self.filtered_projects
+                .retain(|project| project.project_root.as_ref() == Some(&root));
+        }
+
+        // Statuses still on their way are left out until they arrive, and
+        // `on_tick` filters again as each batch comes in.
+        if self.dirty_only {
+            self.request_git_status_for_filter();
+            let cache = &self.git_status_cache;
+            self.filtered_projects.retain(|project| {
+                matches!(cache.get(&project.project_path), Some(Some(status)) if status.dirty)
+            });
+        }
+
+        if self.no_readme_only {
+            let files = &self.user_config.preview_files;
+            let cache = &mut self.has_readme_cache;
+            self.filtered_projects.retain(|project| {
+                let has_readme = cache
+                    .entry(project.project_path.clone())
+                    .or_insert_with_key(|path| {
+                        files.iter().any(|file| find_file(path, file).is_some())
+                    });
+                !*has_readme
+            });
+        }
+
+        group_projects(
+            &mut self.filtered_projects,
+            &self.user_config.group_order,
+            &self.state_store,
+        );
+        self.match_count = self.filtered_projects.len();
+        if self
+            .uncapped_query
+            .as_ref()
+            .is_some_and(|query| query != self.input.value())
+        {
+            self.uncapped_query = None;
+        }
+        if let Some(max_results) = self.user_config.max_results
+            && self.uncapped_query.is_none()
+        {
+            self.filtered_projects.truncate(max_results);
+        }
+
+        if self.user_config.keep_selection_on_empty {
+            // With nothing matching, remember what was selected and put the
+            // cursor back on it once the query matches again.
+            if self.filtered_projects.is_empty() {
+                if previous.is_some() {
+                    self.frozen_selection = previous;
+                }
+                self.state.select(None);
+                return;
+            }
+            if let Some(frozen) = self.frozen_selection.take() {
+                return self.reselect(Some(frozen));
+            }
+        }
+
+        self.reselect(selected);
+    }
+
+    /// Lists the projects the content search has matched so far, starting
+    /// it over in the background when the term has changed. Matches arrive
+    /// on later ticks, each refiltering the list.
+    fn content_search(&mut self, term: &str) {
+        if self
+            .content_term
+            .as_ref()
+            .map(|(current, _)| current.as_str())
+            != Some(term)
+        {
+            self.content_matches.clear();
+            let files = self
+                .user_config
+                .preview_files
+                .iter()
+                .chain(&self.user_config.doc_files)
+                .cloned()
+                .collect();
+            self.content_worker = Some(ContentSearchWorker::spawn(
+                term.to_string(),
+                self.projects
+                    .iter()
+                    .map(|project| project.project_path.clone())
+                    .collect(),
+                files,
+                self.user_config.content_search_source,
+                self.user_config.content_search_max_kb * 1024,
+            ));
+            self.content_term = Some((term.to_string(), Instant::now()));
+        }
+
+        let matches = &self.content_matches;
+        self.filtered_projects = self
+            .projects
+            .iter()
+            .filter(|project| matches.contains_key(&project.project_path))
+            .cloned()
+            .collect();
+    }
+
+    fn stop_content_search(&mut self) {
+        self.content_worker = None;
+        self.content_term = None;
+        self.content_matches.clear();
+    }
+
+    /// Takes in the matches the content search has found since the last
+    /// tick.
+    fn receive_content_matches(&mut self) -> bool {
+        let Some(worker) = &self.content_worker else {
+            return false;
+        };
+
+        // Checked first, so every match sent before it finished is taken.
+        let done = worker.done();
+        let found: Vec<_> = worker.matches().collect();
+        if done {
+            self.content_worker = None;
+        }
+        if found.is_empty() {
+            return done;
+        }
+        self.content_matches.extend(found);
+        self.filter_results();
+        true
+    }
+
+    /// Whether a content search is still running, and for how long, for the
+    /// spinner.
+    pub fn content_search_elapsed(&self) -> Option<Duration> {
+        self.content_worker.as_ref()?;
+        Some(self.content_term.as_ref()?.1.elapsed())
+    }
+
+    /// Starts a content search with the query typed so far, or turns one
+    /// back into a name search.
+    pub fn toggle_content_search(&mut self) {
+        let query = self.input.value();
+        self.input = match query.strip_prefix('>') {
+            Some(term) => Input::new(term.trim_start().to_string()),
+            None => Input::new(format!(">{query}")),
+        };
+        self.filter_results();
+    }
+
+    /// Keeps projects with a tag starting with `prefix`. Reads the git config
+    /// of every project the first time, so it is only done on demand.
+    fn tag_search(&mut self, prefix: &str) {
+        let mut matches = Vec::new();
+        for project in self.projects.clone() {
+            if self
+                .project_tags(&project.project_path)
+                .iter()
+                .any(|tag| tag.starts_with(prefix))
+            {
+                matches.push(project);
+            }
+        }
+        self.filtered_projects = matches;
+    }
+
+    /// The project's `pl.tags` followed by the tags given to it with `#`.
+    fn project_tags(&mut self, project_path: &Path) -> Vec<String> {
+        let mut tags = self.metadata(project_path).tags.clone();
+        for tag in self
+            .state_store
+            .tags
+            .get(project_path)
+            .into_iter()
+            .flatten()
+        {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        tags
+    }
+
+    /// Opens the tag prompt for the selected project, filled in with the
+    /// tags it was given with `#` before.
+    pub fn start_tag_edit(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+        let tags = self
+            .state_store
+            .tags
+            .get(&path)
+            .map(|tags| tags.join(", "))
+            .unwrap_or_default();
+        self.tag_editor = Some(Input::new(tags));
+    }
+
+    /// Saves the typed tags, separated by commas or spaces, to the state
+    /// file. Clearing the prompt removes them.
+    pub fn submit_tag_edit(&mut self) {
+        let (Some(input), Some(path)) = (
+            self.tag_editor.take(),
+            self.selected_project()
+                .map(|project| project.project_path.clone()),
+        ) else {
+            return;
+        };
+
+        let mut tags: Vec<String> = Vec::new();
+        for tag in input
+            .value()
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .map(|tag| tag.trim_start_matches('#').to_lowercase())
+            .filter(|tag| !tag.is_empty())
+        {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        let status = if tags.is_empty() {
+            self.state_store.tags.remove(&path);
+            format!("Cleared the tags of {}", path.display())
+        } else {
+            let status = format!("Tagged {}", tags.join(", "));
+            self.state_store.tags.insert(path, tags);
+            status
+        };
+        if let Err(err) = save_state(&self.state_store) {
+            return self.set_error(format!("failed to save tags: {err}"));
+        }
+        self.set_status(status);
+        self.filter_results();
+    }
+
+    pub fn cancel_tag_edit(&mut self) {
+        self.tag_editor = None;
+    }
+
+    /// Steps the query through `#tag` searches for every tag in use, in
+    /// alphabetical order, and back to an empty query after the last one.
+    pub fn cycle_tag_filter(&mut self) {
+        let mut tags: Vec<String> = Vec::new();
+        for project in self.projects.clone() {
+            tags.extend(self.project_tags(&project.project_path));
+        }
+        tags.sort();
+        tags.dedup();
+        if tags.is_empty() {
+            return self.set_status("No projects are tagged".to_string());
+        }
+
+        let current = self.input.value().strip_prefix('#').map(str::trim);
+        let next = match current {
+            Some(current) => tags.iter().find(|tag| tag.as_str() > current),
+            None => tags.first(),
+        };
+        self.input = match next {
+            Some(tag) => Input::new(format!("#{tag}")),
+            None => Input::default(),
+        };
+        self.filter_results();
+    }
+
+    fn metadata(&mut self, project_path: &Path) -> &Metadata {
+        self.metadata_cache
+            .entry(project_path.to_path_buf())
+            .or_insert_with_key(|path| git::metadata(path))
+    }
+
+    /// A line about the previewed project's git state, like
+    /// `main ↑1 ↓2 · modified · 3h ago · Fix the parser`: the branch and how
+    /// far it is from its upstream, whether the working tree is clean, and
+    /// the last commit. The working-tree state comes from the background git
+    /// status worker and shows up once it has answered; the rest is read
+    /// once per project and kept until a rescan. `None` for projects that
+    /// aren't git repositories.
+    pub fn selected_git_header(&mut self) -> Option<String> {
+        let project_path = self.previewed_project()?.project_path.clone();
+        let branch = self
+            .branch_cache
+            .entry(project_path.clone())
+            .or_insert_with_key(|path| git::head_branch(path))
+            .clone()?;
+
+        let mut parts = vec![branch];
+        match self.git_status_cache.get(&project_path) {
+            Some(Some(status)) => {
+                if status.ahead > 0 {
+                    parts[0].push_str(&format!(" ↑{}", status.ahead));
+                }
+                if status.behind > 0 {
+                    parts[0].push_str(&format!(" ↓{}", status.behind));
+                }
+                parts.push(if status.dirty { "modified" } else { "clean" }.to_string());
+            }
+            Some(None) => {}
+            None if self.git_status_requested.contains(&project_path) => {}
+            None => {
+                if !self.git_status_wanted.contains(&project_path) {
+                    self.git_status_wanted.push(project_path.clone());
+                    self.git_status_wanted_at = Instant::now();
+                }
+            }
+        }
+
+        let committed = *self
+            .commit_time_cache
+            .entry(project_path.clone())
+            .or_insert_with_key(|path| git::last_commit_time(path));
+        if let Some(secs) = committed {
+            parts.push(format_age(UNIX_EPOCH + Duration::from_secs(secs)));
+        }
+        let subject = self
+            .commit_subject_cache
+            .entry(project_path)
+            .or_insert_with_key(|path| git::last_commit_subject(path));
+        if let Some(subject) = subject {
+            parts.push(subject.clone());
+        }
+        Some(parts.join(" · "))
+    }
+
+    /// The description and tags of the selected project, formatted for the
+    /// preview title, or `None` when it has neither.
+    /// Without a `pl.description`, `.git/description` is shown when
+    /// `use_git_description` is on.
+    pub fn selected_metadata(&mut self) -> Option<String> {
+        let project_path = self.previewed_project()?.project_path.clone();
+        let git_description = if self.user_config.use_git_description {
+            self.git_descriptions
+                .entry(project_path.clone())
+                .or_insert_with_key(|path| git::description(path))
+                .clone()
+        } else {
+            None
+        };
+        let tags = self.project_tags(&project_path);
+        let metadata = self.metadata(&project_path);
+
+        let parts: Vec<String> = metadata
+            .description
+            .clone()
+            .or(git_description)
+            .into_iter()
+            .chain(tags.iter().map(|tag| format!("#{tag}")))
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+
+    /// The line of the shown preview that matched a content search, along
+    /// with the lowercased search term.
+    pub fn content_match(&self) -> Option<(usize, String)> {
+        if !matches!(self.preview_mode, PreviewMode::Readme) || self.preview_index != 0 {
+            return None;
+        }
+
+        let project = self.previewed_project()?;
+        let line = self.content_matches.get(&project.project_path)?.line;
+        let term = self.input.value().to_lowercase();
+        let term = term.strip_prefix('>')?.trim().to_string();
+        Some((line, term))
+    }
+
+    /// The match score of `project` for the active name search. `None`
+    /// without a query, for `>` and `#` searches, and for non-matches.
+    pub fn match_score(&self, project: &Project) -> Option<i64> {
+        self.scores.get(&project.project_path).copied()
+    }
+
+    /// The character indices of `project`'s name matched by the active name
+    /// search. Empty without a query, for `>` and `#` searches, and when
+    /// the match isn't within the name.
+    pub fn match_indices(&self, project: &Project) -> &[usize] {
+        self.match_indices
+            .get(&project.project_path)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The match score of `project` for the list annotation, if
+    /// `show_scores` is on.
+    pub fn score(&self, project: &Project) -> Option<i64> {
+        if !self.user_config.show_scores {
+            return None;
+        }
+        self.match_score(project)
+    }
+
+    /// The `project_dirs` root the list is scoped to, if any.
+    pub fn current_root(&self) -> Option<&Path> {
+        self.root_index.map(|index| self.roots[index].as_path())
+    }
+
+    /// Scopes the list to the next (or, with a negative `step`, previous)
+    /// `project_dirs` root, passing through the unscoped list in between.
+    pub fn cycle_root(&mut self, step: isize) {
+        if self.roots.len() < 2 {
+            return;
+        }
+
+        // Position 0 is the unscoped list, `i + 1` is root `i`.
+        let positions = self.roots.len() as isize + 1;
+        let position = self.root_index.map_or(0, |index| index as isize + 1);
+        let position = (position + step).rem_euclid(positions) as usize;
+        self.root_index = position.checked_sub(1);
+
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+        self.filter_results();
+        self.reselect(selected);
+    }
+
+    pub fn current_root_label(&self) -> Option<String> {
+        self.current_root()
+            .map(|root| display_path(root, self.absolute_paths))
+    }
+
+    /// The number of projects matching the query, before `max_results`, and
+    /// the number of projects overall.
+    pub fn match_counts(&self) -> (usize, usize) {
+        let total = self.projects.len();
+        if self.show_all {
+            (total, total)
+        } else {
+            (self.match_count, total)
+        }
+    }
+
+    /// How many projects matched before `max_results` was applied, when the
+    /// cap actually cut the list short.
+    pub fn truncated_from(&self) -> Option<usize> {
+        (!self.show_all && self.match_count > self.filtered_projects.len())
+            .then_some(self.match_count)
+    }
+
+    /// How many directories deep `project` sits below its `project_dirs`
+    /// root, for indenting the list with `show_hierarchy`. Zero otherwise.
+    pub fn hierarchy_depth(&self, project: &Project) -> usize {
+        if !self.user_config.show_hierarchy {
+            return 0;
+        }
+        project
+            .project_root
+            .as_ref()
+            .and_then(|root| project.project_path.strip_prefix(root).ok())
+            .map_or(0, |relative| {
+                relative.components().count().saturating_sub(1)
+            })
+    }
+
+    /// Every listed project in the current sort order, ignoring the query.
+    pub fn projects(&self) -> &[Project] {
+        &self.projects
+    }
+
+    /// Shows every match for the current query, past `max_results`. Typing
+    /// puts the cap back.
+    pub fn expand_results(&mut self) {
+        if self.truncated_from().is_none() {
+            return;
+        }
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+        self.uncapped_query = Some(self.input.value().to_string());
+        self.filter_results();
+        self.reselect(selected);
+    }
+
+    /// The projects currently shown in the list: the filtered set, or every
+    /// project while the filter is temporarily bypassed.
+    pub fn visible_projects(&self) -> &[Project] {
+        if self.show_all {
+            &self.projects
+        } else {
+            &self.filtered_projects
+        }
+    }
+
+    /// Narrows the list to repositories with uncommitted changes, on top of
+    /// the query, or goes back to showing every match.
+    pub fn toggle_dirty_only(&mut self) {
+        self.dirty_only = !self.dirty_only;
+        if self.dirty_only {
+            self.show_all = false;
+        }
+        self.filter_results();
+    }
+
+    pub fn dirty_only(&self) -> bool {
+        self.dirty_only
+    }
+
+    /// Narrows the list to projects with none of the `preview_files`, to
+    /// find the ones that still need a README, or shows every match again.
+    pub fn toggle_no_readme_only(&mut self) {
+        self.no_readme_only = !self.no_readme_only;
+        if self.no_readme_only {
+            self.show_all = false;
+        }
+        self.filter_results();
+    }
+
+    pub fn no_readme_only(&self) -> bool {
+        self.no_readme_only
+    }
+
+    /// Whether the dirty filter is still waiting on statuses, so the list
+    /// may grow as they arrive.
+    pub fn dirty_loading(&self) -> bool {
+        self.dirty_only && !self.git_status_requested.is_empty()
+    }
+
+    /// Hands every match not cached or on its way yet to the git status
+    /// worker, skipping the debounce since the filter needs all of them.
+    fn request_git_status_for_filter(&mut self) {
+        let check_stashes = self.user_config.show_stash_indicator;
+        let worker = self
+            .git_status_worker
+            .get_or_insert_with(|| GitStatusWorker::spawn(check_stashes));
+        for project in &self.filtered_projects {
+            let path = &project.project_path;
+            if !self.git_status_cache.contains_key(path)
+                && !self.git_status_requested.contains(path)
+            {
+                worker.request(path.clone());
+                self.git_status_requested.insert(path.clone());
+            }
+        }
+    }
+
+    /// Flips between the filtered and the full list without touching the query.
+    pub fn toggle_show_all(&mut self) {
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+        self.show_all = !self.show_all;
+        self.reselect(selected);
+    }
+
+    /// Selects the visible project at `path`, falling back to the first one.
+    fn reselect(&mut self, path: Option<PathBuf>) {
+        let index = path.and_then(|path| {
+            self.visible_projects()
+                .iter()
+                .position(|project| project.project_path == path)
+        });
+
+        if index.is_some() {
+            self.state.select(index);
+        } else if self.visible_projects().is_empty() {
+            self.state.select(None);
+        } else {
+            self.state.select_first();
+        }
+    }
+
+    // The list can end in a row that isn't a project (the `max_results`
+    // remainder), so moves are clamped to the projects here rather than
+    // left to the widget.
+    pub fn select_next(&mut self) {
+        let len = self.visible_projects().len();
+        if len > 0 {
+            let next = self.state.selected().map_or(0, |index| index + 1);
+            self.state.select(Some(next.min(len - 1)));
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let len = self.visible_projects().len();
+        if len > 0 {
+            let previous = self
+                .state
+                .selected()
+                .map_or(len - 1, |index| index.saturating_sub(1).min(len - 1));
+            self.state.select(Some(previous));
+        }
+    }
+
+    /// Moves the selection one row down (`direction` 1) or up (-1). With
+    /// `scroll_acceleration`, holding the key makes each step longer until
+    /// it is released for a moment.
+    pub fn move_selection(&mut self, direction: isize) {
+        let len = self.visible_projects().len();
+        let current = self
+            .state
+            .selected()
+            .filter(|_| len > 0 && self.user_config.scroll_acceleration);
+        let Some(current) = current else {
+            return if direction > 0 {
+                self.select_next()
+            } else {
+                self.select_previous()
+            };
+        };
+
+        let now = Instant::now();
+        let streak = match self.motion_streak {
+            Some((previous, at, streak))
+                if previous == direction && now.duration_since(at) < REPEAT_WINDOW =>
+            {
+                streak + 1
+            }
+            _ => 0,
+        };
+        self.motion_streak = Some((direction, now, streak));
+
+        let step = (1 + streak / ACCELERATION_RATE).min(MAX_STEP);
+        let target = if direction > 0 {
+            (current + step).min(len - 1)
+        } else {
+            current.saturating_sub(step)
+        };
+        self.state.select(Some(target));
+    }
+
+    pub fn select_first(&mut self) {
+        if !self.visible_projects().is_empty() {
+            self.state.select_first();
+        }
+    }
+
+    pub fn select_last(&mut self) {
+        let len = self.visible_projects().len();
+        if len > 0 {
+            self.state.select(Some(len - 1));
+        }
+    }
+
+    /// Opens the selected project and exits, or opens it and keeps running
+    /// with `stay_open`. Does nothing when there is no selection, e.g.
+    /// because the filter matched nothing. With projects marked, opens those
+    /// instead.
+    pub fn open_selected_project(&mut self) {
+        if !self.marked.is_empty() {
+            let paths: Vec<PathBuf> = std::mem::take(&mut self.marked)
+                .into_iter()
+                .filter(|path| path.is_dir())
+                .collect();
+            if !paths.is_empty() {
+                self.open_paths(paths);
+            }
+            return;
+        }
+
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        if !path.is_dir() {
+            if self.history_mode {
+                self.request(PendingAction::Forget(path));
+            } else {
+                self.set_error(format!("{} no longer exists", path.display()));
+                self.remove_project(&path);
+            }
+            return;
+        }
+
+        if self.user_config.pick_branch_on_open && !self.history_mode {
+            let branches = git::branches(&path);
+            if branches.len() > 1 {
+                self.branch_picker = Some(BranchPicker::new(path, branches));
+                return;
+            }
+        }
+        self.open_paths(vec![path]);
+    }
+
+    /// Lists the selected repository's branches to pick one to open.
+    pub fn start_branch_pick(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        let branches = git::branches(&path);
+        if branches.is_empty() {
+            return self.set_error(format!("{} has no branches", path.display()));
+        }
+        self.branch_picker = Some(BranchPicker::new(path, branches));
+    }
+
+    pub fn move_branch_selection(&mut self, step: isize) {
+        if let Some(picker) = &mut self.branch_picker {
+            let count = picker.branches.len() as isize;
+            picker.selected = (picker.selected as isize + step).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Opens the picked branch: the worktree that has it checked out, if
+    /// any, or else the repository after switching it to the branch.
+    pub fn open_picked_branch(&mut self) {
+        let Some(picker) = self.branch_picker.take() else {
+            return;
+        };
+        let branch = &picker.branches[picker.selected];
+        if let Some(worktree) = &branch.worktree {
+            return self.open_paths(vec![worktree.clone()]);
+        }
+
+        if !branch.current {
+            if let Err(err) = git::switch_branch(&picker.path, &branch.name) {
+                return self.set_error(format!("failed to switch to {}: {err}", branch.name));
+            }
+            // A worktree's tag names its branch.
+            if let Some(project) = self
+                .projects
+                .iter_mut()
+                .find(|project| project.project_path == picker.path && project.worktree.is_some())
+            {
+                project.worktree = Some(branch.name.clone());
+            }
+            self.git_status_cache.remove(&picker.path);
+            self.branch_cache.remove(&picker.path);
+            self.commit_subject_cache.remove(&picker.path);
+            self.commit_time_cache.remove(&picker.path);
+        }
+        self.open_paths(vec![picker.path]);
+    }
+
+    /// With `--select-1`: opens the project if exactly one matches the
+    /// starting query, so the TUI needn't start. Returns whether it did.
+    pub fn select_only_match(&mut self) -> bool {
+        if self.visible_projects().len() != 1 {
+            return false;
+        }
+        self.open_selected_project();
+        self.exit
+    }
+
+    /// Opens the `n`th project on screen (1-based), counting from the top of
+    /// the scrolled list. Does nothing when there are fewer rows, or when
+    /// `number_keys` is off.
+    pub fn open_nth(&mut self, n: usize) {
+        if !self.user_config.number_keys || n == 0 {
+            return;
+        }
+
+        let index = self.state.offset() + n - 1;
+        if index < self.visible_projects().len() {
+            self.state.select(Some(index));
+            self.open_selected_project();
+        }
+    }
+
+    /// Asks to open every project in the filtered list, refusing outright
+    /// past `open_all_limit`; `request` then confirms past
+    /// `confirm_open_all_over`.
+    pub fn request_open_all(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .visible_projects()
+            .iter()
+            .map(|project| project.project_path.clone())
+            .collect();
+
+        let limit = self.user_config.open_all_limit;
+        match paths.len() {
+            0 => {}
+            count if count > limit => self.set_error(format!(
+                "refusing to open {count} projects (open_all_limit is {limit})"
+            )),
+            _ => self.request(PendingAction::OpenAll(paths)),
+        }
+    }
+
+    /// Remembers `paths` as opened and hands them to the editor.
+    fn open_paths(&mut self, paths: Vec<PathBuf>) {
+        self.open_paths_as(paths, self.user_config.open_mode);
+    }
+
+    /// `open_paths`, but opening them the `mode` way rather than by
+    /// `open_mode`.
+    fn open_paths_as(&mut self, paths: Vec<PathBuf>, mode: OpenMode) {
+        self.launch_mode = mode;
+        self.record_search();
+        self.state_store.last_opened = paths.last().cloned();
+        let recent = &mut self.state_store.recently_opened;
+        for path in &paths {
+            recent.retain(|opened| opened != path);
+            recent.insert(0, path.clone());
+        }
+        recent.truncate(self.user_config.mru_size);
+        let now = unix_now();
+        for path in &paths {
+            self.state_store.opened_at.insert(path.clone(), now);
+            *self
+                .state_store
+                .open_counts
+                .entry(path.clone())
+                .or_default() += 1;
+        }
+        let _ = save_state(&self.state_store);
+        for path in &paths {
+            let template = self.user_config.hooks.on_open.clone();
+            self.run_hook("on_open", template, Some(path));
+        }
+        self.launch_paths(paths);
+    }
+
+    /// How long ago `path` was last opened from `pl`, such as `3h ago`.
+    fn opened_age(&self, path: &Path) -> Option<String> {
+        let secs = *self.state_store.opened_at.get(path)?;
+        Some(format_age(UNIX_EPOCH + Duration::from_secs(secs)))
+    }
+
+    /// The list annotation for when `project` was last opened, with
+    /// `show_last_opened`.
+    pub fn last_opened_label(&self, project: &Project) -> Option<String> {
+        if !self.user_config.show_last_opened {
+            return None;
+        }
+        self.opened_age(&project.project_path)
+    }
+
+    /// The most recently opened projects that still exist, newest first.
+    pub fn recent_projects(&self) -> Vec<&Path> {
+        self.state_store
+            .recently_opened
+            .iter()
+            .map(PathBuf::as_path)
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+
+    pub fn toggle_recent(&mut self) {
+        self.recent_selection = match self.recent_selection {
+            Some(_) => None,
+            None if self.recent_projects().is_empty() => {
+                return self.set_error("no recently opened projects yet".to_string());
+            }
+            None => Some(0),
+        };
+    }
+
+    pub fn move_recent_selection(&mut self, step: isize) {
+        let count = self.recent_projects().len() as isize;
+        if let Some(selected) = &mut self.recent_selection
+            && count > 0
+        {
+            *selected = (*selected as isize + step).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Opens the project picked from the recently opened list.
+    pub fn open_recent(&mut self) {
+        let Some(selected) = self.recent_selection.take() else {
+            return;
+        };
+        if let Some(path) = self.recent_projects().get(selected) {
+            let path = path.to_path_buf();
+            self.open_paths(vec![path]);
+        }
+    }
+
+    /// Opens the preview file shown for the selected project, such as its
+    /// README, in the editor rather than the whole project.
+    pub fn open_preview_file(&mut self) {
+        if self.previewed_project().is_none() {
+            return;
+        }
+
+        match self.shown_preview_file() {
+            Some(path) => self.launch_paths(vec![path]),
+            None => self.set_error("no README to edit (Enter opens the project)".to_string()),
+        }
+    }
+
+    /// Opens the directory containing the selected project, such as a client
+    /// folder holding several repos. Refuses the filesystem root.
+    pub fn open_selected_parent(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        match path.parent() {
+            Some(parent) if parent.parent().is_some() => {
+                let parent = parent.to_path_buf();
+                // A session opened earlier with `t` mustn't carry over.
+                self.launch_mode = self.user_config.open_mode;
+                self.launch_paths(vec![parent]);
+            }
+            _ => self.set_error(format!("{} has no parent to open", path.display())),
+        }
+    }
+
+    /// Waits for the key picking a leader action.
+    pub fn start_leader(&mut self) {
+        if self.selected_project().is_some() {
+            self.leader_pending = true;
+        }
+    }
+
+    /// Runs the leader action bound to `key`, preferring one from
+    /// `[[actions]]` over a built-in one.
+    pub fn run_leader_action(&mut self, key: char) {
+        self.leader_pending = false;
+        if let Some(action) = self
+            .user_config
+            .actions
+            .iter()
+            .find(|action| action.key == key)
+            .cloned()
+        {
+            self.run_custom_action(&action);
+        } else if let Some(action) = LEADER_ACTIONS.iter().find(|action| action.key == key) {
+            (action.run)(self);
+        }
+    }
+
+    /// Runs an `[[actions]]` command on the selected project: in the
+    /// background, or with `wait` by the TUI loop once it has stepped aside.
+    fn run_custom_action(&mut self, action: &Action) {
+        let Some(project) = self.selected_project() else {
+            return;
+        };
+        let name = project.project_name.to_string_lossy().into_owned();
+        let path = project.project_path.clone();
+
+        let mut command = match launch::action_command(&action.command, &path, &name) {
+            Ok(command) => command,
+            Err(err) => {
+                self.set_error(format!("{}: {err}", action.description));
+                return;
+            }
+        };
+        log::info!("running action {command:?}");
+        if action.wait {
+            self.action_request = Some((action.description.clone(), command));
+            return;
+        }
+
+        let spawned = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match spawned {
+            Ok(mut child) => {
+                thread::spawn(move || child.wait());
+                self.set_status(format!("Started {}", action.description));
+            }
+            Err(err) => self.set_error(format!("{}: {err}", action.description)),
+        }
+    }
+
+    pub fn take_action_request(&mut self) -> Option<(String, Command)> {
+        self.action_request.take()
+    }
+
+    /// Reports how a `wait` action ended, unless it succeeded.
+    pub fn action_finished(&mut self, description: &str, result: io::Result<ExitStatus>) {
+        match result {
+            Ok(status) if !status.success() => {
+                self.set_error(format!("{description} failed ({status})"));
+            }
+            Ok(_) => {}
+            Err(err) => self.set_error(format!("{description}: {err}")),
+        }
+    }
+
+    /// Copies the selected project's path to the clipboard.
+    pub fn copy_selected_path(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        match clipboard::copy(&path.to_string_lossy()) {
+            Ok(()) => self.set_status(format!("Copied {}", path.display())),
+            Err(err) => self.set_error(format!("failed to copy: {err}")),
+        }
+    }
+
+    /// Reveals the selected project in the system file manager.
+    pub fn reveal_selected(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        match launch::reveal(&path) {
+            Ok(()) => self.set_status(format!("Revealed {}", path.display())),
+            Err(err) => self.set_error(format!("failed to reveal: {err}")),
+        }
+    }
+
+    /// Asks the TUI loop to suspend and start a shell in the selected project.
+    pub fn open_shell(&mut self) {
+        self.shell_request = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+    }
+
+    pub fn take_shell_request(&mut self) -> Option<PathBuf> {
+        self.shell_request.take()
+    }
+
+    /// Reports a shell that couldn't be started. How it exited doesn't
+    /// matter, since the last command run in it decides the exit code.
+    pub fn shell_finished(&mut self, result: io::Result<ExitStatus>) {
+        if let Err(err) = result {
+            self.set_error(format!("failed to start shell: {err}"));
+        }
+    }
+
+    /// Hands `paths` to the editor. With `stay_open`, detached editors are
+    /// launched right away and waited-on ones by the TUI loop, which steps
+    /// aside while they run; otherwise they are opened after exiting.
+    fn launch_paths(&mut self, paths: Vec<PathBuf>) {
+        self.launch_query = self.search_query();
+        if !self.user_config.stay_open || self.print_selection {
+            self.open_targets = paths;
+            self.exit = true;
+        } else if self.user_config.editor_detach {
+            let config = self.user_config.clone();
+            let query = self.launch_query.clone();
+            let mut opened = true;
+            launch::open_projects(
+                &paths,
+                &config,
+                self.launch_mode,
+                query.as_deref(),
+                |path, result| {
+                    opened = self.editor_finished(path, result);
+                    opened
+                },
+            );
+            if opened && paths.len() > 1 {
+                self.set_status(format!("Opened {} projects", paths.len()));
+            }
+        } else {
+            self.foreground_launches = paths;
+        }
+    }
+
+    /// The search text to hand to `editor_query_template`: the query as
+    /// typed, or the term of a `>` content search. Tag searches and an empty
+    /// query have none.
+    fn search_query(&self) -> Option<String> {
+        let query = self.input.value().trim();
+        let query = query.strip_prefix('>').map_or(query, str::trim);
+        if query.is_empty() || query.starts_with('#') {
+            return None;
+        }
+        Some(query.to_string())
+    }
+
+    pub fn launch_query(&self) -> Option<&str> {
+        self.launch_query.as_deref()
+    }
+
+    /// How the projects being opened are opened: `open_mode`, or a session
+    /// for ones opened with `t`.
+    pub fn launch_mode(&self) -> OpenMode {
+        self.launch_mode
+    }
+
+    /// Opens the selected project in a tmux session, or a zellij one when
+    /// that is the `open_mode`.
+    pub fn open_session(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+        if !path.is_dir() {
+            self.set_error(format!("{} no longer exists", path.display()));
+            return;
+        }
+        let mode = self.user_config.open_mode.session();
+        self.open_paths_as(vec![path], mode);
+    }
+
+    /// Projects to open in an editor the TUI waits on, with `stay_open`.
+    pub fn take_foreground_launches(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.foreground_launches)
+    }
+
+    /// Reports how launching `path` went. An editor exiting with
+    /// `quit_on_exit_code` quits `pl`. Returns whether it went fine.
+    pub fn editor_finished(
+        &mut self,
+        path: &Path,
+        result: io::Result<Option<std::process::ExitStatus>>,
+    ) -> bool {
+        match result {
+            Ok(Some(status)) if !status.success() => {
+                if status.code().is_some() && status.code() == self.user_config.quit_on_exit_code {
+                    self.exit();
+                }
+                self.set_error(launch::describe_exit(status));
+                false
+            }
+            Ok(_) => {
+                self.set_status(format!("Opened {}", path.display()));
+                true
+            }
+            Err(err) => {
+                self.set_error(format!("failed to open {}: {err}", path.display()));
+                false
+            }
+        }
+    }
+
+    /// Asks to archive the selected project, if `archive_dir` is usable.
+    pub fn request_archive(&mut self) {
+        let Some(project) = self.selected_project().cloned() else {
+            return;
+        };
+
+        if let Err(err) = self.archive_dir() {
+            self.set_error(err);
+            return;
+        }
+
+        self.request(PendingAction::Archive(project));
+    }
+
+    /// Runs `action`, asking first when it needs confirming. With
+    /// `confirm_destructive` every action asks; without it only opening more
+    /// than `confirm_open_all_over` projects does.
+    fn request(&mut self, action: PendingAction) {
+        let needs_confirmation = self.user_config.confirm_destructive
+            || match &action {
+                PendingAction::Archive(_)
+                | PendingAction::Forget(_)
+                | PendingAction::Rename(..) => false,
+                PendingAction::OpenAll(paths) => {
+                    paths.len() > self.user_config.confirm_open_all_over
+                }
+            };
+
+        if needs_confirmation {
+            self.pending_action = Some(action);
+        } else {
+            self.run_action(action);
+        }
+    }
+
+    pub fn confirm_pending_action(&mut self) {
+        if let Some(action) = self.pending_action.take() {
+            self.run_action(action);
+        }
+    }
+
+    fn run_action(&mut self, action: PendingAction) {
+        match action {
+            PendingAction::Archive(project) => self.archive(project),
+            PendingAction::OpenAll(paths) => self.open_paths(paths),
+            PendingAction::Forget(path) => self.forget_project(path),
+            PendingAction::Rename(path, name) => self.rename_project(path, &name),
+        }
+    }
+
+    pub fn cancel_pending_action(&mut self) {
+        self.pending_action = None;
+    }
+
+    /// The configured archive directory. Refused when it lies inside a
+    /// `project_dirs` root, where archived projects would be rediscovered.
+    fn archive_dir(&self) -> Result<PathBuf, String> {
+        let archive_dir = self
+            .user_config
+            .archive_dir
+            .as_deref()
+            .map(parse_dir)
+            .ok_or("archive_dir is not set")?;
+
+        if self
+            .user_config
+            .project_dirs
+            .iter()
+            .any(|root| archive_dir.starts_with(parse_dir(root.path())))
+        {
+            return Err(format!(
+                "archive_dir {} is inside a project_dirs root",
+                archive_dir.display()
+            ));
+        }
+
+        Ok(archive_dir)
+    }
+
+    fn archive(&mut self, project: Project) {
+        let archive_dir = match self.archive_dir() {
+            Ok(archive_dir) => archive_dir,
+            Err(err) => return self.set_error(err),
+        };
+
+        match archive_project(&project.project_path, &archive_dir) {
+            Ok(target) => {
+                self.remove_project(&project.project_path);
+                self.set_status(format!("Archived to {} (u to undo)", target.display()));
+                self.last_archive = Some((project, target));
+            }
+            Err(err) => self.set_error(format!("failed to archive: {err}")),
+        }
+    }
+
+    /// Moves the most recently archived project back to where it was.
+    pub fn undo_archive(&mut self) {
+        let Some((project, archived_at)) = self.last_archive.take() else {
+            return;
+        };
+
+        if project.project_path.exists() {
+            self.set_error(format!("{} already exists", project.project_path.display()));
+            return;
+        }
+
+        if let Err(err) = std::fs::rename(&archived_at, &project.project_path) {
+            self.set_error(format!("failed to restore: {err}"));
+            self.last_archive = Some((project, archived_at));
+            return;
+        }
+
+        let path = project.project_path.clone();
+        self.set_status(format!("Restored {}", path.display()));
+        self.projects.push(project);
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(Some(path));
+    }
+
+    /// Hides the selected project from future listings. Nothing on disk is
+    /// touched; `pl --unignore` brings ignored projects back.
+    pub fn ignore_selected(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        self.state_store.ignored.push(path.clone());
+        if let Err(err) = save_state(&self.state_store) {
+            self.state_store.ignored.pop();
+            self.set_error(format!("failed to save ignore list: {err}"));
+            return;
+        }
+
+        self.remove_project(&path);
+        self.set_status(format!(
+            "Ignored {} (pl --unignore to restore)",
+            path.display()
+        ));
+    }
+
+    /// Opens the prompt for a new project, created under the root the list is
+    /// scoped to, or the first `project_dirs` entry.
+    pub fn start_new_project(&mut self) {
+        match self.new_project_root() {
+            Ok(_) => self.new_project = Some(NewProject::Name(Input::default())),
+            Err(err) => self.set_error(err),
+        }
+    }
+
+    /// Enter on a search that matches nothing. With `create_on_enter` this
+    /// opens the new project prompt filled in with the query, so creating it
+    /// still takes a second Enter; otherwise it just says nothing matched.
+    pub fn submit_unmatched_query(&mut self) {
+        let query = self.input.value().trim().to_string();
+        let is_name = !query.is_empty() && !query.starts_with(['>', '#']);
+        if self.user_config.create_on_enter && is_name && !self.history_mode {
+            match self.new_project_root() {
+                Ok(_) => self.new_project = Some(NewProject::Name(Input::new(query))),
+                Err(err) => self.set_error(err),
+            }
+        } else {
+            self.set_status(format!("No project matches '{query}'"));
+        }
+    }
+
+    fn new_project_root(&self) -> Result<PathBuf, String> {
+        self.current_root()
+            .or(self.roots.first().map(PathBuf::as_path))
+            .map(Path::to_path_buf)
+            .ok_or_else(|| "new projects need a project_dirs root".to_string())
+    }
+
+    /// The roots offered by the root step, shown as in the list.
+    pub fn root_choices(&self) -> Vec<String> {
+        self.roots
+            .iter()
+            .map(|root| self.display_path(root))
+            .collect()
+    }
+
+    /// The choices offered by the template step, starting with no template.
+    pub fn template_choices(&self) -> Vec<&str> {
+        std::iter::once("(none)")
+            .chain(self.user_config.templates.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// Moves the cursor of the root or template step.
+    pub fn move_template_selection(&mut self, step: isize) {
+        let count = match &self.new_project {
+            Some(NewProject::Root { .. }) => self.roots.len(),
+            _ => self.template_choices().len(),
+        } as isize;
+        if let Some(NewProject::Root { selected, .. } | NewProject::Template { selected, .. }) =
+            &mut self.new_project
+        {
+            *selected = (*selected as isize + step).rem_euclid(count) as usize;
+        }
+    }
+
+    /// Moves on to the next step: from the name to picking a root, when
+    /// there are several and the list isn't scoped to one, then a template,
+    /// when there are any, then the template's prompt, if it has one.
+    /// Creates the project once there is nothing left to ask.
+    pub fn submit_new_project(&mut self) {
+        match self.new_project.take() {
+            Some(NewProject::Name(input)) => {
+                let name = input.value().trim().to_string();
+                if self.current_root().is_none() && self.roots.len() > 1 {
+                    self.new_project = Some(NewProject::Root { name, selected: 0 });
+                    return;
+                }
+                match self.new_project_root() {
+                    Ok(root) => self.pick_template(name, root),
+                    Err(err) => self.set_error(err),
+                }
+            }
+            Some(NewProject::Root { name, selected }) => {
+                let root = self.roots[selected].clone();
+                self.pick_template(name, root);
+            }
+            Some(NewProject::Template {
+                name,
+                root,
+                selected,
+            }) => {
+                let Some((key, template)) = selected
+                    .checked_sub(1)
+                    .and_then(|index| self.user_config.templates.iter().nth(index))
+                else {
+                    return self.create_new_project(&name, &root, None, "");
+                };
+                if template.prompt().is_some() {
+                    self.new_project = Some(NewProject::Prompt {
+                        name,
+                        root,
+                        template: key.clone(),
+                        input: Input::default(),
+                    });
+                } else {
+                    let key = key.clone();
+                    self.create_new_project(&name, &root, Some(&key), "");
+                }
+            }
+            Some(NewProject::Prompt {
+                name,
+                root,
+                template,
+                input,
+            }) => {
+                self.create_new_project(&name, &root, Some(&template), input.value().trim());
+            }
+            None => {}
+        }
+    }
+
+    /// The template step for `name` under `root`, or straight to creating
+    /// it without any templates.
+    fn pick_template(&mut self, name: String, root: PathBuf) {
+        if self.user_config.templates.is_empty() {
+            self.create_new_project(&name, &root, None, "");
+        } else {
+            self.new_project = Some(NewProject::Template {
+                name,
+                root,
+                selected: 0,
+            });
+        }
+    }
+
+    /// The picked template's prompt, for the prompt step's title.
+    pub fn template_prompt(&self, template: &str) -> Option<&str> {
+        self.user_config.templates.get(template)?.prompt()
+    }
+
+    pub fn cancel_new_project(&mut self) {
+        self.new_project = None;
+    }
+
+    /// Creates `name` under `root` from the `[templates]` entry `template`,
+    /// then selects it, and opens it with `open_on_create`.
+    fn create_new_project(&mut self, name: &str, root: &Path, template: Option<&str>, input: &str) {
+        let template = template.and_then(|key| self.user_config.templates.get(key));
+        let dir = template.and_then(|template| template.dir()).map(parse_dir);
+        let commands = template.map_or(&[][..], |template| template.commands());
+
+        match create_project(root, name, dir.as_deref(), commands, input) {
+            Ok(project) => {
+                let path = project.project_path.clone();
+                self.set_status(format!("Created {}", path.display()));
+                self.projects.push(project);
+                self.record_seen_projects();
+                self.apply_sort();
+                self.filter_results();
+                self.reselect(Some(path.clone()));
+                if self.user_config.open_on_create {
+                    self.open_paths(vec![path]);
+                }
+            }
+            Err(err) => self.set_error(format!("failed to create project: {err}")),
+        }
+    }
+
+    /// Opens the rename prompt for the selected project, filled in with its
+    /// current directory name.
+    pub fn start_rename(&mut self) {
+        if self.history_mode {
+            return self.set_error("projects can't be renamed from history".to_string());
+        }
+        let Some(name) = self
+            .selected_project()
+            .and_then(|project| project.project_path.file_name())
+        else {
+            return;
+        };
+
+        self.rename = Some(Input::new(name.to_string_lossy().into_owned()));
+    }
+
+    pub fn submit_rename(&mut self) {
+        let (Some(input), Some(path)) = (
+            self.rename.take(),
+            self.selected_project()
+                .map(|project| project.project_path.clone()),
+        ) else {
+            return;
+        };
+
+        let name = input.value().trim().to_string();
+        if path.file_name() == Some(name.as_ref()) {
+            return;
+        }
+        self.request(PendingAction::Rename(path, name));
+    }
+
+    pub fn cancel_rename(&mut self) {
+        self.rename = None;
+    }
+
+    fn rename_project(&mut self, path: PathBuf, name: &str) {
+        let target = match rename_project(&path, name) {
+            Ok(target) => target,
+            Err(err) => return self.set_error(format!("failed to rename: {err}")),
+        };
+
+        let old_name = path.file_name().unwrap_or_default().to_string_lossy();
+        for project in self
+            .projects
+            .iter_mut()
+            .chain(&mut self.filtered_projects)
+            .filter(|project| project.project_path == path)
+        {
+            // Keep any `org/` prefix a ghq name has.
+            let prefix = project
+                .project_name
+                .to_str()
+                .and_then(|current| current.strip_suffix(old_name.as_ref()))
+                .unwrap_or("");
+            project.project_name = format!("{prefix}{name}").into();
+            project.project_path = target.clone();
+        }
+
+        for stored in self
+            .state_store
+            .pinned
+            .iter_mut()
+            .chain(&mut self.state_store.recently_opened)
+            .chain(&mut self.state_store.last_opened)
+            .filter(|stored| **stored == path)
+        {
+            *stored = target.clone();
+        }
+        let state = &mut self.state_store;
+        if let Some(opened_at) = state.opened_at.remove(&path) {
+            state.opened_at.insert(target.clone(), opened_at);
+        }
+        if let Some(count) = state.open_counts.remove(&path) {
+            state.open_counts.insert(target.clone(), count);
+        }
+        if let Some(tags) = state.tags.remove(&path) {
+            state.tags.insert(target.clone(), tags);
+        }
+        // The old path is gone, so history shouldn't offer it.
+        state.seen_projects.retain(|seen| *seen != path);
+        self.record_seen_projects();
+        if let Err(err) = save_state(&self.state_store) {
+            return self.set_error(format!("renamed, but failed to save state: {err}"));
+        }
+
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(Some(target.clone()));
+        self.set_status(format!("Renamed to {}", target.display()));
+    }
+
+    /// Drops a project from every list, keeping the cursor at the same row.
+    fn remove_project(&mut self, path: &Path) {
+        self.projects.retain(|project| project.project_path != path);
+        self.filtered_projects
+            .retain(|project| project.project_path != path);
+
+        let len = self.visible_projects().len();
+        if len == 0 {
+            self.state.select(None);
+        } else if let Some(index) = self.state.selected() {
+            self.state.select(Some(index.min(len - 1)));
+        }
+    }
+
+    pub fn is_last_opened(&self, project: &Project) -> bool {
+        self.state_store.last_opened.as_ref() == Some(&project.project_path)
+    }
+
+    /// The highlighted project. The list state indexes the projects on
+    /// screen, the filtered ones or all of them with `Ctrl-a`, so it is always
+    /// resolved against `visible_projects` and never `projects`.
+    pub fn selected_project(&self) -> Option<&Project> {
+        self.state
+            .selected()
+            .and_then(|index| self.visible_projects().get(index))
+    }
+
+    /// Whether chosen projects are printed rather than opened.
+    pub fn prints_selection(&self) -> bool {
+        self.print_selection
+    }
+
+    pub fn take_open_targets(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.open_targets)
+    }
+
+    pub fn config(&self) -> &UserConfig {
+        &self.user_config
+    }
+
+    pub fn open_project_remote(&self) {
+        let Some(project) = self.selected_project() else {
+            return;
+        };
+
+        let remote = normalize_url(&project.project_remote);
+        if remote.is_empty() {
+            return;
+        }
+
+        if let Err(err) = open::that(&remote) {
+            eprintln!("failed to open remote '{remote}': {err}");
+        }
+    }
+
+    /// Whether the selected project matches `no_preview_for`, so its preview
+    /// isn't read at all. An entry is either a project type as detected from
+    /// its manifest, such as `python`, or a glob: one with a `/` is matched
+    /// against the whole path, `~` expanded, and one without against the
+    /// directory name.
+    pub fn preview_suppressed(&mut self) -> bool {
+        let patterns = &self.user_config.no_preview_for;
+        if patterns.is_empty() {
+            return false;
+        }
+        let Some(project) = self.previewed_project() else {
+            return false;
+        };
+
+        *self
+            .no_preview_cache
+            .entry(project.project_path.clone())
+            .or_insert_with_key(|path| {
+                let language = detect_language(path);
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                let full = path.to_string_lossy();
+                patterns.iter().any(|pattern| {
+                    if language.is_some_and(|language| language.eq_ignore_ascii_case(pattern)) {
+                        true
+                    } else if pattern.contains('/') {
+                        glob_match(&parse_dir(pattern).to_string_lossy(), &full)
+                    } else {
+                        glob_match(pattern, &name)
+                    }
+                })
+            })
+    }
+
+    /// Returns the file name and contents of the preview file currently shown
+    /// for the selected project. When several `preview_files` exist, the first
+    /// one in the configured priority order is shown until cycled. Projects
+    /// without any preview file fall back to a listing of their files.
+    pub fn selected_preview(&mut self) -> Option<(String, &str)> {
+        let project_path = self.sync_preview()?;
+        if let PreviewMode::RecentFiles = self.preview_mode {
+            return Some(("Recent files".to_string(), self.recent_files(project_path)));
+        }
+
+        let index = self.preview_index;
+        let Some(path) = self
+            .content_match_file(&project_path)
+            .or_else(|| self.preview_paths(project_path.clone()).get(index).cloned())
+        else {
+            if !self.manifest_cache.contains_key(&project_path) {
+                let summary = manifest_summary(&project_path);
+                self.manifest_cache.insert(project_path.clone(), summary);
+            }
+            if let Some((name, summary)) = &self.manifest_cache[&project_path] {
+                return Some((name.to_string(), summary.as_str()));
+            }
+
+            let show_hidden = self.preview_show_hidden;
+            let tree = self
+                .tree_cache
+                .entry(project_path)
+                .or_insert_with_key(|path| tree_preview(path, show_hidden));
+            return Some(("Files".to_string(), tree.as_str()));
+        };
+        let name = match path.strip_prefix(&project_path) {
+            Ok(relative) => relative.to_string_lossy().into_owned(),
+            Err(_) => path.file_name()?.to_string_lossy().into_owned(),
+        };
+        let contents = self.read_cached(path)?;
+
+        Some((name, contents))
+    }
+
+    /// The word count and estimated reading time of the shown preview file,
+    /// such as `340 words, ~2 min`, when `show_reading_time` is on.
+    pub fn selected_reading_time(&mut self) -> Option<String> {
+        const WORDS_PER_MINUTE: usize = 200;
+
+        if !self.user_config.show_reading_time || !matches!(self.preview_mode, PreviewMode::Readme)
+        {
+            return None;
+        }
+
+        let project_path = self.sync_preview()?;
+        let index = self.preview_index;
+        let path = self.preview_paths(project_path).get(index)?.clone();
+        let words = match self.word_counts.get(&path) {
+            Some(words) => *words,
+            None => {
+                let words = self.read_cached(path.clone())?.split_whitespace().count();
+                self.word_counts.insert(path, words);
+                words
+            }
+        };
+
+        let minutes = words.div_ceil(WORDS_PER_MINUTE).max(1);
+        Some(format!("{words} words, ~{minutes} min"))
+    }
+
+    /// Copies the raw contents of the preview file shown for the selected
+    /// project to the clipboard.
+    pub fn copy_preview(&mut self) {
+        if self.previewed_project().is_none() {
+            return;
+        }
+
+        let Some(path) = self.shown_preview_file() else {
+            return self.set_error("no README to copy".to_string());
+        };
+
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let Some(contents) = self.read_cached(path).map(str::to_string) else {
+            return self.set_error(format!("failed to read {name}"));
+        };
+        match clipboard::copy(&contents) {
+            Ok(()) => self.set_status(format!("Copied {name}")),
+            Err(err) => self.set_error(format!("failed to copy: {err}")),
+        }
+    }
+
+    /// Moves focus between the list and the text preview; the languages and
+    /// changes views don't take it. With `fold_markdown`, a markdown preview
+    /// starts with only its top-level headings showing.
+    pub fn toggle_preview_focus(&mut self) {
+        if self.preview_cursor.take().is_some() {
+            self.preview_folds = None;
+            return;
+        }
+        if !matches!(
+            self.preview_mode,
+            PreviewMode::Readme | PreviewMode::RecentFiles
+        ) {
+            return;
+        }
+
+        let fold =
+            self.user_config.fold_markdown && matches!(self.preview_mode, PreviewMode::Readme);
+        let Some((name, contents)) = self.selected_preview() else {
+            return;
+        };
+        let folds = (fold && markdown::is_markdown(&name)).then(|| {
+            let headings = markdown::headings(contents);
+            PreviewFolds {
+                collapsed: headings.iter().map(|heading| heading.line).collect(),
+                headings,
+            }
+        });
+        self.preview_folds = folds;
+        self.preview_cursor = Some(PreviewCursor::default());
+    }
+
+    /// The preview lines on screen while headings are folded, each with
+    /// whether it is a folded heading. `None` when nothing is folded.
+    pub fn preview_rows(&mut self) -> Option<Vec<(usize, bool)>> {
+        self.preview_folds.as_ref()?;
+        let count = self.preview_line_count();
+        let folds = self.preview_folds.as_ref()?;
+        let rows = markdown::visible_lines(count, &folds.headings, &folds.collapsed)
+            .into_iter()
+            .map(|line| (line, folds.collapsed.contains(&line)))
+            .collect();
+        Some(rows)
+    }
+
+    /// Folds or unfolds the section under the preview cursor, moving the
+    /// cursor to its heading.
+    pub fn toggle_preview_fold(&mut self) {
+        let (Some(cursor), Some(folds)) = (&mut self.preview_cursor, &mut self.preview_folds)
+        else {
+            return;
+        };
+        let Some(heading) = markdown::section_of(&folds.headings, cursor.line) else {
+            return;
+        };
+
+        if !folds.collapsed.remove(&heading.line) {
+            folds.collapsed.insert(heading.line);
+        }
+        cursor.line = heading.line;
+    }
+
+    fn preview_line_count(&mut self) -> usize {
+        self.selected_preview()
+            .map_or(0, |(_, contents)| contents.lines().count())
+    }
+
+    /// Moves the preview cursor by `step` lines, stopping at either end and
+    /// stepping over folded sections.
+    pub fn move_preview_cursor(&mut self, step: isize) {
+        let rows = self.preview_rows();
+        let last = self.preview_line_count().saturating_sub(1);
+        let Some(cursor) = &mut self.preview_cursor else {
+            return;
+        };
+
+        cursor.line = match rows {
+            Some(rows) => {
+                let row = row_of(&rows, cursor.line)
+                    .saturating_add_signed(step)
+                    .min(rows.len().saturating_sub(1));
+                rows.get(row).map_or(0, |&(line, _)| line)
+            }
+            None => cursor.line.saturating_add_signed(step).min(last),
+        };
+    }
+
+    pub fn preview_cursor_to_end(&mut self) {
+        let last = match self.preview_rows() {
+            Some(rows) => rows.last().map_or(0, |&(line, _)| line),
+            None => self.preview_line_count().saturating_sub(1),
+        };
+        if let Some(cursor) = &mut self.preview_cursor {
+            cursor.line = last;
+        }
+    }
+
+    /// Starts a selection at the cursor, or drops the current one.
+    pub fn toggle_preview_selection(&mut self) {
+        if let Some(cursor) = &mut self.preview_cursor {
+            cursor.anchor = match cursor.anchor {
+                Some(_) => None,
+                None => Some(cursor.line),
+            };
+        }
+    }
+
+    /// Copies the selected preview lines, or the cursor line, to the
+    /// clipboard and ends the selection.
+    pub fn yank_preview_selection(&mut self) {
+        let Some(cursor) = self.preview_cursor else {
+            return;
+        };
+        let Some((_, contents)) = self.selected_preview() else {
+            return;
+        };
+
+        let selection = cursor.selection();
+        let lines: Vec<&str> = contents
+            .lines()
+            .skip(*selection.start())
+            .take(selection.count())
+            .collect();
+        let count = lines.len();
+        let result = clipboard::copy(&lines.join("\n"));
+
+        if let Some(cursor) = &mut self.preview_cursor {
+            cursor.anchor = None;
+        }
+        match result {
+            Ok(()) if count == 1 => self.set_status("Copied 1 line".to_string()),
+            Ok(()) => self.set_status(format!("Copied {count} lines")),
+            Err(err) => self.set_error(format!("failed to copy: {err}")),
+        }
+    }
+
+    /// Scrolls the unfocused preview by `step` rows, stopping at the top.
+    pub fn scroll_preview(&mut self, step: isize) {
+        self.sync_preview();
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(step);
+    }
+
+    /// The unfocused preview's scroll offset, kept short of scrolling the
+    /// last `height` rows off the bottom.
+    pub fn clamp_preview_scroll(&mut self, height: usize) -> usize {
+        let count = match self.preview_rows() {
+            Some(rows) => rows.len(),
+            None => self.preview_line_count(),
+        };
+        self.preview_scroll = self.preview_scroll.min(count.saturating_sub(height));
+        self.preview_scroll
+    }
+
+    /// Scrolls the focused preview so the cursor stays within `height` rows,
+    /// returning the cursor to draw. While headings are folded, `scroll`
+    /// counts rows on screen rather than lines of the file.
+    pub fn scroll_preview_cursor(&mut self, height: usize) -> Option<PreviewCursor> {
+        let rows = self.preview_rows();
+        let cursor = self.preview_cursor.as_mut()?;
+        let row = rows.map_or(cursor.line, |rows| row_of(&rows, cursor.line));
+        if row < cursor.scroll {
+            cursor.scroll = row;
+        } else if height > 0 && row >= cursor.scroll + height {
+            cursor.scroll = row + 1 - height;
+        }
+        Some(*cursor)
+    }
+
+    /// The preview file on screen, if the preview shows one rather than a
+    /// listing.
+    fn shown_preview_file(&mut self) -> Option<PathBuf> {
+        let project_path = self.sync_preview()?;
+        if !matches!(self.preview_mode, PreviewMode::Readme) {
+            return None;
+        }
+
+        let index = self.preview_index;
+        self.content_match_file(&project_path)
+            .or_else(|| self.preview_paths(project_path).get(index).cloned())
+    }
+
+    /// The file a content search matched in the project, previewed in place
+    /// of its first preview file.
+    fn content_match_file(&self, project_path: &Path) -> Option<PathBuf> {
+        if self.preview_index != 0 {
+            return None;
+        }
+        let found = self.content_matches.get(project_path)?;
+        Some(found.file.clone())
+    }
+
+    fn read_cached(&mut self, path: PathBuf) -> Option<&str> {
+        match self.readme_cache.entry(path) {
+            Entry::Occupied(entry) => entry.into_mut().as_deref(),
+            Entry::Vacant(entry) => {
+                let contents = std::fs::read_to_string(entry.key()).ok();
+                entry.insert(contents).as_deref()
+            }
+        }
+    }
+
+    pub fn preview_count(&mut self) -> usize {
+        match self.sync_preview() {
+            Some(project_path) => self.preview_paths(project_path).len(),
+            None => 0,
+        }
+    }
+
+    pub fn preview_position(&self) -> usize {
+        self.preview_index
+    }
+
+    pub fn cycle_preview(&mut self, step: isize) {
+        let count = self.preview_count();
+        if count > 1 {
+            self.preview_index =
+                (self.preview_index as isize + step).rem_euclid(count as isize) as usize;
+            self.preview_scroll = 0;
+        }
+    }
+
+    /// Switches the focused preview to the next or previous tab, starting
+    /// over at its top.
+    pub fn cycle_preview_tab(&mut self, step: isize) {
+        self.cycle_preview(step);
+        if self.preview_cursor.take().is_some() {
+            self.preview_folds = None;
+            self.toggle_preview_focus();
+        }
+    }
+
+    /// The names of the preview files for the tab bar, relative to the
+    /// project. Empty unless there are several to pick from.
+    pub fn preview_tabs(&mut self) -> Vec<String> {
+        let Some(project_path) = self.sync_preview() else {
+            return Vec::new();
+        };
+        if !matches!(self.preview_mode, PreviewMode::Readme) {
+            return Vec::new();
+        }
+
+        let paths = self.preview_paths(project_path.clone());
+        if paths.len() < 2 {
+            return Vec::new();
+        }
+        paths
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&project_path)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    pub fn next_preview_mode(&mut self) {
+        self.preview_mode = match self.preview_mode {
+            PreviewMode::Readme => PreviewMode::RecentFiles,
+            PreviewMode::RecentFiles => PreviewMode::Languages,
+            PreviewMode::Languages => PreviewMode::Changes,
+            PreviewMode::Changes => PreviewMode::Readme,
+        };
+        self.preview_scroll = 0;
+    }
+
+    /// The selected project's language breakdown while the languages preview
+    /// is shown. Computed once per project.
+    pub fn selected_languages(&mut self) -> Option<&[(&'static str, u64)]> {
+        if !matches!(self.preview_mode, PreviewMode::Languages) {
+            return None;
+        }
+
+        let project_path = self.previewed_project()?.project_path.clone();
+        let languages = self
+            .language_cache
+            .entry(project_path)
+            .or_insert_with_key(|path| language_breakdown(path));
+        Some(languages)
+    }
+
+    /// `git diff --stat` for the selected project while the changes preview
+    /// is shown, read once per project until refreshed with `r`. The inner
+    /// `None` means git couldn't produce one.
+    pub fn selected_diff_stat(&mut self) -> Option<Option<&str>> {
+        if !matches!(self.preview_mode, PreviewMode::Changes) {
+            return None;
+        }
+
+        let project_path = self.previewed_project()?.project_path.clone();
+        let diff_stat = self
+            .diff_stat_cache
+            .entry(project_path)
+            .or_insert_with_key(|path| git::diff_stat(path));
+        Some(diff_stat.as_deref())
+    }
+
+    /// Recently modified files, cached until the project's `HEAD` moves.
+    fn recent_files(&mut self, project_path: PathBuf) -> &str {
+        let head = head_commit(&project_path);
+        let stale = self
+            .recent_files_cache
+            .get(&project_path)
+            .is_none_or(|(cached_head, _)| *cached_head != head);
+
+        if stale {
+            let preview = recent_files_preview(&project_path);
+            self.recent_files_cache
+                .insert(project_path.clone(), (head, preview));
+        }
+
+        &self.recent_files_cache[&project_path].1
+    }
+
+    pub fn selected_display_path(&self) -> Option<String> {
+        let project = self.previewed_project()?;
+        Some(self.display_path(&project.project_path))
+    }
+
+    /// `path` shortened to `~` unless absolute paths are toggled on.
+    pub fn display_path(&self, path: &Path) -> String {
+        display_path(path, self.absolute_paths)
+    }
+
+    pub fn toggle_absolute_paths(&mut self) {
+        self.absolute_paths = !self.absolute_paths;
+    }
+
+    pub fn toggle_preview_hidden(&mut self) {
+        self.preview_show_hidden = !self.preview_show_hidden;
+        self.tree_cache.clear();
+    }
+
+    /// The project the preview shows: the one it is locked on with `L`, or
+    /// else the selection. A locked project that has gone from the list no
+    /// longer holds the preview.
+    pub fn previewed_project(&self) -> Option<&Project> {
+        if let Some(path) = &self.preview_locked_path
+            && let Some(project) = self
+                .projects
+                .iter()
+                .chain(&self.filtered_projects)
+                .find(|project| project.project_path == *path)
+        {
+            return Some(project);
+        }
+        self.selected_project()
+    }
+
+    pub fn preview_locked(&self) -> bool {
+        self.preview_locked_path.is_some()
+    }
+
+    /// Locks the preview on the selected project so it stays put as the
+    /// selection moves, or unlocks it to follow the selection again.
+    pub fn toggle_preview_lock(&mut self) {
+        if self.preview_locked_path.take().is_some() {
+            return self.set_status("Preview follows the selection".to_string());
+        }
+        let Some(project) = self.selected_project() else {
+            return;
+        };
+        let message = format!(
+            "Preview locked on {}",
+            project.project_name.to_string_lossy()
+        );
+        self.preview_locked_path = Some(project.project_path.clone());
+        self.set_status(message);
+    }
+
+    fn sync_preview(&mut self) -> Option<PathBuf> {
+        let project_path = self.previewed_project()?.project_path.clone();
+        if self.preview_project.as_ref() != Some(&project_path) {
+            self.preview_project = Some(project_path.clone());
+            self.preview_index = 0;
+            self.preview_scroll = 0;
+        }
+        Some(project_path)
+    }
+
+    /// The existing `preview_files`, then the existing `doc_files`.
+    fn preview_paths(&mut self, project_path: PathBuf) -> &[PathBuf] {
+        let files = &self.user_config.preview_files;
+        let docs = &self.user_config.doc_files;
+        self.preview_paths_cache
+            .entry(project_path)
+            .or_insert_with_key(|project_path| {
+                let mut paths: Vec<PathBuf> = Vec::new();
+                for path in files
+                    .iter()
+                    .chain(docs)
+                    .filter_map(|file| find_file(project_path, file))
+                {
+                    if !paths.contains(&path) {
+                        paths.push(path);
+                    }
+                }
+                paths
+            })
+    }
+
+    /// Returns the human-readable on-disk size of the selected project when
+    /// `show_size` is enabled. Computed on first request and cached.
+    pub fn selected_size(&mut self) -> Option<String> {
+        if !self.user_config.show_size {
+            return None;
+        }
+
+        let project_path = self.previewed_project()?.project_path.clone();
+        let size = *self
+            .size_cache
+            .entry(project_path)
+            .or_insert_with_key(|path| dir_size(path));
+
+        Some(format_size(size))
+    }
+
+    /// Queues git status for the `height` rows the list will show, so the
+    /// cost is paid only for projects on screen. The worker picks them up on a
+    /// later tick. No-op unless `show_git_status`.
+    pub fn load_visible_git_status(&mut self, height: usize) {
+        if !self.user_config.show_git_status {
+            return;
+        }
+
+        let mut start = self.state.offset();
+        if let Some(selected) = self.state.selected() {
+            if selected < start {
+                start = selected;
+            } else if selected >= start + height {
+                start = selected + 1 - height;
+            }
+        }
+
+        let paths: Vec<PathBuf> = self
+            .visible_projects()
+            .iter()
+            .skip(start)
+            .take(height)
+            .map(|project| project.project_path.clone())
+            .collect();
+
+        let wanted: Vec<PathBuf> = paths
+            .into_iter()
+            .filter(|path| {
+                !self.git_status_cache.contains_key(path)
+                    && !self.git_status_requested.contains(path)
+            })
+            .collect();
+        if wanted != self.git_status_wanted {
+            self.git_status_wanted = wanted;
+            self.git_status_wanted_at = Instant::now();
+        }
+    }
+
+    /// Hands the wanted paths to the worker once scrolling has paused for
+    /// `git_status_debounce_ms`, so flicking through the list doesn't queue
+    /// up status for every project passed on the way.
+    fn request_git_status(&mut self) {
+        let debounce = Duration::from_millis(self.user_config.git_status_debounce_ms);
+        if self.git_status_wanted.is_empty() || self.git_status_wanted_at.elapsed() < debounce {
+            return;
+        }
+
+        let check_stashes = self.user_config.show_stash_indicator;
+        let worker = self
+            .git_status_worker
+            .get_or_insert_with(|| GitStatusWorker::spawn(check_stashes));
+        for path in self.git_status_wanted.drain(..) {
+            worker.request(path.clone());
+            self.git_status_requested.insert(path);
+        }
+    }
+
+    /// Moves finished statuses into the cache. Returns whether any arrived.
+    fn receive_git_status(&mut self) -> bool {
+        let Some(worker) = &self.git_status_worker else {
+            return false;
+        };
+
+        let mut received = false;
+        for (path, status) in worker.finished() {
+            self.git_status_requested.remove(&path);
+            self.git_status_cache.insert(path, status);
+            received = true;
+        }
+        received
+    }
+
+    pub fn git_status(&self, project: &Project) -> Option<&GitStatus> {
+        if !self.user_config.show_git_status {
+            return None;
+        }
+        self.git_status_cache.get(&project.project_path)?.as_ref()
+    }
+
+    /// Whether the list should show a placeholder while `project`'s git status
+    /// is still loading.
+    pub fn git_status_loading(&self, project: &Project) -> bool {
+        self.user_config.show_git_status
+            && !self.git_status_cache.contains_key(&project.project_path)
+    }
+
+    /// Recomputes git status and the diff stat for just the selected project.
+    pub fn refresh_git_status(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        self.diff_stat_cache.remove(&path);
+        self.branch_cache.remove(&path);
+        self.commit_subject_cache.remove(&path);
+        self.commit_time_cache.remove(&path);
+
+        let status = git::status(&path, self.user_config.show_stash_indicator);
+        self.git_status_cache.insert(path, status);
+    }
+
+    /// Scans for projects again, keeping the selected project and the scroll
+    /// position when it is still listed.
+    pub fn rescan(&mut self) {
+        self.scan_in_background(ScanKind::Manual);
+        self.set_status("Rescanning…".to_string());
+    }
+
+    /// Starts a scan on a background thread, replacing any still running.
+    fn scan_in_background(&mut self, kind: ScanKind) {
+        self.last_scan = Instant::now();
+        self.scan_worker = Some((ScanWorker::spawn(self.user_config.clone()), kind));
+    }
+
+    fn report_rescan(&mut self, count: usize) {
+        if self.scan_errors.is_empty() {
+            self.set_status(format!("Rescanned ({count} projects)"));
+        } else {
+            self.set_error(format!(
+                "Rescanned ({count} projects), {} scan errors (Ctrl-e to view)",
+                self.scan_errors.len()
+            ));
+        }
+    }
+
+    /// Rescans and returns the number of projects found.
+    fn rescan_projects(&mut self) -> usize {
+        // Whatever a background scan still had to say is superseded.
+        self.scan_worker = None;
+        self.last_scan = Instant::now();
+        let (projects, scan_errors) = get_all_projects(&self.user_config);
+        self.apply_scan(projects, scan_errors)
+    }
+
+    /// Replaces the list with a scan's results, keeping the selected
+    /// project and the scroll position when it is still listed. Returns the
+    /// number of projects.
+    fn apply_scan(&mut self, mut projects: Vec<Project>, scan_errors: Vec<ScanError>) -> usize {
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+        let offset = self.state.offset();
+
+        save_cache(&self.user_config, &projects);
+        projects.retain(|project| !self.state_store.ignored.contains(&project.project_path));
+        let count = projects.len();
+        if self.history_mode {
+            self.stashed_projects = projects;
+        } else {
+            self.projects = projects;
+            self.record_seen_projects();
+        }
+        self.scan_errors = scan_errors;
+        // A content search starts over on the new list.
+        self.content_term = None;
+        self.preview_paths_cache.clear();
+        self.tree_cache.clear();
+        self.manifest_cache.clear();
+        self.no_preview_cache.clear();
+        self.has_readme_cache.clear();
+        self.branch_cache.clear();
+        self.commit_subject_cache.clear();
+        self.commit_time_cache.clear();
+
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(selected.clone());
+        if selected.is_some()
+            && self.selected_project().map(|project| &project.project_path) == selected.as_ref()
+        {
+            *self.state.offset_mut() = offset;
+        }
+        count
+    }
+
+    pub fn toggle_scan_errors(&mut self) {
+        self.show_scan_errors = !self.show_scan_errors;
+    }
+
+    /// Opens the info panel for the selected project, or closes it.
+    pub fn toggle_info(&mut self) {
+        if self.info.take().is_some() {
+            return;
+        }
+
+        let Some(project) = self.selected_project().cloned() else {
+            return;
+        };
+        let path = &project.project_path;
+        let unknown = || "unknown".to_string();
+
+        let branch = match git::status(path, false) {
+            Some(status) => status.branch.unwrap_or_else(|| "detached".to_string()),
+            None => unknown(),
+        };
+        let remote = normalize_url(&project.project_remote);
+        let size = *self
+            .size_cache
+            .entry(path.clone())
+            .or_insert_with_key(|path| dir_size(path));
+
+        let mut info = vec![
+            ("Path", path.display().to_string()),
+            (
+                "Language",
+                detect_language(path).map_or_else(unknown, str::to_string),
+            ),
+            ("Branch", branch),
+            (
+                "Last commit",
+                git::last_commit_date(path).unwrap_or_else(unknown),
+            ),
+            (
+                "Remote",
+                if remote.is_empty() {
+                    "none".to_string()
+                } else {
+                    remote
+                },
+            ),
+            ("Size", format_size(size)),
+            (
+                "Opened",
+                self.opened_age(path).unwrap_or_else(|| "never".to_string()),
+            ),
+        ];
+
+        if self.user_config.show_commit_activity {
+            let weeks = self.user_config.activity_weeks;
+            let activity = self
+                .activity_cache
+                .entry(path.clone())
+                .or_insert_with_key(|path| git::weekly_commits(path, weeks));
+            let activity = match activity {
+                Some(counts) => format!(
+                    "{} ({} commits in {weeks} weeks)",
+                    sparkline(counts),
+                    counts.iter().sum::<usize>()
+                ),
+                None => unknown(),
+            };
+            info.push(("Activity", activity));
+        }
+
+        self.info = Some(info);
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+        self.help_scroll = 0;
+    }
+
+    pub fn scroll_help(&mut self, step: isize) {
+        self.help_scroll = self.help_scroll.saturating_add_signed(step);
+    }
+
+    pub fn sort_label(&self) -> &str {
+        match (&self.sort_state, self.sort_reverse) {
+            (SortState::Frecent, false) => "Frecent",
+            (SortState::Frecent, true) => "Rarest",
+            (SortState::Alphabetical, false) => "A-Z",
+            (SortState::Alphabetical, true) => "Z-A",
+            (SortState::RecentlyModified, false) => "Recent",
+            (SortState::RecentlyModified, true) => "Oldest",
+            (SortState::RecentlyCommitted, false) => "Committed",
+            (SortState::RecentlyCommitted, true) => "Stalest",
+        }
+    }
+
+    /// Cycles the sort mode.
+    pub fn next_sort(&mut self) {
+        self.set_sort(match self.sort_state {
+            SortState::Frecent => SortState::Alphabetical,
+            SortState::Alphabetical => SortState::RecentlyModified,
+            SortState::RecentlyModified => SortState::RecentlyCommitted,
+            SortState::RecentlyCommitted => SortState::Frecent,
+        });
+    }
+
+    /// Switches straight between frecency and name order, whichever the
+    /// list isn't in; from the other orders it goes to frecency.
+    pub fn toggle_frecency_sort(&mut self) {
+        self.set_sort(match self.sort_state {
+            SortState::Frecent => SortState::Alphabetical,
+            _ => SortState::Frecent,
+        });
+    }
+
+    /// Re-sorts by `sort_state`, keeping the selected project selected.
+    fn set_sort(&mut self, sort_state: SortState) {
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+
+        self.sort_state = sort_state;
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(selected);
+    }
+
+    /// Flips the sort direction, keeping the selected project selected.
+    pub fn reverse_sort(&mut self) {
+        let selected = self
+            .selected_project()
+            .map(|project| project.project_path.clone());
+
+        self.sort_reverse = !self.sort_reverse;
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(selected);
+    }
+
+    fn apply_sort(&mut self) {
+        match self.sort_state {
+            // Projects never opened all score zero and stay in name order.
+            SortState::Frecent => {
+                let now = unix_now();
+                let state = &self.state_store;
+                self.projects
+                    .sort_by(|a, b| natural_cmp(&a.project_name, &b.project_name));
+                self.projects.sort_by_cached_key(|project| {
+                    Reverse(frecency(state, &project.project_path, now).unwrap_or(0))
+                });
+            }
+            // With `show_hierarchy`, nested projects sort by path so they
+            // sit together where their parent directory would be.
+            SortState::Alphabetical if self.user_config.show_hierarchy => {
+                self.projects.sort_by(|a, b| {
+                    natural_cmp(a.project_path.as_os_str(), b.project_path.as_os_str())
+                });
+            }
+            SortState::Alphabetical => {
+                self.projects
+                    .sort_by(|a, b| natural_cmp(&a.project_name, &b.project_name));
+            }
+            SortState::RecentlyModified => {
+                self.projects.sort_by(|a, b| {
+                    let a_modified = a.project_path.metadata().and_then(|m| m.modified()).ok();
+                    let b_modified = b.project_path.metadata().and_then(|m| m.modified()).ok();
+
+                    b_modified.cmp(&a_modified)
+                });
+            }
+            // Times still being read sort last until `on_tick` sorts again.
+            SortState::RecentlyCommitted => {
+                let worker = self
+                    .commit_time_worker
+                    .get_or_insert_with(CommitTimeWorker::spawn);
+                for project in &self.projects {
+                    let path = &project.project_path;
+                    if !self.commit_time_cache.contains_key(path)
+                        && self.commit_time_requested.insert(path.clone())
+                    {
+                        worker.request(path.clone());
+                    }
+                }
+                let times = &self.commit_time_cache;
+                let time = |project: &Project| times.get(&project.project_path).copied().flatten();
+                self.projects.sort_by_key(|project| Reverse(time(project)));
+            }
+        }
+
+        if self.sort_reverse {
+            self.projects.reverse();
+        }
+        group_projects(
+            &mut self.projects,
+            &self.user_config.group_order,
+            &self.state_store,
+        );
+    }
+
+    /// Whether the `Committed` sort is still waiting on commit times.
+    pub fn sorting(&self) -> bool {
+        !self.commit_time_requested.is_empty()
+    }
+
+    /// Moves finished commit times into the cache, and sorts again, keeping
+    /// the selection, once the last one is in. Returns whether any arrived.
+    fn receive_commit_times(&mut self) -> bool {
+        let Some(worker) = &self.commit_time_worker else {
+            return false;
+        };
+
+        let mut received = false;
+        for (path, time) in worker.finished() {
+            self.commit_time_requested.remove(&path);
+            self.commit_time_cache.insert(path, time);
+            received = true;
+        }
+        if received
+            && self.commit_time_requested.is_empty()
+            && matches!(self.sort_state, SortState::RecentlyCommitted)
+        {
+            let selected = self
+                .selected_project()
+                .map(|project| project.project_path.clone());
+            self.apply_sort();
+            self.filter_results();
+            self.reselect(selected);
+        }
+        received
+    }
+
+    pub fn is_marked(&self, project: &Project) -> bool {
+        self.marked.contains(&project.project_path)
+    }
+
+    pub fn marked_count(&self) -> usize {
+        self.marked.len()
+    }
+
+    /// Marks the selected project to be opened with the others on `Enter`,
+    /// or unmarks it, and moves on to the next one.
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        match self.marked.iter().position(|marked| *marked == path) {
+            Some(index) => {
+                self.marked.remove(index);
+            }
+            None => self.marked.push(path),
+        }
+        self.move_selection(1);
+    }
+
+    pub fn clear_marks(&mut self) {
+        self.marked.clear();
+    }
+
+    pub fn is_pinned(&self, project: &Project) -> bool {
+        self.state_store.pinned.contains(&project.project_path)
+    }
+
+    /// Pins the selected project below the other pinned ones, or unpins it.
+    pub fn toggle_pin(&mut self) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+
+        let pinned = &mut self.state_store.pinned;
+        let now_pinned = match pinned.iter().position(|pin| *pin == path) {
+            Some(index) => {
+                pinned.remove(index);
+                false
+            }
+            None => {
+                pinned.push(path.clone());
+                true
+            }
+        };
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(Some(path.clone()));
+
+        match save_state(&self.state_store) {
+            Err(err) => self.set_error(format!("failed to save pins: {err}")),
+            Ok(()) if now_pinned => self.set_status(format!("Pinned {}", path.display())),
+            Ok(()) => self.set_status(format!("Unpinned {}", path.display())),
+        }
+    }
+
+    /// Moves the selected pinned project `step` places within the pinned
+    /// group, which is saved in its new order.
+    pub fn move_pin(&mut self, step: isize) {
+        let Some(path) = self
+            .selected_project()
+            .map(|project| project.project_path.clone())
+        else {
+            return;
+        };
+        let pinned = &mut self.state_store.pinned;
+        let Some(index) = pinned.iter().position(|pin| *pin == path) else {
+            return self.set_error("only pinned projects can be moved (m pins)".to_string());
+        };
+        let Some(target) = index
+            .checked_add_signed(step)
+            .filter(|target| *target < pinned.len())
+        else {
+            return;
+        };
+
+        pinned.swap(index, target);
+        if let Err(err) = save_state(&self.state_store) {
+            self.set_error(format!("failed to save pins: {err}"));
+        }
+
+        self.apply_sort();
+        self.filter_results();
+        self.reselect(Some(path));
+    }
+}
+
+/// Splits `projects` into the `order` segments, front to back. Pinned
+/// projects keep their pin order and frecent ones go most frecent first;
+/// the `sort` segment, which is last when it isn't listed, keeps the
+/// current order.
+fn group_projects(projects: &mut [Project], order: &[Group], state: &State) {
+    let sort_at = order
+        .iter()
+        .position(|group| *group == Group::Sort)
+        .unwrap_or(order.len());
+    let now = unix_now();
+
+    // (segment, rank within it); the sort is stable, so rank 0 for the
+    // `sort` segment leaves it as it was.
+    let key = |project: &Project| -> (usize, i64) {
+        let path = &project.project_path;
+        for (segment, group) in order.iter().enumerate().take(sort_at) {
+            match group {
+                Group::Pinned => {
+                    if let Some(index) = state.pinned.iter().position(|pin| pin == path) {
+                        return (segment, index as i64);
+                    }
+                }
+                Group::Frecency => {
+                    if let Some(score) = frecency(state, path, now) {
+                        return (segment, -(score as i64));
+                    }
+                }
+                Group::Sort => {}
+            }
+        }
+        (sort_at, 0)
+    };
+    projects.sort_by_cached_key(key);
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// Remembers what a scan found for the next startup. Failing to is only
+/// logged, since the next scan finds them all again anyway.
+fn save_cache(config: &UserConfig, projects: &[Project]) {
+    if let Err(err) = save_scan_cache(&config.scan_key(), projects) {
+        log::warn!("failed to save the scan cache: {err}");
+    }
+}
+
+/// How often and how recently `path` was opened, zoxide-style: the open
+/// count weighted by how long ago the last open was. `None` if it has never
+/// been opened from `pl`.
+fn frecency(state: &State, path: &Path, now: u64) -> Option<u64> {
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+    const WEEK: u64 = 7 * DAY;
+
+    let opened_at = *state.opened_at.get(path)?;
+    // Opens from before counts were kept still count once.
+    let count = u64::from(state.open_counts.get(path).copied().unwrap_or(1));
+    let weight = match now.saturating_sub(opened_at) {
+        age if age < HOUR => 16,
+        age if age < DAY => 8,
+        age if age < WEEK => 2,
+        _ => 1,
+    };
+    Some(count * weight)
+}
+
+/// Draws `counts` as a row of block characters scaled to the largest one.
+fn sparkline(counts: &[usize]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let max = counts.iter().copied().max().unwrap_or(0).max(1);
+    counts
+        .iter()
+        .map(|&count| match count {
+            0 => ' ',
+            _ => BARS[(count * (BARS.len() - 1)).div_ceil(max)],
+        })
+        .collect()
+}
+
+fn normalize_url(value: &str) -> String {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return String::new();
+    }
+
+    if trimmed.contains("://") {
+        return trimmed.to_string();
+    }
+
+    format!("https://{trimmed}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MatchBackend;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use std::fs;
+
+    /// Projects in fresh directories named after `names`, under a root of
+    /// their own for `test`.
+    fn projects(test: &str, names: &[&str]) -> (PathBuf, Vec<Project>) {
+        let root = std::env::temp_dir().join(format!("pl-app-{test}-{}", std::process::id()));
+        let projects = names
+            .iter()
+            .map(|name| {
+                let path = root.join(name);
+                fs::create_dir_all(&path).unwrap();
+                Project {
+                    project_name: name.into(),
+                    project_path: path,
+                    project_remote: String::new(),
+                    project_root: Some(root.clone()),
+                    bare: false,
+                    worktree: None,
+                    marker: None,
+                }
+            })
+            .collect();
+        (root, projects)
+    }
+
+    fn app(config: UserConfig, projects: Vec<Project>) -> App {
+        App::from_projects(config, State::default(), projects)
+    }
+
+    fn search(app: &mut App, query: &str) {
+        app.input = Input::new(query.to_string());
+        app.filter_results();
+    }
+
+    fn names(app: &App) -> Vec<String> {
+        app.visible_projects()
+            .iter()
+            .map(|project| project.project_name.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    fn draw(app: &mut App) {
+        let mut terminal = Terminal::new(TestBackend::new(80, 20)).unwrap();
+        terminal.draw(|frame| crate::ui::draw(frame, app)).unwrap();
+    }
+
+    #[test]
+    fn nothing_is_opened_without_a_match() {
+        let (root, projects) = projects("no-match", &["alpha", "beta"]);
+        let mut app = app(UserConfig::default(), projects);
+
+        search(&mut app, "zzz");
+        assert!(app.visible_projects().is_empty());
+        assert!(app.selected_project().is_none());
+        app.select_next();
+        app.select_previous();
+        app.open_selected_project();
+        draw(&mut app);
+
+        assert!(!app.should_exit());
+        assert!(app.take_open_targets().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn an_empty_list_draws_and_opens_nothing() {
+        let mut app = app(UserConfig::default(), Vec::new());
+
+        assert!(app.selected_project().is_none());
+        app.open_selected_project();
+        draw(&mut app);
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn a_deleted_project_is_dropped_instead_of_opened() {
+        let (root, projects) = projects("stale", &["alpha", "beta"]);
+        let beta = projects[1].project_path.clone();
+        let mut app = app(UserConfig::default(), projects);
+        search(&mut app, "beta");
+        fs::remove_dir_all(&beta).unwrap();
+
+        app.open_selected_project();
+
+        assert!(!app.should_exit());
+        assert!(app.take_open_targets().is_empty());
+        let (message, is_error) = app.status().unwrap();
+        assert!(is_error);
+        assert!(message.contains("no longer exists"), "{message}");
+        assert!(app.visible_projects().is_empty());
+        assert!(
+            app.projects
+                .iter()
+                .all(|project| project.project_path != beta)
+        );
+
+        search(&mut app, "");
+        assert_eq!(app.visible_projects().len(), 1);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn score_sort_puts_the_best_match_first() {
+        let (root, projects) = projects("score-sort", &["a-app", "app", "other"]);
+        let mut app = app(UserConfig::default(), projects);
+
+        search(&mut app, "app");
+        assert_eq!(names(&app), ["app", "a-app"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn stable_sort_keeps_the_base_order() {
+        let (root, projects) = projects("stable-sort", &["a-app", "app", "other"]);
+        let config = UserConfig {
+            filter_sort: FilterSort::Stable,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+
+        search(&mut app, "app");
+        assert_eq!(names(&app), ["a-app", "app"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn equal_scores_prefer_the_shorter_name_then_alphabetical() {
+        let names_in = ["api", "api-a", "api-b", "api-gateway"];
+        let (root, projects) = projects("tie-break", &names_in);
+        // Reversed, so the base order can't be what decides.
+        let config = UserConfig {
+            sort_reverse: true,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+        assert_eq!(names(&app), ["api-gateway", "api-b", "api-a", "api"]);
+
+        search(&mut app, "api");
+        assert_eq!(names(&app), names_in);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn layered_matching_orders_prefix_substring_fuzzy() {
+        let (root, projects) = projects("layered", &["a-plain-idea", "my-api", "apiary", "zoo"]);
+        let config = UserConfig {
+            match_backend: MatchBackend::Layered,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+
+        search(&mut app, "api");
+        assert_eq!(names(&app), ["apiary", "my-api", "a-plain-idea"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn smart_case_only_filters_by_case_with_an_uppercase_query() {
+        let (root, projects) = projects("smart-case", &["MyApp", "myapp-old", "other"]);
+        let mut app = app(UserConfig::default(), projects);
+
+        search(&mut app, "myapp");
+        assert_eq!(names(&app), ["MyApp", "myapp-old"]);
+        search(&mut app, "MyApp");
+        assert_eq!(names(&app), ["MyApp"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn group_order_puts_pins_then_frecent_then_the_rest() {
+        let (root, mut projects) = projects("groups", &["a", "b", "c", "d", "e"]);
+        let path = |name: &str| root.join(name);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let mut state = State {
+            pinned: vec![path("d"), path("b")],
+            ..State::default()
+        };
+        // `e` was opened three times, `c` only once.
+        state.opened_at.insert(path("c"), now);
+        state.opened_at.insert(path("e"), now);
+        state.open_counts.insert(path("e"), 3);
+        // `b` is both, so it goes in whichever group comes first.
+        state.opened_at.insert(path("b"), now);
+        let order = |projects: &[Project]| -> Vec<String> {
+            projects
+                .iter()
+                .map(|project| project.project_name.to_string_lossy().into_owned())
+                .collect()
+        };
+
+        group_projects(
+            &mut projects,
+            &[Group::Pinned, Group::Frecency, Group::Sort],
+            &state,
+        );
+        assert_eq!(order(&projects), ["d", "b", "e", "c", "a"]);
+
+        group_projects(&mut projects, &[Group::Frecency, Group::Pinned], &state);
+        assert_eq!(order(&projects), ["e", "b", "c", "d", "a"]);
+
+        // With no groups, the sort order is kept as it is.
+        group_projects(&mut projects, &[], &state);
+        assert_eq!(order(&projects), ["e", "b", "c", "d", "a"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn opening_a_filtered_project_opens_the_match() {
+        let (root, projects) = projects("filtered-open", &["alpha", "beta", "gamma"]);
+        let gamma = projects[2].project_path.clone();
+        let mut app = app(UserConfig::default(), projects);
+
+        search(&mut app, "gam");
+        assert_eq!(app.visible_projects().len(), 1);
+        app.open_selected_project();
+
+        assert!(app.should_exit());
+        assert_eq!(app.take_open_targets(), [gamma]);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn match_scores_rank_the_fixture_without_show_scores() {
+        let (root, projects) = projects(
+            "match-score",
+            &["crate-list", "ratatui-cli", "rust-cli", "readme"],
+        );
+        let config = UserConfig {
+            match_backend: MatchBackend::Layered,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+        let scores = |app: &App| -> Vec<Option<i64>> {
+            app.visible_projects()
+                .iter()
+                .map(|project| app.match_score(project))
+                .collect()
+        };
+        assert!(scores(&app).iter().all(Option::is_none));
+
+        search(&mut app, "rcli");
+        // The tighter match goes first; `crate-list` has no `c` after its `r`.
+        assert_eq!(names(&app), ["rust-cli", "ratatui-cli"]);
+        let found: Vec<i64> = scores(&app).into_iter().map(Option::unwrap).collect();
+        assert!(found.is_sorted_by(|a, b| a >= b), "{found:?}");
+        let readme = app.projects().iter().find(|p| p.project_name == "readme");
+        assert_eq!(app.match_score(readme.unwrap()), None);
+        assert_eq!(app.score(&app.visible_projects()[0]), None);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn highlights_cjk_names_by_character() {
+        let (root, projects) = projects("cjk", &["日本語プロジェクト", "english"]);
+        let mut app = app(UserConfig::default(), projects);
+
+        search(&mut app, "プロ");
+        assert_eq!(names(&app), ["日本語プロジェクト"]);
+        let project = app.visible_projects()[0].clone();
+        assert_eq!(app.match_indices(&project), [3, 4]);
+        draw(&mut app);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn dirty_filter_loads_statuses_in_the_background() {
+        let (root, projects) = projects("dirty-loading", &["alpha", "beta"]);
+        let mut app = app(UserConfig::default(), projects);
+
+        app.toggle_dirty_only();
+        assert!(app.dirty_loading());
+        assert!(names(&app).is_empty());
+        let started = Instant::now();
+        while app.dirty_loading() && started.elapsed() < Duration::from_secs(10) {
+            app.on_tick();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!app.dirty_loading());
+        // Neither directory is a repository, so neither is dirty.
+        assert!(names(&app).is_empty());
+        draw(&mut app);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn committed_sort_reads_times_in_the_background() {
+        let (root, projects) = projects("commit-sort", &["alpha", "beta"]);
+        let config = UserConfig {
+            sort_by: SortBy::Committed,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+
+        assert!(app.sorting());
+        assert_eq!(names(&app).len(), 2);
+        let started = Instant::now();
+        while app.sorting() && started.elapsed() < Duration::from_secs(10) {
+            app.on_tick();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!app.sorting());
+        assert_eq!(names(&app).len(), 2);
+        draw(&mut app);
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn quitting_after_a_stay_open_launch_opens_nothing() {
+        use crossterm::event::{KeyCode, KeyEvent};
+
+        let (root, projects) = projects("stay-open-quit", &["alpha", "beta"]);
+        let config = UserConfig {
+            stay_open: true,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+
+        app.open_selected_project();
+        assert!(!app.should_exit());
+        crate::input::handle_key_event(&mut app, KeyEvent::from(KeyCode::Char('q')));
+
+        assert!(app.should_exit());
+        assert!(app.take_open_targets().is_empty());
+        assert!(app.take_foreground_launches().is_empty());
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    fn selected_name(app: &App) -> Option<String> {
+        app.selected_project()
+            .map(|project| project.project_name.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn a_no_match_query_clears_the_selection_for_good() {
+        let (root, projects) = projects("no-keep", &["alpha", "beta", "gamma"]);
+        let mut app = app(UserConfig::default(), projects);
+        app.select_next();
+        assert_eq!(selected_name(&app).as_deref(), Some("beta"));
+
+        search(&mut app, "zzz");
+        assert_eq!(selected_name(&app), None);
+        search(&mut app, "zzzz");
+        assert_eq!(selected_name(&app), None);
+
+        search(&mut app, "");
+        assert_eq!(selected_name(&app).as_deref(), Some("alpha"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn keep_selection_on_empty_snaps_back_once_matches_return() {
+        let (root, projects) = projects("keep", &["alpha", "beta", "gamma"]);
+        let config = UserConfig {
+            keep_selection_on_empty: true,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+        app.select_next();
+        assert_eq!(selected_name(&app).as_deref(), Some("beta"));
+
+        search(&mut app, "zzz");
+        assert_eq!(selected_name(&app), None);
+        search(&mut app, "zzzz");
+        assert_eq!(selected_name(&app), None);
+
+        search(&mut app, "");
+        assert_eq!(selected_name(&app).as_deref(), Some("beta"));
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn renaming_moves_the_project_through_the_state() {
+        let (root, projects) = projects("rename", &["alpha", "beta"]);
+        let (alpha, beta) = (root.join("alpha"), root.join("beta"));
+        let mut app = app(UserConfig::default(), projects);
+        app.state_store.recently_opened = vec![beta.clone(), alpha.clone()];
+        assert!(app.state_store.seen_projects.contains(&alpha));
+
+        app.rename_project(alpha.clone(), "gamma");
+
+        let gamma = root.join("gamma");
+        assert!(gamma.is_dir());
+        assert_eq!(app.state_store.recently_opened, [beta, gamma.clone()]);
+        assert!(!app.state_store.seen_projects.contains(&alpha));
+        assert!(app.state_store.seen_projects.contains(&gamma));
+        assert_eq!(names(&app), ["beta", "gamma"]);
+
+        app.rename_project(gamma, "beta");
+        let (message, is_error) = app.status().unwrap();
+        assert!(is_error);
+        assert!(message.contains("already exists"), "{message}");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn open_all_refuses_more_than_open_all_limit() {
+        let (root, projects) = projects("open-all-limit", &["alpha", "beta"]);
+        let config = UserConfig {
+            open_all_limit: 1,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+
+        app.request_open_all();
+        assert!(app.pending_action.is_none());
+        let (message, is_error) = app.status().unwrap();
+        assert!(is_error);
+        assert!(message.contains("open_all_limit is 1"), "{message}");
+
+        app.user_config.open_all_limit = 2;
+        app.request_open_all();
+        assert!(
+            matches!(app.pending_action, Some(PendingAction::OpenAll(ref paths)) if paths.len() == 2)
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn equal_scores_prefer_fewer_characters_not_bytes() {
+        let (root, projects) = projects("tie-chars", &["cafe-apii", "café-api"]);
+        let config = UserConfig {
+            match_backend: MatchBackend::Layered,
+            ..UserConfig::default()
+        };
+        let mut app = app(config, projects);
+
+        search(&mut app, "caf");
+        assert_eq!(names(&app), ["café-api", "cafe-apii"]);
+        fs::remove_dir_all(root).unwrap();
+    }
 }
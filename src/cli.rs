@@ -0,0 +1,110 @@
+use crate::config::config_path;
+
+/// Command-line options. Anything not given here comes from the config file.
+#[derive(Debug, Default)]
+pub struct Cli {
+    pub version: bool,
+    pub help: bool,
+    pub unignore: bool,
+    /// Print the discovered projects instead of starting the TUI.
+    pub list: bool,
+    /// With `list`, print them as JSON.
+    pub json: bool,
+    /// Directories to scan for this run instead of `project_dirs`.
+    pub dirs: Vec<String>,
+    /// Scan `dirs` in addition to `project_dirs` rather than instead.
+    pub extend_dirs: bool,
+    /// Print the chosen project's path instead of opening it.
+    pub print: bool,
+    /// Search text to start with.
+    pub query: Option<String>,
+    /// Pick the only match without starting the TUI.
+    pub select_one: bool,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+    let mut cli = Cli::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if let Some(query) = arg.strip_prefix("--query=") {
+            cli.query = Some(query.to_string());
+            continue;
+        }
+        match arg.as_str() {
+            "-V" | "--version" => cli.version = true,
+            "-h" | "--help" => cli.help = true,
+            "--unignore" => cli.unignore = true,
+            "-l" | "--list" => cli.list = true,
+            "--json" => {
+                cli.list = true;
+                cli.json = true;
+            }
+            "-e" | "--extend" => cli.extend_dirs = true,
+            "-p" | "--print" => cli.print = true,
+            "-q" | "--query" => match args.next() {
+                Some(query) => cli.query = Some(query),
+                None => return Err(format!("'{arg}' needs a value")),
+            },
+            "-1" | "--select-1" => cli.select_one = true,
+            _ if arg.starts_with('-') => return Err(format!("unknown argument '{arg}'")),
+            _ => cli.dirs.push(arg),
+        }
+    }
+
+    Ok(cli)
+}
+
+pub fn version() -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    let build_date = env!("PL_BUILD_DATE");
+    match option_env!("PL_GIT_COMMIT") {
+        Some(commit) => format!("pl {version} ({commit} {build_date})"),
+        None => format!("pl {version} ({build_date})"),
+    }
+}
+
+pub fn help() -> String {
+    let config = config_path().map_or_else(
+        || "<config dir>/pl/config.toml".to_string(),
+        |path| path.display().to_string(),
+    );
+
+    let log = crate::logging::log_path().map_or_else(
+        || "<state dir>/pl/pl.log".to_string(),
+        |path| path.display().to_string(),
+    );
+
+    format!(
+        "\
+pl - a terminal UI project launcher
+
+Usage: pl [OPTIONS] [DIR]...
+
+Arguments:
+  [DIR]...  Directories to scan instead of project_dirs for this run
+
+Options:
+  -h, --help      Print this help and exit
+  -V, --version   Print version and build info and exit
+  -e, --extend    Scan the DIR arguments in addition to project_dirs
+  -l, --list      Print each project's name and path, tab-separated, and exit
+      --json      Like --list, but print a JSON array of {{\"name\", \"path\"}}
+      --unignore  Clear the list of projects hidden with x, printing each one
+  -p, --print     Print the chosen project's path instead of opening it; the
+                  TUI draws on stderr, so `cd \"$(pl --print)\"` works
+  -q, --query <TEXT>
+                  Start with TEXT in the search box
+  -1, --select-1  Pick the project straight away if only one matches
+
+Configuration is read from {config}
+(TOML; every key is optional). For example:
+
+  project_dirs = [\"~/Projects\"]
+  editor_command = \"code --wait\"  # $EDITOR when unset
+
+Set PL_LOG=debug to log to {log}.
+
+Press ? inside pl to list the keybindings."
+    )
+}
@@ -1,36 +1,875 @@
 use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct UserConfig {
-    pub project_dirs: Vec<String>,
+    pub project_dirs: Vec<ProjectDir>,
+    /// Files or directories whose presence makes a directory a project.
+    pub project_markers: Vec<String>,
+    /// How many levels below each root to look, for roots that don't set
+    /// their own `max_depth`.
+    pub max_depth: usize,
+    /// Globs for directories the scan skips, under every root.
+    pub exclude: Vec<String>,
+    pub on_empty: OnEmpty,
+    pub projects: Vec<String>,
+    pub detect_bare: bool,
+    pub include_worktrees: bool,
+    pub scan_timeout_ms: Option<u64>,
+    pub project_source_command: Option<String>,
+    pub use_ghq: bool,
+    /// Template name to what new projects made from it start with.
+    pub templates: BTreeMap<String, Template>,
+    /// Open projects in the editor once `n` has created them.
+    pub open_on_create: bool,
+    /// Pick a branch before opening a repository that has several.
+    pub pick_branch_on_open: bool,
     pub editor_command: String,
+    pub editor_query_template: Option<String>,
+    pub open_mode: OpenMode,
+    pub url_template: String,
+    pub editor_detach: bool,
+    pub editor_retries: u32,
+    pub editor_cwd: bool,
+    pub editor_server: Option<String>,
+    pub editor_server_command: String,
+    pub stay_open: bool,
+    pub quit_on_exit_code: Option<i32>,
+    pub pre_open_command: Option<String>,
+    /// Opens several marked projects at once, with `{paths}` for all of
+    /// them.
+    pub multi_open_command: Option<String>,
+    pub on_select_command: Option<String>,
+    pub allow_project_pre_open: bool,
+    pub preview_files: Vec<String>,
+    /// Make `>` searches read source files too, not just the preview file.
+    pub content_search_source: bool,
+    /// Source files bigger than this many KiB are left out of `>` searches.
+    pub content_search_max_kb: u64,
+    pub doc_files: Vec<String>,
+    /// Globs or project types whose preview is left blank.
+    pub no_preview_for: Vec<String>,
+    pub show_size: bool,
+    pub show_reading_time: bool,
+    pub absolute_paths: bool,
+    pub show_git_status: bool,
+    pub git_status_debounce_ms: u64,
+    pub show_stash_indicator: bool,
+    pub show_detached_indicator: bool,
+    pub colorize_by_root: bool,
+    pub show_hierarchy: bool,
+    pub collapse_input: bool,
+    pub collapse_clears_query: bool,
+    pub editing_enter: EditingEnter,
+    pub create_on_enter: bool,
+    pub start_mode: StartMode,
+    pub preview_show_hidden: bool,
+    pub fold_markdown: bool,
+    pub sort_by: SortBy,
+    pub sort_reverse: bool,
+    pub search_scope: SearchScope,
+    pub use_git_description: bool,
+    pub normalize_diacritics: bool,
+    pub tick_rate_ms: u64,
+    pub auto_rescan_secs: u64,
+    pub title: Option<String>,
+    pub layout: LayoutConfig,
+    pub theme: ThemeConfig,
+    pub hooks: Hooks,
+    pub filter_sort: FilterSort,
+    pub group_order: Vec<Group>,
+    pub sticky_selection: bool,
+    pub keep_selection_on_empty: bool,
+    pub match_backend: MatchBackend,
+    pub case: Case,
+    pub max_results: Option<usize>,
+    pub archive_dir: Option<String>,
+    pub show_scores: bool,
+    pub mru_size: usize,
+    pub show_last_opened: bool,
+    pub show_commit_activity: bool,
+    pub activity_weeks: usize,
+    pub confirm_destructive: bool,
+    pub confirm_open_all_over: usize,
+    /// The most projects `Alt-Enter` opens; a longer list is refused before
+    /// `confirm_open_all_over` would ask.
+    pub open_all_limit: usize,
+    pub number_keys: bool,
+    pub scroll_acceleration: bool,
+    pub leader_key: char,
+    /// Commands of your own on the leader menu, from `[[actions]]` tables.
+    pub actions: Vec<Action>,
+    /// The `[keys]` section: extra keys for list actions.
+    pub keys: BTreeMap<KeyAction, KeySpecs>,
+    pub highlight_symbol: String,
+    pub item_prefix: String,
+}
+
+/// The `[layout]` section: how the left column is stacked.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// Regions from top to bottom. `input` and `list` are added at the end
+    /// when left out.
+    pub order: Vec<Region>,
+    /// Height of the search box, borders included.
+    pub input_height: u16,
+    /// Match count shown at the right of the search box, with `{matched}`
+    /// and `{total}` filled in. Empty to hide it.
+    pub count_format: String,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            order: vec![Region::Input, Region::List, Region::Status],
+            input_height: 3,
+            count_format: "{matched}/{total}".to_string(),
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// `order` without duplicates and with the required regions present.
+    pub fn regions(&self) -> Vec<Region> {
+        let mut regions = Vec::new();
+        for region in self.order.iter().chain(&[Region::Input, Region::List]) {
+            if !regions.contains(region) {
+                regions.push(*region);
+            }
+        }
+        regions
+    }
+}
+
+/// A `[templates]` entry: a directory to copy into new projects, or a table
+/// that can also run commands in them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Template {
+    Dir(String),
+    Recipe(TemplateRecipe),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TemplateRecipe {
+    /// A directory to copy in first, as a plain entry names.
+    pub path: Option<String>,
+    /// Run one after another in the new directory, with `{name}`, `{path}`
+    /// and `{input}` filled in.
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Asked for after picking the template, such as a URL to clone; the
+    /// answer fills in `{input}`.
+    pub prompt: Option<String>,
+}
+
+impl Template {
+    pub fn dir(&self) -> Option<&str> {
+        match self {
+            Template::Dir(dir) => Some(dir),
+            Template::Recipe(recipe) => recipe.path.as_deref(),
+        }
+    }
+
+    pub fn commands(&self) -> &[String] {
+        match self {
+            Template::Dir(_) => &[],
+            Template::Recipe(recipe) => &recipe.commands,
+        }
+    }
+
+    pub fn prompt(&self) -> Option<&str> {
+        match self {
+            Template::Dir(_) => None,
+            Template::Recipe(recipe) => recipe.prompt.as_deref(),
+        }
+    }
+}
+
+/// An `[[actions]]` entry: a command on the leader menu, run on the selected
+/// project with `{path}` and `{name}` filled in. One with the key of a
+/// built-in action replaces it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Action {
+    pub key: char,
+    pub description: String,
+    pub command: String,
+    /// Hand the terminal to the command and wait for it, for interactive
+    /// programs like `lazygit`. Otherwise it runs in the background with its
+    /// output discarded.
+    #[serde(default)]
+    pub wait: bool,
+}
+
+/// An action that `[keys]` can bind, named like `select_next`. Each one
+/// does what its default key does in the current mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    Quit,
+    SelectNext,
+    SelectPrev,
+    SelectFirst,
+    SelectLast,
+    Open,
+    OpenAll,
+    OpenParent,
+    OpenRemote,
+    OpenSession,
+    PickBranch,
+    /// Start searching, or stop while searching.
+    Filter,
+    Leader,
+    Shell,
+    Reveal,
+    CopyPath,
+    TogglePin,
+    ToggleMark,
+    ClearMarks,
+    NextSort,
+    ReverseSort,
+    NextRoot,
+    PrevRoot,
+    FocusPreview,
+    Rescan,
+    Info,
+    Help,
+}
+
+impl KeyAction {
+    /// What the action does, for the help.
+    pub fn description(self) -> &'static str {
+        match self {
+            KeyAction::Quit => "quit",
+            KeyAction::SelectNext => "move down",
+            KeyAction::SelectPrev => "move up",
+            KeyAction::SelectFirst => "go to first",
+            KeyAction::SelectLast => "go to last",
+            KeyAction::Open => "open project",
+            KeyAction::OpenAll => "open all matching projects",
+            KeyAction::OpenParent => "open the parent directory",
+            KeyAction::OpenRemote => "open remote in browser",
+            KeyAction::OpenSession => "open in a tmux / zellij session",
+            KeyAction::PickBranch => "pick a branch to open",
+            KeyAction::Filter => "search / stop searching",
+            KeyAction::Leader => "leader: actions on the selected project",
+            KeyAction::Shell => "shell in the project",
+            KeyAction::Reveal => "reveal in Finder / Explorer",
+            KeyAction::CopyPath => "copy the project path",
+            KeyAction::TogglePin => "pin / unpin project",
+            KeyAction::ToggleMark => "mark to open together",
+            KeyAction::ClearMarks => "clear marks",
+            KeyAction::NextSort => "cycle sort",
+            KeyAction::ReverseSort => "reverse sort direction",
+            KeyAction::NextRoot => "next project_dirs root",
+            KeyAction::PrevRoot => "previous project_dirs root",
+            KeyAction::FocusPreview => "focus preview",
+            KeyAction::Rescan => "rescan project_dirs",
+            KeyAction::Info => "show project info",
+            KeyAction::Help => "toggle this help",
+        }
+    }
+}
+
+/// One key spec, like `"ctrl-j"`, or a list of them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeySpecs {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeySpecs {
+    pub fn specs(&self) -> &[String] {
+        match self {
+            KeySpecs::One(spec) => std::slice::from_ref(spec),
+            KeySpecs::Many(specs) => specs,
+        }
+    }
+}
+
+/// The `[hooks]` section: commands run in the background at points in the
+/// app's lifecycle, with `{path}` and `{query}` filled in.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// Each project opened.
+    pub on_open: Option<String>,
+    /// The selection settling on a project, like `on_select_command`.
+    pub on_select: Option<String>,
+    /// The TUI starting.
+    pub on_start: Option<String>,
+    /// Quitting, with the project selected at the time.
+    pub on_quit: Option<String>,
+}
+
+/// The `[theme]` section. Colors are names like `"yellow"`, indices like
+/// `"208"` or hex like `"#e5c07b"`; an empty or unknown one leaves the text
+/// uncolored.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Repositories with uncommitted changes, with `show_git_status`.
+    pub dirty: String,
+    /// Repositories without uncommitted changes.
+    pub clean: String,
+    /// What the `dirty` and `clean` colors apply to.
+    pub git_status_target: StatusTarget,
+    /// The selected row of the list and of popups, and a focused preview's
+    /// border.
+    pub highlight: String,
+    /// Behind the selected row.
+    pub highlight_bg: String,
+    /// Panel and popup borders.
+    pub border: String,
+    /// Panel and popup titles.
+    pub title: String,
+    /// The query while typing it.
+    pub input: String,
+    /// Preview text that markdown or git output doesn't color itself.
+    pub preview: String,
+    /// The matched characters of names and of content search hits.
+    #[serde(rename = "match")]
+    pub matched: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            dirty: "yellow".to_string(),
+            clean: String::new(),
+            git_status_target: StatusTarget::default(),
+            highlight: "cyan".to_string(),
+            highlight_bg: String::new(),
+            border: String::new(),
+            title: "red".to_string(),
+            input: "cyan".to_string(),
+            preview: String::new(),
+            matched: "yellow".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusTarget {
+    /// The `*` of a dirty repository and the branch name of a clean one.
+    #[default]
+    Marker,
+    /// The whole list row.
+    Row,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Region {
+    Input,
+    List,
+    /// A one-line status bar. Without it, statuses show in the list border.
+    Status,
+}
+
+/// Which project text the search query is matched against.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchScope {
+    #[default]
+    Name,
+    Remote,
+    All,
 }
 
 impl Default for UserConfig {
     fn default() -> Self {
         Self {
-            project_dirs: vec!["~/Projects".to_string()],
-            editor_command: "code".to_string(),
+            project_dirs: vec![ProjectDir::Path("~/Projects".to_string())],
+            project_markers: vec![".git".to_string()],
+            max_depth: 1,
+            exclude: Vec::new(),
+            on_empty: OnEmpty::default(),
+            projects: Vec::new(),
+            detect_bare: false,
+            include_worktrees: true,
+            scan_timeout_ms: None,
+            project_source_command: None,
+            use_ghq: false,
+            templates: BTreeMap::new(),
+            open_on_create: true,
+            pick_branch_on_open: false,
+            editor_command: String::new(),
+            editor_query_template: None,
+            open_mode: OpenMode::default(),
+            url_template: "vscode://file/{path}".to_string(),
+            editor_detach: false,
+            editor_retries: 2,
+            editor_cwd: false,
+            editor_server: None,
+            editor_server_command: "nvim --server {server} --remote {path}".to_string(),
+            stay_open: false,
+            quit_on_exit_code: None,
+            pre_open_command: None,
+            multi_open_command: None,
+            on_select_command: None,
+            allow_project_pre_open: false,
+            preview_files: ["README.md", "README", "README.rst", "README.txt"]
+                .map(String::from)
+                .to_vec(),
+            content_search_source: false,
+            content_search_max_kb: 256,
+            no_preview_for: Vec::new(),
+            doc_files: [
+                "CONTRIBUTING.md",
+                "CHANGELOG.md",
+                "ARCHITECTURE.md",
+                "SECURITY.md",
+                "CODE_OF_CONDUCT.md",
+            ]
+            .map(String::from)
+            .to_vec(),
+            show_size: false,
+            show_reading_time: true,
+            absolute_paths: false,
+            show_git_status: false,
+            git_status_debounce_ms: 100,
+            show_stash_indicator: true,
+            show_detached_indicator: true,
+            colorize_by_root: false,
+            show_hierarchy: false,
+            collapse_input: false,
+            collapse_clears_query: false,
+            editing_enter: EditingEnter::default(),
+            create_on_enter: false,
+            start_mode: StartMode::default(),
+            preview_show_hidden: false,
+            fold_markdown: false,
+            sort_by: SortBy::default(),
+            sort_reverse: false,
+            search_scope: SearchScope::default(),
+            use_git_description: false,
+            normalize_diacritics: false,
+            tick_rate_ms: 250,
+            auto_rescan_secs: 0,
+            title: None,
+            layout: LayoutConfig::default(),
+            theme: ThemeConfig::default(),
+            hooks: Hooks::default(),
+            filter_sort: FilterSort::default(),
+            group_order: vec![Group::Pinned],
+            sticky_selection: false,
+            keep_selection_on_empty: false,
+            match_backend: MatchBackend::default(),
+            case: Case::default(),
+            max_results: None,
+            archive_dir: None,
+            show_scores: false,
+            mru_size: 10,
+            show_last_opened: false,
+            show_commit_activity: false,
+            activity_weeks: 12,
+            confirm_destructive: true,
+            confirm_open_all_over: 5,
+            open_all_limit: 20,
+            number_keys: true,
+            scroll_acceleration: false,
+            leader_key: ' ',
+            actions: Vec::new(),
+            keys: BTreeMap::new(),
+            highlight_symbol: "> ".to_string(),
+            item_prefix: "  ".to_string(),
+        }
+    }
+}
+
+/// What to do when no projects are found at all.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnEmpty {
+    /// Explain how to point `pl` at some projects.
+    #[default]
+    Message,
+    /// Look for repositories directly inside the home directory instead.
+    ScanHome,
+    /// Ask for a directory to scan.
+    PromptAdd,
+}
+
+/// The sort order the list starts in.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// How often and how recently each project was opened from `pl`, then
+    /// name for the rest.
+    #[default]
+    Frecency,
+    Name,
+    /// Directory modification time.
+    Modified,
+    /// Time of the latest commit.
+    #[serde(alias = "last_commit")]
+    Committed,
+}
+
+/// How projects are handed to the editor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpenMode {
+    /// Run `editor_command`.
+    #[default]
+    Editor,
+    /// Open `url_template` with the system URL handler.
+    Url,
+    /// Switch to, or attach to, a tmux session named after the project,
+    /// creating it in the project directory first if need be.
+    Tmux,
+    /// The same with zellij; inside zellij, a new tab instead.
+    Zellij,
+}
+
+impl OpenMode {
+    /// The session manager `t` opens projects with: zellij when it is the
+    /// `open_mode`, tmux otherwise.
+    pub fn session(self) -> Self {
+        match self {
+            OpenMode::Zellij => OpenMode::Zellij,
+            _ => OpenMode::Tmux,
         }
     }
 }
 
+/// Whether `pl` starts with the cursor in the search box.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StartMode {
+    /// Start in the list, navigating with `j` / `k`.
+    #[default]
+    Normal,
+    /// Start typing a search right away.
+    Search,
+}
+
+/// What Enter does while typing a search.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EditingEnter {
+    /// Open the selected project, like a fuzzy finder.
+    #[default]
+    Open,
+    /// Leave search and go back to the list.
+    Confirm,
+}
+
+/// Which matcher ranks search results.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchBackend {
+    /// The query must appear as one contiguous piece.
+    Substring,
+    /// The query's characters must appear in order, possibly spread out.
+    Fuzzy,
+    /// Prefix matches first, then other substring matches, then fuzzy ones.
+    #[default]
+    Layered,
+    /// The `nucleo-matcher` crate's fuzzy matching, from the `nucleo` feature.
+    Nucleo,
+    /// The skim algorithm from the `fuzzy-matcher` crate, from the `skim`
+    /// feature.
+    Skim,
+}
+
+/// Whether search queries care about case.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Case {
+    /// Ignore case until the query contains an uppercase letter.
+    #[default]
+    Smart,
+    Sensitive,
+    Insensitive,
+}
+
+/// How the filtered results are ordered while a query is active.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterSort {
+    /// Best matches first, ties kept in the base order.
+    #[default]
+    Score,
+    /// Keep the base order and only drop non-matches.
+    Stable,
+}
+
+/// A segment of the list in `group_order`. Each project lands in the first
+/// listed segment that claims it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Group {
+    /// Pinned projects, in pin order.
+    Pinned,
+    /// Projects opened from `pl` before, most frecent first.
+    Frecency,
+    /// Everything else, in the sort order or search ranking.
+    Sort,
+}
+
+/// A `project_dirs` entry: either a bare path or a table that also overrides
+/// settings for the projects found under it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ProjectDir {
+    Path(String),
+    Table(ProjectDirSettings),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectDirSettings {
+    pub path: String,
+    /// How many levels below the root to look for projects. Defaults to the
+    /// global `max_depth`.
+    pub max_depth: Option<usize>,
+    /// Editor for projects under this root instead of the global one.
+    pub editor_command: Option<String>,
+    /// When `false`, directories without any of the `project_markers` count
+    /// as projects too.
+    pub require_git: Option<bool>,
+}
+
+impl ProjectDir {
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Path(path) => path,
+            Self::Table(settings) => &settings.path,
+        }
+    }
+
+    /// This root's `max_depth`, or `default` when it has none.
+    pub fn max_depth(&self, default: usize) -> usize {
+        let depth = match self {
+            Self::Table(ProjectDirSettings {
+                max_depth: Some(depth),
+                ..
+            }) => *depth,
+            _ => default,
+        };
+        depth.max(1)
+    }
+
+    pub fn require_git(&self) -> bool {
+        match self {
+            Self::Table(settings) => settings.require_git.unwrap_or(true),
+            Self::Path(_) => true,
+        }
+    }
+}
+
+impl UserConfig {
+    /// Describes every setting that decides what a scan finds, so a cached
+    /// scan can be matched to the settings it came from.
+    pub fn scan_key(&self) -> String {
+        format!(
+            "{:?}",
+            (
+                &self.project_dirs,
+                &self.project_markers,
+                self.max_depth,
+                &self.exclude,
+                &self.projects,
+                self.detect_bare,
+                self.include_worktrees,
+                &self.project_source_command,
+                self.use_ghq,
+            )
+        )
+    }
+
+    /// The editor for `path`: the `editor_command` of the innermost
+    /// `project_dirs` root containing it, or the global one. An empty one
+    /// falls back to `$EDITOR`, then `$VISUAL`, then `nvim`.
+    pub fn editor_for(&self, path: &Path) -> String {
+        let editor = self
+            .project_dirs
+            .iter()
+            .filter_map(|dir| match dir {
+                ProjectDir::Table(settings) => {
+                    let root = crate::project::parse_dir(&settings.path);
+                    let editor = settings.editor_command.as_deref()?;
+                    path.starts_with(&root).then_some((root, editor))
+                }
+                ProjectDir::Path(_) => None,
+            })
+            .max_by_key(|(root, _)| root.components().count())
+            .map_or(self.editor_command.as_str(), |(_, editor)| editor);
+        editor_or_fallback(editor, |name| std::env::var(name).ok())
+    }
+}
+
+/// `editor` unless it is blank, else the first non-blank of `$EDITOR` and
+/// `$VISUAL` as read by `var`, else `nvim`.
+fn editor_or_fallback(editor: &str, var: impl Fn(&str) -> Option<String>) -> String {
+    if !editor.trim().is_empty() {
+        return editor.to_string();
+    }
+
+    ["EDITOR", "VISUAL"]
+        .into_iter()
+        .filter_map(var)
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| "nvim".to_string())
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("pl").join("config.toml"))
+}
+
+/// Loads the first of `config.toml`, `config.json`, `config.yaml` and
+/// `config.yml` that exists, or the defaults when there is none.
 pub fn load_user_config() -> io::Result<UserConfig> {
-    let config_dir = dirs::config_dir()
+    let toml_path = config_path()
         .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "config directory not found"))?;
 
-    let config_path = config_dir.join("pl").join("config.toml");
+    let formats = [
+        ("toml", "TOML"),
+        ("json", "JSON"),
+        ("yaml", "YAML"),
+        ("yml", "YAML"),
+    ];
 
-    match fs::read_to_string(&config_path) {
-        Ok(raw) => toml::from_str(&raw).map_err(|e| {
-            io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("invalid config at {}: {e}", config_path.display()),
-            )
-        }),
-        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(UserConfig::default()),
-        Err(err) => Err(err),
+    for (extension, format) in formats {
+        let path = toml_path.with_extension(extension);
+        match fs::read_to_string(&path) {
+            Ok(raw) => {
+                log::info!("reading config from {}", path.display());
+                return parse_config(&raw, format).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid {format} config at {}: {e}", path.display()),
+                    )
+                });
+            }
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            Err(_) => {}
+        }
+    }
+
+    log::info!("no config at {}, using defaults", toml_path.display());
+    Ok(UserConfig::default())
+}
+
+/// Parses a config written in `format`. In JSON and YAML a `null` value
+/// leaves its key at the default, as a missing one would.
+fn parse_config(raw: &str, format: &str) -> Result<UserConfig, String> {
+    match format {
+        "TOML" => toml::from_str(raw).map_err(|e| e.to_string()),
+        "JSON" => {
+            let mut value: serde_json::Value =
+                serde_json::from_str(raw).map_err(|e| e.to_string())?;
+            drop_json_nulls(&mut value);
+            serde_json::from_value(value).map_err(|e| e.to_string())
+        }
+        _ => {
+            let mut value: serde_yaml::Value =
+                serde_yaml::from_str(raw).map_err(|e| e.to_string())?;
+            drop_yaml_nulls(&mut value);
+            serde_yaml::from_value(value).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Removes `null` object members, at any depth, so they read as missing.
+pub(crate) fn drop_json_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(object) => {
+            object.retain(|_, value| !value.is_null());
+            object.values_mut().for_each(drop_json_nulls);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(drop_json_nulls),
+        _ => {}
+    }
+}
+
+fn drop_yaml_nulls(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            mapping.retain(|_, value| !value.is_null());
+            mapping.values_mut().for_each(drop_yaml_nulls);
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(drop_yaml_nulls),
+        _ => {}
+    }
+}
+
+/// Per-project settings read from a `.pl` file in the project root.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// Command run from the project root instead of the editor.
+    pub open: Option<String>,
+    /// Editor used for this project instead of `editor_command`.
+    pub editor: Option<String>,
+    /// Command run before opening, only honored with `allow_project_pre_open`.
+    pub pre_open: Option<String>,
+}
+
+/// Loads `.pl` from the project root, treating a missing or invalid file as empty.
+pub fn load_project_config(project_path: &Path) -> ProjectConfig {
+    fs::read_to_string(project_path.join(".pl"))
+        .ok()
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn a_configured_editor_wins_over_the_environment() {
+        let vars = [("EDITOR", "vim")];
+        assert_eq!(editor_or_fallback("code --wait", env(&vars)), "code --wait");
+    }
+
+    #[test]
+    fn a_blank_editor_falls_back_to_editor_then_visual() {
+        let vars = [("EDITOR", "vim"), ("VISUAL", "emacs")];
+        assert_eq!(editor_or_fallback("", env(&vars)), "vim");
+
+        let vars = [("EDITOR", "  "), ("VISUAL", "emacs")];
+        assert_eq!(editor_or_fallback(" ", env(&vars)), "emacs");
+    }
+
+    #[test]
+    fn without_any_editor_it_is_nvim() {
+        assert_eq!(editor_or_fallback("", env(&[])), "nvim");
+    }
+
+    #[test]
+    fn json_and_yaml_nulls_leave_the_default() {
+        let default = UserConfig::default();
+        let json = r#"{"editor_command": "hx", "tick_rate_ms": null, "max_results": 5}"#;
+        let yaml = "editor_command: hx\ntick_rate_ms: ~\nmax_results: 5\n";
+
+        for (raw, format) in [(json, "JSON"), (yaml, "YAML")] {
+            let config = parse_config(raw, format).unwrap();
+            assert_eq!(config.editor_command, "hx");
+            assert_eq!(config.tick_rate_ms, default.tick_rate_ms);
+            assert_eq!(config.max_results, Some(5));
+        }
+    }
+
+    #[test]
+    fn invalid_json_and_yaml_configs_are_errors() {
+        assert!(parse_config(r#"{"tick_rate_ms": "soon"}"#, "JSON").is_err());
+        assert!(parse_config("tick_rate_ms: [1", "YAML").is_err());
     }
 }
@@ -0,0 +1,428 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct GitStatus {
+    /// `None` on a detached `HEAD`.
+    pub branch: Option<String>,
+    pub dirty: bool,
+    /// Commits the branch is ahead of and behind its upstream; zero for
+    /// both without one.
+    pub ahead: u32,
+    pub behind: u32,
+    /// Whether `git stash list` has entries; only checked on request.
+    pub stashed: bool,
+}
+
+/// Reads the branch and working-tree state with `git status`, and with
+/// `check_stashes` whether anything is stashed.
+pub fn status(project_path: &Path, check_stashes: bool) -> Option<GitStatus> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "--branch"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let header = lines.next()?.strip_prefix("## ")?;
+
+    let (ahead, behind) = parse_ahead_behind(header);
+    Some(GitStatus {
+        branch: parse_branch(header),
+        dirty: lines.next().is_some(),
+        ahead,
+        behind,
+        stashed: check_stashes && has_stashes(project_path),
+    })
+}
+
+fn has_stashes(project_path: &Path) -> bool {
+    Command::new("git")
+        .args(["stash", "list"])
+        .current_dir(project_path)
+        .output()
+        .is_ok_and(|output| output.status.success() && !output.stdout.is_empty())
+}
+
+/// Parses the branch out of a `git status --branch` header such as
+/// `main...origin/main [ahead 1]` or `No commits yet on main`.
+fn parse_branch(header: &str) -> Option<String> {
+    if header.starts_with("HEAD (no branch)") {
+        return None;
+    }
+
+    let header = header
+        .strip_prefix("No commits yet on ")
+        .or_else(|| header.strip_prefix("Initial commit on "))
+        .unwrap_or(header);
+    let branch = header.split("...").next()?.split(' ').next()?;
+    Some(branch.to_string())
+}
+
+/// Parses the `[ahead 1, behind 2]` part of a `git status --branch`
+/// header; either count is left out when it is zero.
+fn parse_ahead_behind(header: &str) -> (u32, u32) {
+    let Some(counts) = header
+        .rsplit_once(" [")
+        .and_then(|(_, counts)| counts.strip_suffix(']'))
+    else {
+        return (0, 0);
+    };
+
+    let (mut ahead, mut behind) = (0, 0);
+    for count in counts.split(", ") {
+        match count.split_once(' ') {
+            Some(("ahead", n)) => ahead = n.parse().unwrap_or(0),
+            Some(("behind", n)) => behind = n.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    (ahead, behind)
+}
+
+/// Resolves the commit `HEAD` points at by reading `.git` directly.
+pub fn head_commit(project_path: &Path) -> Option<String> {
+    let git_dir = project_path.join(".git");
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    let Some(reference) = head.strip_prefix("ref: ") else {
+        return Some(head.to_string());
+    };
+
+    if let Ok(commit) = fs::read_to_string(git_dir.join(reference)) {
+        return Some(commit.trim().to_string());
+    }
+
+    let packed_refs = fs::read_to_string(git_dir.join("packed-refs")).ok()?;
+    packed_refs.lines().find_map(|line| {
+        let (commit, name) = line.split_once(' ')?;
+        (name == reference).then(|| commit.to_string())
+    })
+}
+
+/// The checked-out branch, or the short commit on a detached `HEAD`, read
+/// from `HEAD` without running git. Follows a `.git` file to the real git
+/// directory, as in worktrees and submodules. `None` outside a repository.
+pub fn head_branch(project_path: &Path) -> Option<String> {
+    let dot_git = project_path.join(".git");
+    let git_dir = if dot_git.is_file() {
+        let pointer = fs::read_to_string(&dot_git).ok()?;
+        project_path.join(pointer.trim().strip_prefix("gitdir:")?.trim())
+    } else {
+        dot_git
+    };
+
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    Some(match head.strip_prefix("ref: ") {
+        Some(reference) => reference
+            .strip_prefix("refs/heads/")
+            .unwrap_or(reference)
+            .to_string(),
+        None => head.chars().take(7).collect(),
+    })
+}
+
+/// The branch checked out in a linked worktree (made by `git worktree add`),
+/// whose `.git` is a file pointing into the main repository's
+/// `.git/worktrees`, or the short commit on a detached `HEAD`. `None` for any
+/// other checkout, including a submodule, whose `.git` file points elsewhere.
+pub fn worktree_branch(project_path: &Path) -> Option<String> {
+    let dot_git = project_path.join(".git");
+    if !dot_git.is_file() {
+        return None;
+    }
+
+    let pointer = fs::read_to_string(dot_git).ok()?;
+    let git_dir = project_path.join(pointer.trim().strip_prefix("gitdir:")?.trim());
+    if git_dir.parent()?.file_name()? != "worktrees" {
+        return None;
+    }
+
+    let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    Some(match head.strip_prefix("ref: ") {
+        Some(reference) => reference
+            .strip_prefix("refs/heads/")
+            .unwrap_or(reference)
+            .to_string(),
+        None => head.chars().take(7).collect(),
+    })
+}
+
+/// The linked worktrees of the repository at `project_path`, read from the
+/// `gitdir` files in its `.git/worktrees` (or `worktrees`, when bare). Ones
+/// whose directory has since been deleted are left out.
+pub fn linked_worktrees(project_path: &Path) -> Vec<PathBuf> {
+    let dot_git = project_path.join(".git");
+    let git_dir = if dot_git.is_dir() {
+        dot_git
+    } else if dot_git.exists() {
+        // A worktree or submodule itself.
+        return Vec::new();
+    } else {
+        project_path.to_path_buf()
+    };
+    let Ok(entries) = fs::read_dir(git_dir.join("worktrees")) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let gitdir = fs::read_to_string(entry.path().join("gitdir")).ok()?;
+            let path = Path::new(gitdir.trim()).parent()?.to_path_buf();
+            path.is_dir().then_some(path)
+        })
+        .collect()
+}
+
+/// A local branch, as offered by the branch picker.
+#[derive(Debug, Clone)]
+pub struct Branch {
+    pub name: String,
+    /// Checked out in the repository the branches were listed from.
+    pub current: bool,
+    /// The checkout that has it, when that is a worktree of its own.
+    pub worktree: Option<PathBuf>,
+}
+
+/// The local branches of the repository at `project_path`, most recently
+/// committed to first. Empty outside a repository.
+pub fn branches(project_path: &Path) -> Vec<Branch> {
+    let Ok(output) = Command::new("git")
+        .args([
+            "for-each-ref",
+            "--sort=-committerdate",
+            "--format=%(refname:short)%00%(HEAD)%00%(worktreepath)",
+            "refs/heads",
+        ])
+        .current_dir(project_path)
+        .output()
+    else {
+        return Vec::new();
+    };
+    if !output.status.success() {
+        return Vec::new();
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let name = fields.next()?.to_string();
+            let current = fields.next() == Some("*");
+            let worktree = fields
+                .next()
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .filter(|path| !current && path.as_path() != project_path);
+            Some(Branch {
+                name,
+                current,
+                worktree,
+            })
+        })
+        .collect()
+}
+
+/// Checks out `branch` with `git switch`. A failure, such as local changes
+/// that would be overwritten, reports git's reason.
+pub fn switch_branch(project_path: &Path, branch: &str) -> io::Result<()> {
+    let output = Command::new("git")
+        .args(["switch", "--quiet", branch])
+        .current_dir(project_path)
+        .output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .map_or_else(|| output.status.to_string(), |line| line.trim().to_string());
+    Err(io::Error::other(reason))
+}
+
+/// Lists tracked and untracked-but-not-ignored files relative to the project root.
+pub fn list_files(project_path: &Path) -> Vec<PathBuf> {
+    let Ok(output) = Command::new("git")
+        .args(["ls-files", "--cached", "--others", "--exclude-standard"])
+        .current_dir(project_path)
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Annotations stored in the repository's git config under `pl.*`.
+#[derive(Debug, Default, Clone)]
+pub struct Metadata {
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Reads `pl.description` and `pl.tags` with `git config`. `pl.tags` may be
+/// set several times and each value may hold several comma- or
+/// space-separated tags. Unset keys give empty metadata.
+pub fn metadata(project_path: &Path) -> Metadata {
+    let mut metadata = Metadata::default();
+    let Ok(output) = Command::new("git")
+        .args([
+            "config",
+            "--local",
+            "--get-regexp",
+            r"^pl\.(description|tags)$",
+        ])
+        .current_dir(project_path)
+        .output()
+    else {
+        return metadata;
+    };
+
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "pl.description" => metadata.description = Some(value.trim().to_string()),
+            "pl.tags" => metadata.tags.extend(
+                value
+                    .split(|ch: char| ch == ',' || ch.is_whitespace())
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_lowercase),
+            ),
+            _ => {}
+        }
+    }
+
+    metadata
+}
+
+/// The committer date of `HEAD` as `YYYY-MM-DD`.
+pub fn last_commit_date(project_path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%cs"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let date = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!date.is_empty()).then_some(date)
+}
+
+/// The subject line of the commit at `HEAD`.
+pub fn last_commit_subject(project_path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%s"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let subject = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!subject.is_empty()).then_some(subject)
+}
+
+/// When `HEAD` last moved, as seconds since the epoch, read from the last
+/// entry of `.git/logs/HEAD`. Falls back to the committer date of `HEAD`
+/// when the reflog is missing.
+pub fn last_commit_time(project_path: &Path) -> Option<u64> {
+    let reflog = fs::read_to_string(project_path.join(".git").join("logs").join("HEAD"));
+    if let Ok(reflog) = reflog
+        && let Some(last) = reflog.lines().next_back()
+    {
+        // `<old> <new> <name> <<email>> <timestamp> <tz>\t<message>`
+        let (entry, _) = last.split_once('\t').unwrap_or((last, ""));
+        let mut fields = entry.rsplit(' ');
+        fields.next();
+        if let Some(time) = fields.next().and_then(|time| time.parse().ok()) {
+            return Some(time);
+        }
+    }
+
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// `git diff --stat` of the working tree against `HEAD`, covering staged and
+/// unstaged changes, in git's colors. Empty when the tree is clean.
+pub fn diff_stat(project_path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["diff", "--stat", "--color=always", "HEAD"])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The one-line description in `.git/description` (or `description` in a
+/// bare repository), unless it is still git's placeholder.
+pub fn description(project_path: &Path) -> Option<String> {
+    let git_dir = project_path.join(".git");
+    let git_dir = if git_dir.is_dir() {
+        git_dir
+    } else {
+        project_path.to_path_buf()
+    };
+
+    let description = fs::read_to_string(git_dir.join("description")).ok()?;
+    let description = description.trim();
+    if description.is_empty() || description.starts_with("Unnamed repository;") {
+        return None;
+    }
+    Some(description.to_string())
+}
+
+/// Commit counts for each of the last `weeks` weeks, oldest first.
+pub fn weekly_commits(project_path: &Path, weeks: usize) -> Option<Vec<usize>> {
+    const WEEK: u64 = 7 * 24 * 60 * 60;
+
+    let output = Command::new("git")
+        .args(["log", "--format=%ct", &format!("--since={weeks} weeks ago")])
+        .current_dir(project_path)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let mut counts = vec![0; weeks];
+    for time in String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse::<u64>().ok())
+    {
+        let age = (now.saturating_sub(time) / WEEK) as usize;
+        if age < weeks {
+            counts[weeks - 1 - age] += 1;
+        }
+    }
+    Some(counts)
+}
@@ -0,0 +1,76 @@
+/// Matches `path` against a gitignore-style glob, where `*` and `?` stay
+/// within one `/`-separated segment and `**` spans any number of them.
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = path.chars().collect();
+    let memo = vec![None; (pattern.len() + 1) * (text.len() + 1)];
+    Glob {
+        pattern,
+        text,
+        memo,
+    }
+    .matches(0, 0)
+}
+
+/// A match in progress. Each wildcard tries every way to continue, so
+/// `memo` keeps whether the pattern from one position matches the text from
+/// another; without it `**a**a**a` takes exponential time.
+struct Glob {
+    pattern: Vec<char>,
+    text: Vec<char>,
+    memo: Vec<Option<bool>>,
+}
+
+impl Glob {
+    fn matches(&mut self, p: usize, t: usize) -> bool {
+        let key = p * (self.text.len() + 1) + t;
+        if let Some(matched) = self.memo[key] {
+            return matched;
+        }
+
+        let end = self.text.len();
+        let matched = match self.pattern[p..] {
+            [] => t == end,
+            ['*', '*', ..] => {
+                // `**/` also matches no directories at all.
+                (self.pattern.get(p + 2) == Some(&'/') && self.matches(p + 3, t))
+                    || (t..=end).any(|skip| self.matches(p + 2, skip))
+            }
+            ['*', ..] => {
+                let segment_end = self.text[t..]
+                    .iter()
+                    .position(|ch| *ch == '/')
+                    .map_or(end, |len| t + len);
+                (t..=segment_end).any(|skip| self.matches(p + 1, skip))
+            }
+            ['?', ..] => {
+                self.text.get(t).is_some_and(|ch| *ch != '/') && self.matches(p + 1, t + 1)
+            }
+            [ch, ..] => self.text.get(t) == Some(&ch) && self.matches(p + 1, t + 1),
+        };
+        self.memo[key] = Some(matched);
+        matched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_stay_within_segments_unless_doubled() {
+        assert!(glob_match("scratch-*", "scratch-one"));
+        assert!(!glob_match("*", "a/b"));
+        assert!(glob_match("a/**/b", "a/b"));
+        assert!(glob_match("a/**/b", "a/x/y/b"));
+        assert!(glob_match("?b", "ab"));
+        assert!(!glob_match("?b", "/b"));
+    }
+
+    #[test]
+    fn repeated_double_stars_fail_fast() {
+        let path = "a".repeat(40) + "b";
+        assert!(!glob_match("**a**a**a**a**a**a**a**a**c", &path));
+        assert!(glob_match("**a**a**a**b", &path));
+    }
+}
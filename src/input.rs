@@ -1,51 +1,488 @@
-use crate::app::{App, InputMode};
+use crate::app::{App, InputMode, NewProject};
+use crate::config::{EditingEnter, KeyAction, KeySpecs};
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::collections::BTreeMap;
 use std::io;
+use std::time::Duration;
 use tui_input::backend::crossterm::EventHandler;
 
-pub fn next_key_event() -> io::Result<Option<KeyEvent>> {
+/// Ctrl-Enter opens every match, but most terminals only report Alt-Enter.
+const OPEN_ALL_MODIFIERS: KeyModifiers = KeyModifiers::CONTROL.union(KeyModifiers::ALT);
+
+/// Lines moved by `Ctrl-d` / `Ctrl-u` in the focused preview.
+const PREVIEW_PAGE: isize = 10;
+
+/// The `[keys]` bindings, parsed once at startup.
+#[derive(Debug, Default)]
+pub struct Keymap {
+    /// Each key with the spec it was read from, for the help.
+    bindings: Vec<(KeyCode, KeyModifiers, KeyAction, String)>,
+}
+
+impl Keymap {
+    /// Parses every key spec in `keys`, returning the bindings along with
+    /// the specs that couldn't be read.
+    pub fn new(keys: &BTreeMap<KeyAction, KeySpecs>) -> (Self, Vec<String>) {
+        let mut bindings = Vec::new();
+        let mut invalid = Vec::new();
+        for (&action, specs) in keys {
+            for spec in specs.specs() {
+                match parse_key(spec) {
+                    Some((code, modifiers)) => {
+                        bindings.push((code, modifiers, action, spec.clone()))
+                    }
+                    None => invalid.push(spec.clone()),
+                }
+            }
+        }
+        (Self { bindings }, invalid)
+    }
+
+    /// The action bound to `key_event`. Shift is ignored for characters,
+    /// since the character itself already says whether it was held.
+    fn action(&self, key_event: &KeyEvent) -> Option<KeyAction> {
+        let modifiers = match key_event.code {
+            KeyCode::Char(_) => key_event.modifiers - KeyModifiers::SHIFT,
+            _ => key_event.modifiers,
+        };
+        self.bindings
+            .iter()
+            .find(|(code, bound, ..)| *code == key_event.code && *bound == modifiers)
+            .map(|&(_, _, action, _)| action)
+    }
+
+    /// The configured keys as written, with what they do.
+    pub fn describe(&self) -> impl Iterator<Item = (&str, KeyAction)> {
+        self.bindings
+            .iter()
+            .map(|(_, _, action, spec)| (spec.as_str(), *action))
+    }
+}
+
+/// Reads a key spec like `"j"`, `"G"`, `"ctrl-n"`, `"alt-enter"`, `"space"`
+/// or `"f2"`. Modifier prefixes and key names ignore case; a single
+/// character is taken as written.
+fn parse_key(spec: &str) -> Option<(KeyCode, KeyModifiers)> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = spec;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        let (modifier, len) = if lower.starts_with("ctrl-") && rest.len() > 5 {
+            (KeyModifiers::CONTROL, 5)
+        } else if lower.starts_with("alt-") && rest.len() > 4 {
+            (KeyModifiers::ALT, 4)
+        } else if lower.starts_with("shift-") && rest.len() > 6 {
+            (KeyModifiers::SHIFT, 6)
+        } else {
+            break;
+        };
+        modifiers |= modifier;
+        rest = &rest[len..];
+    }
+
+    let mut chars = rest.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        // Shift only matters for characters as the case it produces.
+        return Some(if modifiers.contains(KeyModifiers::SHIFT) {
+            (
+                KeyCode::Char(ch.to_ascii_uppercase()),
+                modifiers - KeyModifiers::SHIFT,
+            )
+        } else {
+            (KeyCode::Char(ch), modifiers)
+        });
+    }
+
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "backtab" => KeyCode::BackTab,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        name => KeyCode::F(
+            name.strip_prefix('f')?
+                .parse()
+                .ok()
+                .filter(|n| (1..=12).contains(n))?,
+        ),
+    };
+    Some((code, modifiers))
+}
+
+/// Runs a `[keys]` action the way its default key does in the current mode.
+fn run_key_action(app: &mut App, action: KeyAction) {
+    let editing = app.input_mode == InputMode::Editing;
+    match action {
+        KeyAction::Quit => app.exit(),
+        KeyAction::SelectNext if editing => app.select_next(),
+        KeyAction::SelectNext => app.move_selection(1),
+        KeyAction::SelectPrev if editing => app.select_previous(),
+        KeyAction::SelectPrev => app.move_selection(-1),
+        KeyAction::SelectFirst => app.select_first(),
+        KeyAction::SelectLast => app.select_last(),
+        KeyAction::Open => app.open_selected_project(),
+        KeyAction::OpenAll => app.request_open_all(),
+        KeyAction::OpenParent => app.open_selected_parent(),
+        KeyAction::OpenRemote => app.open_project_remote(),
+        KeyAction::OpenSession => app.open_session(),
+        KeyAction::PickBranch => app.start_branch_pick(),
+        KeyAction::Filter if editing => app.stop_editing(),
+        KeyAction::Filter => app.start_editing(),
+        KeyAction::Leader => app.start_leader(),
+        KeyAction::Shell => app.open_shell(),
+        KeyAction::Reveal => app.reveal_selected(),
+        KeyAction::CopyPath => app.copy_selected_path(),
+        KeyAction::TogglePin => app.toggle_pin(),
+        KeyAction::ToggleMark => app.toggle_mark(),
+        KeyAction::ClearMarks => app.clear_marks(),
+        KeyAction::NextSort => app.next_sort(),
+        KeyAction::ReverseSort => app.reverse_sort(),
+        KeyAction::NextRoot => app.cycle_root(1),
+        KeyAction::PrevRoot => app.cycle_root(-1),
+        KeyAction::FocusPreview => app.toggle_preview_focus(),
+        KeyAction::Rescan => app.rescan(),
+        KeyAction::Info => app.toggle_info(),
+        KeyAction::Help => app.toggle_help(),
+    }
+}
+
+pub enum InputEvent {
+    Key(KeyEvent),
+    Paste(String),
+    /// Any other terminal event, such as a resize.
+    Other,
+    /// No event arrived before the timeout.
+    Tick,
+}
+
+pub fn next_event(timeout: Duration) -> io::Result<InputEvent> {
+    if !event::poll(timeout)? {
+        return Ok(InputEvent::Tick);
+    }
+
     match event::read()? {
-        Event::Key(key_event) if key_event.kind == KeyEventKind::Press => Ok(Some(key_event)),
-        _ => Ok(None),
+        Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+            Ok(InputEvent::Key(key_event))
+        }
+        Event::Paste(text) => Ok(InputEvent::Paste(text)),
+        _ => Ok(InputEvent::Other),
+    }
+}
+
+fn handle_new_project_key(app: &mut App, key_event: KeyEvent) {
+    match key_event.code {
+        KeyCode::Esc => return app.cancel_new_project(),
+        KeyCode::Enter => return app.submit_new_project(),
+        _ => {}
+    }
+
+    match &mut app.new_project {
+        Some(NewProject::Name(input)) => {
+            input.handle_event(&Event::Key(key_event));
+        }
+        Some(NewProject::Prompt { input, .. }) => {
+            input.handle_event(&Event::Key(key_event));
+        }
+        Some(NewProject::Root { .. } | NewProject::Template { .. }) => match key_event.code {
+            KeyCode::Char('j') | KeyCode::Down => app.move_template_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => app.move_template_selection(-1),
+            _ => {}
+        },
+        None => {}
     }
 }
 
 pub fn handle_key_event(app: &mut App, key_event: KeyEvent) {
+    app.clear_status();
+
     if app.show_help {
         match key_event.code {
-            KeyCode::Char('q') | KeyCode::Esc => app.toggle_help(),
+            KeyCode::Char('q') | KeyCode::Char('?') | KeyCode::Esc => app.toggle_help(),
+            KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.scroll_help(PREVIEW_PAGE)
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.scroll_help(-PREVIEW_PAGE)
+            }
+            KeyCode::Char('j') | KeyCode::Down => app.scroll_help(1),
+            KeyCode::Char('k') | KeyCode::Up => app.scroll_help(-1),
+            KeyCode::PageDown => app.scroll_help(PREVIEW_PAGE),
+            KeyCode::PageUp => app.scroll_help(-PREVIEW_PAGE),
+            KeyCode::Char('g') | KeyCode::Home => app.help_scroll = 0,
+            KeyCode::Char('G') | KeyCode::End => app.help_scroll = usize::MAX,
+            _ => {}
+        }
+        return;
+    }
+
+    if app.pending_action.is_some() {
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Enter => app.confirm_pending_action(),
+            KeyCode::Char('n') | KeyCode::Char('q') | KeyCode::Esc => app.cancel_pending_action(),
             _ => {}
         }
         return;
     }
 
+    if app.new_project.is_some() {
+        handle_new_project_key(app, key_event);
+        return;
+    }
+
+    if app.preview_cursor.is_some() {
+        match key_event.code {
+            KeyCode::Esc | KeyCode::Char('q') => app.toggle_preview_focus(),
+            KeyCode::Char('f') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.toggle_preview_focus()
+            }
+            KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.move_preview_cursor(PREVIEW_PAGE)
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.move_preview_cursor(-PREVIEW_PAGE)
+            }
+            KeyCode::Char('j') | KeyCode::Down => app.move_preview_cursor(1),
+            KeyCode::Char('k') | KeyCode::Up => app.move_preview_cursor(-1),
+            KeyCode::Char('J') => app.move_preview_cursor(PREVIEW_PAGE),
+            KeyCode::Char('K') => app.move_preview_cursor(-PREVIEW_PAGE),
+            KeyCode::Char('g') => app.move_preview_cursor(isize::MIN),
+            KeyCode::Char('G') => app.preview_cursor_to_end(),
+            KeyCode::Char('v') => app.toggle_preview_selection(),
+            KeyCode::Char('y') => app.yank_preview_selection(),
+            KeyCode::Enter | KeyCode::Char('o') => app.toggle_preview_fold(),
+            KeyCode::Tab => app.cycle_preview_tab(1),
+            KeyCode::BackTab => app.cycle_preview_tab(-1),
+            _ => {}
+        }
+        return;
+    }
+
+    if app.leader_pending {
+        match key_event.code {
+            KeyCode::Char(key) => app.run_leader_action(key),
+            _ => app.leader_pending = false,
+        }
+        return;
+    }
+
+    if app.recent_selection.is_some() {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Char('M') | KeyCode::Esc => app.toggle_recent(),
+            KeyCode::Char('j') | KeyCode::Down => app.move_recent_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => app.move_recent_selection(-1),
+            KeyCode::Enter => app.open_recent(),
+            _ => {}
+        }
+        return;
+    }
+
+    if app.branch_picker.is_some() {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Char('b') | KeyCode::Esc => app.branch_picker = None,
+            KeyCode::Char('j') | KeyCode::Down => app.move_branch_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => app.move_branch_selection(-1),
+            KeyCode::Enter => app.open_picked_branch(),
+            _ => {}
+        }
+        return;
+    }
+
+    if let Some(input) = &mut app.tag_editor {
+        match key_event.code {
+            KeyCode::Esc => app.cancel_tag_edit(),
+            KeyCode::Enter => app.submit_tag_edit(),
+            _ => {
+                input.handle_event(&Event::Key(key_event));
+            }
+        }
+        return;
+    }
+
+    if let Some(input) = &mut app.rename {
+        match key_event.code {
+            KeyCode::Esc => app.cancel_rename(),
+            KeyCode::Enter => app.submit_rename(),
+            _ => {
+                input.handle_event(&Event::Key(key_event));
+            }
+        }
+        return;
+    }
+
+    if let Some(input) = &mut app.add_dir {
+        match key_event.code {
+            KeyCode::Esc => app.cancel_add_dir(),
+            KeyCode::Enter => app.submit_add_dir(),
+            _ => {
+                input.handle_event(&Event::Key(key_event));
+            }
+        }
+        return;
+    }
+
+    if app.info.is_some() {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Char('i') | KeyCode::Esc => app.toggle_info(),
+            _ => {}
+        }
+        return;
+    }
+
+    if app.show_scan_errors {
+        match key_event.code {
+            KeyCode::Char('q') | KeyCode::Esc => app.toggle_scan_errors(),
+            KeyCode::Char('e') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.toggle_scan_errors()
+            }
+            _ => {}
+        }
+        return;
+    }
+
+    // `[keys]` bindings come first. While searching, plain characters are
+    // left to type into the search box.
+    let typing = app.input_mode == InputMode::Editing
+        && matches!(key_event.code, KeyCode::Char(_))
+        && (key_event.modifiers - KeyModifiers::SHIFT).is_empty();
+    if !typing && let Some(action) = app.keymap.action(&key_event) {
+        return run_key_action(app, action);
+    }
+
     match app.input_mode {
         InputMode::Normal => match key_event.code {
             KeyCode::Char('q') | KeyCode::Esc => app.exit(),
-            KeyCode::Char('j') | KeyCode::Down => app.state.select_next(),
-            KeyCode::Char('k') | KeyCode::Up => app.state.select_previous(),
-            KeyCode::Char('G') => app.state.select_last(),
-            KeyCode::Char('g') => app.state.select_first(),
+            KeyCode::Char('j') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.cycle_preview(1)
+            }
+            KeyCode::Char('k') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.cycle_preview(-1)
+            }
+            KeyCode::Char('e') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.toggle_scan_errors()
+            }
+            KeyCode::Char('r') if key_event.modifiers == KeyModifiers::CONTROL => app.rescan(),
+            KeyCode::Char('n') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.start_new_project()
+            }
+            KeyCode::Char('g') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.toggle_content_search();
+                app.start_editing();
+            }
+            KeyCode::Char('f') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.toggle_preview_focus()
+            }
+            KeyCode::Char('l') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.expand_results()
+            }
+            KeyCode::Char('d') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.scroll_preview(PREVIEW_PAGE)
+            }
+            KeyCode::Char('u') if key_event.modifiers == KeyModifiers::CONTROL => {
+                app.scroll_preview(-PREVIEW_PAGE)
+            }
+            KeyCode::Char(key)
+                if key == app.config().leader_key
+                    && !key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                app.start_leader()
+            }
+            KeyCode::Char('j') | KeyCode::Down => app.move_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => app.move_selection(-1),
+            KeyCode::Char('G') => app.select_last(),
+            KeyCode::Char('g') => app.select_first(),
             KeyCode::Char('/') => app.start_editing(),
+            KeyCode::Tab => app.cycle_root(1),
+            KeyCode::BackTab => app.cycle_root(-1),
+            KeyCode::Enter if key_event.modifiers.intersects(OPEN_ALL_MODIFIERS) => {
+                app.request_open_all()
+            }
             KeyCode::Enter => app.open_selected_project(),
             KeyCode::Char('o') => app.open_project_remote(),
+            KeyCode::Char('O') => app.open_selected_parent(),
+            KeyCode::Char('F') => app.reveal_selected(),
+            KeyCode::Char('!') => app.open_shell(),
+            KeyCode::Char('D') => app.toggle_dirty_only(),
+            KeyCode::Char('N') => app.toggle_no_readme_only(),
             KeyCode::Char('s') => app.next_sort(),
+            KeyCode::Char('S') => app.reverse_sort(),
+            KeyCode::Char('A') => app.toggle_frecency_sort(),
+            KeyCode::Char('t') => app.open_session(),
+            KeyCode::Char('b') => app.start_branch_pick(),
+            KeyCode::Char('v') => app.toggle_mark(),
+            KeyCode::Char('V') => app.clear_marks(),
+            KeyCode::Char('.') => app.toggle_preview_hidden(),
+            KeyCode::Char('p') => app.next_preview_mode(),
+            KeyCode::Char('L') => app.toggle_preview_lock(),
+            KeyCode::Char('P') => app.toggle_absolute_paths(),
+            KeyCode::Char('r') => app.refresh_git_status(),
+            KeyCode::Char('a') => app.request_archive(),
+            KeyCode::Char('u') => app.undo_archive(),
+            KeyCode::Char('x') => app.ignore_selected(),
+            KeyCode::Char('R') => app.start_rename(),
+            KeyCode::Char('#') => app.start_tag_edit(),
+            KeyCode::Char('T') => app.cycle_tag_filter(),
+            KeyCode::Char('m') => app.toggle_pin(),
+            KeyCode::Char('J') => app.move_pin(1),
+            KeyCode::Char('K') => app.move_pin(-1),
+            KeyCode::Char('i') => app.toggle_info(),
+            KeyCode::Char('H') => app.toggle_history_mode(),
+            KeyCode::Char('M') => app.toggle_recent(),
+            KeyCode::Char('Y') => app.copy_preview(),
+            KeyCode::Char('n') => app.start_new_project(),
+            KeyCode::Char('e') => app.open_preview_file(),
             KeyCode::Char('?') => app.toggle_help(),
+            KeyCode::Char(digit @ '1'..='9') => app.open_nth(digit as usize - '0' as usize),
             _ => {}
         },
         InputMode::Editing => match (key_event.code, key_event.modifiers) {
             (KeyCode::Char('c'), KeyModifiers::CONTROL) => app.exit(),
             (KeyCode::Esc, KeyModifiers::NONE) => app.stop_editing(),
+            (KeyCode::Enter, modifiers) if modifiers.intersects(OPEN_ALL_MODIFIERS) => {
+                app.request_open_all()
+            }
+            (KeyCode::Enter, KeyModifiers::NONE)
+                if app.config().editing_enter == EditingEnter::Confirm =>
+            {
+                app.stop_editing()
+            }
+            (KeyCode::Enter, KeyModifiers::NONE) if app.visible_projects().is_empty() => {
+                app.submit_unmatched_query()
+            }
             (KeyCode::Enter, KeyModifiers::NONE) => app.open_selected_project(),
+            (KeyCode::Char('n'), KeyModifiers::CONTROL) if app.in_history() => app.history_next(),
+            (KeyCode::Char('p'), KeyModifiers::CONTROL) if app.browsing_history() => {
+                app.history_previous()
+            }
             (KeyCode::Char('n'), KeyModifiers::CONTROL) | (KeyCode::Down, KeyModifiers::NONE) => {
-                app.state.select_next()
+                app.select_next()
             }
             (KeyCode::Char('p'), KeyModifiers::CONTROL) | (KeyCode::Up, KeyModifiers::NONE) => {
-                app.state.select_previous()
+                app.select_previous()
             }
             (KeyCode::Char('o'), KeyModifiers::CONTROL) => app.open_project_remote(),
+            (KeyCode::Tab, KeyModifiers::NONE) => app.cycle_root(1),
+            (KeyCode::BackTab, _) => app.cycle_root(-1),
+            (KeyCode::Char('a'), KeyModifiers::CONTROL) => app.toggle_show_all(),
+            (KeyCode::Char('g'), KeyModifiers::CONTROL) => app.toggle_content_search(),
+            (KeyCode::Char('l'), KeyModifiers::CONTROL) => app.expand_results(),
+            (KeyCode::Char('e'), KeyModifiers::CONTROL) => app.toggle_scan_errors(),
+            (KeyCode::Char('r'), KeyModifiers::CONTROL) => app.rescan(),
+            (KeyCode::Char('j'), KeyModifiers::CONTROL) => app.cycle_preview(1),
+            (KeyCode::Char('k'), KeyModifiers::CONTROL) => app.cycle_preview(-1),
             _ => {
                 app.input.handle_event(&Event::Key(key_event));
+                app.show_all = false;
+                app.stop_browsing_history();
                 app.filter_results();
             }
         },
@@ -0,0 +1,671 @@
+use crate::config::{OpenMode, ProjectConfig, UserConfig, load_project_config};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Opens `path` with the project's own `.pl` command when it has one, and
+/// with the global editor otherwise. A pre-open command that fails stops the
+/// launch. A file, such as a README, is simply handed to the global editor.
+/// Returns the editor's exit status, or `None` when it was detached.
+/// `mode` is usually `open_mode`, but `t` opens a session whatever it is.
+/// `query` is the search that found the project, for
+/// `editor_query_template`.
+pub fn open_project(
+    path: &Path,
+    config: &UserConfig,
+    mode: OpenMode,
+    query: Option<&str>,
+) -> io::Result<Option<ExitStatus>> {
+    if path.is_file() {
+        if mode == OpenMode::Url {
+            return open_url(path, config);
+        }
+        let mut command = command_from_template(&config.editor_for(path), path, true)?;
+        if let Some(dir) = path.parent() {
+            command.current_dir(dir);
+        }
+        return run_editor(command, config);
+    }
+
+    let project_config = load_project_config(path);
+    pre_open(path, config, &project_config)?;
+    if matches!(mode, OpenMode::Tmux | OpenMode::Zellij) {
+        return open_session(path, mode);
+    }
+
+    let command = if let Some(open) = &project_config.open {
+        let mut command = command_from_template(open, path, false)?;
+        command.current_dir(path);
+        command
+    } else if let Some(editor) = &project_config.editor {
+        command_from_template(editor, path, true)?
+    } else {
+        if mode == OpenMode::Url {
+            return open_url(path, config);
+        }
+        if send_to_editor_server(path, config) {
+            return Ok(None);
+        }
+        if let (Some(template), Some(query)) = (&config.editor_query_template, query) {
+            return run_editor(command_with_query(template, path, query)?, config);
+        }
+        let editor = config.editor_for(path);
+        if config.editor_cwd {
+            let mut command = command_from_template(&editor, Path::new("."), true)?;
+            command.current_dir(path);
+            command
+        } else {
+            command_from_template(&editor, path, true)?
+        }
+    };
+
+    run_editor(command, config)
+}
+
+/// Runs the project's `.pl` `pre_open`, when allowed, or else
+/// `pre_open_command`.
+fn pre_open(path: &Path, config: &UserConfig, project_config: &ProjectConfig) -> io::Result<()> {
+    let pre_open = project_config
+        .pre_open
+        .as_ref()
+        .filter(|_| config.allow_project_pre_open)
+        .or(config.pre_open_command.as_ref());
+    match pre_open {
+        Some(pre_open) => run_pre_open(pre_open, path, config.editor_detach),
+        None => Ok(()),
+    }
+}
+
+/// Opens each of `paths` with `open_project`, handing every result to
+/// `finished`, which returns whether to go on. Several projects are opened
+/// together instead when there is a `multi_open_command`, or as the windows
+/// of one tmux session with `open_mode = "tmux"`; `finished` then hears once,
+/// for the first path.
+pub fn open_projects(
+    paths: &[PathBuf],
+    config: &UserConfig,
+    mode: OpenMode,
+    query: Option<&str>,
+    mut finished: impl FnMut(&Path, io::Result<Option<ExitStatus>>) -> bool,
+) {
+    if let [first, _, ..] = paths
+        && (config.multi_open_command.is_some() || mode == OpenMode::Tmux)
+    {
+        let result = paths
+            .iter()
+            .try_for_each(|path| pre_open(path, config, &load_project_config(path)))
+            .and_then(|()| match &config.multi_open_command {
+                Some(template) => run_editor(multi_open_command(template, paths)?, config),
+                None => open_tmux_workspace(paths),
+            });
+        finished(first, result);
+        return;
+    }
+
+    for path in paths {
+        if !finished(path, open_project(path, config, mode, query)) {
+            break;
+        }
+    }
+}
+
+/// Builds `multi_open_command` for `paths`: an argument that is just
+/// `{paths}` becomes all of them, and without one they go at the end.
+fn multi_open_command(template: &str, paths: &[PathBuf]) -> io::Result<Command> {
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect();
+    let mut args = split_args(template);
+    match args.iter().position(|arg| arg == "{paths}") {
+        Some(index) => {
+            args.splice(index..=index, paths);
+        }
+        None => args.extend(paths),
+    }
+    command_from_args(&args)
+}
+
+/// Opens `paths` as the windows of one tmux session, named after all of
+/// them, like `api+web`, and created if it isn't running yet.
+fn open_tmux_workspace(paths: &[PathBuf]) -> io::Result<Option<ExitStatus>> {
+    let name = paths
+        .iter()
+        .map(|path| session_name(path))
+        .collect::<Vec<_>>()
+        .join("+");
+    let target = format!("={name}");
+    if !tmux_has_session(&target)? {
+        let (first, rest) = paths.split_first().expect("at least two paths");
+        let status = Command::new("tmux")
+            .args([
+                "new-session",
+                "-d",
+                "-s",
+                &name,
+                "-n",
+                &session_name(first),
+                "-c",
+            ])
+            .arg(first)
+            .status()?;
+        session_result("tmux new-session", status)?;
+        for path in rest {
+            let status = Command::new("tmux")
+                .args(["new-window", "-t", &target, "-n", &session_name(path), "-c"])
+                .arg(path)
+                .status()?;
+            session_result("tmux new-window", status)?;
+        }
+    }
+    attach_tmux(&target)
+}
+
+fn tmux_has_session(target: &str) -> io::Result<bool> {
+    Ok(Command::new("tmux")
+        .args(["has-session", "-t", target])
+        .stderr(Stdio::null())
+        .status()?
+        .success())
+}
+
+/// Switches the tmux client to `target` from inside tmux, or attaches to it
+/// and waits otherwise.
+fn attach_tmux(target: &str) -> io::Result<Option<ExitStatus>> {
+    if std::env::var_os("TMUX").is_some_and(|value| !value.is_empty()) {
+        let status = Command::new("tmux")
+            .args(["switch-client", "-t", target])
+            .status()?;
+        return session_result("tmux switch-client", status).map(|()| None);
+    }
+    let mut command = Command::new("tmux");
+    command.args(["attach-session", "-t", target]);
+    log::info!("launching {command:?}");
+    command.status().map(Some)
+}
+
+/// Opens a tmux or zellij session named after the project, starting it in
+/// `path` if it doesn't exist yet. From inside the same multiplexer the
+/// client is switched over (zellij opens a tab) and `None` is returned;
+/// otherwise the session is attached to and waited on.
+fn open_session(path: &Path, mode: OpenMode) -> io::Result<Option<ExitStatus>> {
+    let name = session_name(path);
+    let inside = |variable| std::env::var_os(variable).is_some_and(|value| !value.is_empty());
+
+    if mode == OpenMode::Zellij {
+        if inside("ZELLIJ") {
+            let status = Command::new("zellij")
+                .args(["action", "new-tab", "--name", &name, "--cwd"])
+                .arg(path)
+                .status()?;
+            return session_result("zellij", status).map(|()| None);
+        }
+        let mut command = Command::new("zellij");
+        command
+            .args(["attach", "--create", &name])
+            .current_dir(path);
+        log::info!("launching {command:?}");
+        return command.status().map(Some);
+    }
+
+    let target = format!("={name}");
+    if !tmux_has_session(&target)? {
+        let status = Command::new("tmux")
+            .args(["new-session", "-d", "-s", &name, "-c"])
+            .arg(path)
+            .status()?;
+        session_result("tmux new-session", status)?;
+    }
+    attach_tmux(&target)
+}
+
+/// The project's directory name as a session name. tmux treats `.` and `:`
+/// in targets as window and pane separators, so they become `_`.
+fn session_name(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+    name.replace(['.', ':'], "_")
+}
+
+fn session_result(what: &str, status: ExitStatus) -> io::Result<()> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("{what} failed ({status})")))
+    }
+}
+
+/// Opens `url_template` with `{path}` replaced by the percent-encoded path,
+/// for editors that register a URL handler such as `vscode://`.
+fn open_url(path: &Path, config: &UserConfig) -> io::Result<Option<ExitStatus>> {
+    let url = config
+        .url_template
+        .replace("{path}", &percent_encode_path(path));
+    log::info!("opening {url}");
+    open::that_detached(&url)
+        .map_err(|err| io::Error::new(err.kind(), format!("can't open {url}: {err}")))?;
+    Ok(None)
+}
+
+/// Percent-encodes `path` for a URL, keeping `/` separators. Windows
+/// backslashes become `/` too, as URL handlers expect.
+fn percent_encode_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let path = if cfg!(windows) {
+        path.replace('\\', "/")
+    } else {
+        path.into_owned()
+    };
+
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// Hands `path` to a running editor through `editor_server_command`. Returns
+/// `false` when no server is configured or it doesn't answer, so the editor
+/// is launched normally instead.
+fn send_to_editor_server(path: &Path, config: &UserConfig) -> bool {
+    let Some(server) = &config.editor_server else {
+        return false;
+    };
+    // A socket path that doesn't exist means the editor isn't running.
+    if server.contains(std::path::is_separator) && !Path::new(server).exists() {
+        return false;
+    }
+
+    let template = config.editor_server_command.replace("{server}", server);
+    let Ok(mut command) = command_from_template(&template, path, true) else {
+        return false;
+    };
+    log::info!("sending to editor server: {command:?}");
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+fn run_editor(mut command: Command, config: &UserConfig) -> io::Result<Option<ExitStatus>> {
+    log::info!("launching {command:?}");
+    let retries = config.editor_retries;
+    if config.editor_detach {
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        with_retries(retries, || command.spawn())?;
+        Ok(None)
+    } else {
+        with_retries(retries, || command.status()).map(Some)
+    }
+}
+
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Runs `start` again, up to `retries` more times, while it fails in a way
+/// that tends to clear up by itself, such as the system briefly running out
+/// of processes or memory after waking from sleep. Anything else, like a
+/// missing editor, fails right away.
+fn with_retries<T>(retries: u32, mut start: impl FnMut() -> io::Result<T>) -> io::Result<T> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        match start() {
+            Ok(value) => {
+                if attempt > 1 {
+                    log::info!("editor started on attempt {attempt}");
+                }
+                return Ok(value);
+            }
+            Err(err) if attempt <= retries && is_transient(&err) => {
+                log::warn!("editor failed to start ({err}), retrying");
+                thread::sleep(RETRY_DELAY);
+            }
+            Err(err) if attempt > 1 => {
+                return Err(io::Error::new(
+                    err.kind(),
+                    format!("{err} (after {attempt} attempts)"),
+                ));
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ResourceBusy
+            | io::ErrorKind::ExecutableFileBusy
+            | io::ErrorKind::OutOfMemory
+    )
+}
+
+/// Shows `path` in the system file manager with it selected: Finder on
+/// macOS, Explorer on Windows. Other platforms can't select an entry, so the
+/// parent directory is opened instead.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        let status = Command::new("open").arg("-R").arg(path).status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("open -R failed ({status})")));
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    {
+        // Explorer exits non-zero even when it succeeds, so only a failure to
+        // start it is reported.
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(path);
+        Command::new("explorer").arg(select).spawn()?;
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    {
+        let parent = path
+            .parent()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no parent directory"))?;
+        open::that_detached(parent)
+    }
+}
+
+/// Runs an interactive shell in `path` and waits for it to exit: `$SHELL`,
+/// or `sh` when it is unset. On Windows, `%COMSPEC%` or `cmd`.
+pub fn shell(path: &Path) -> io::Result<ExitStatus> {
+    let (variable, fallback) = if cfg!(windows) {
+        ("COMSPEC", "cmd")
+    } else {
+        ("SHELL", "sh")
+    };
+    let shell = std::env::var_os(variable)
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| fallback.into());
+
+    let mut command = Command::new(shell);
+    command.current_dir(path);
+    log::info!("starting shell {command:?}");
+    command.status()
+}
+
+/// Describes how an editor that did not exit cleanly ended.
+pub fn describe_exit(status: ExitStatus) -> String {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return format!("editor killed by signal {signal}");
+    }
+
+    match status.code() {
+        Some(code) => format!("editor exited with code {code}"),
+        None => format!("editor exited ({status})"),
+    }
+}
+
+/// Runs the pre-open command to completion. Detached launches may happen
+/// while the TUI is still drawn, so their output is discarded.
+fn run_pre_open(template: &str, path: &Path, quiet: bool) -> io::Result<()> {
+    let mut command = command_from_template(template, path, false)?;
+    command.current_dir(path);
+    if quiet {
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+    }
+    let status = command.status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "pre-open command `{template}` failed ({status})"
+        )))
+    }
+}
+
+/// Starts a hook or `on_select_command` in the background, with `{path}`
+/// and `{query}` filled in and its output discarded, since the TUI owns the
+/// terminal. It runs from the project root when there is a project.
+pub fn spawn_hook(template: &str, path: Option<&Path>, query: &str) -> io::Result<Child> {
+    let mut command = command_with_query(template, path.unwrap_or(Path::new("")), query)?;
+    log::debug!("running hook: {command:?}");
+    if let Some(path) = path.filter(|path| path.is_dir()) {
+        command.current_dir(path);
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+/// Builds an `[[actions]]` command for the project at `path` named `name`,
+/// run from the project root.
+pub fn action_command(template: &str, path: &Path, name: &str) -> io::Result<Command> {
+    setup_command(template, path, name, "")
+}
+
+/// Builds a `[templates]` command for a new project, which also fills in
+/// `{input}` with the answer to the template's prompt.
+pub fn setup_command(template: &str, path: &Path, name: &str, input: &str) -> io::Result<Command> {
+    let path_text = path.to_string_lossy();
+    let args: Vec<String> = split_args(template)
+        .into_iter()
+        .map(|arg| {
+            arg.replace("{path}", &path_text)
+                .replace("{name}", name)
+                .replace("{input}", input)
+        })
+        .collect();
+    let mut command = command_from_args(&args)?;
+    command.current_dir(path);
+    Ok(command)
+}
+
+/// Builds a command from a shell-style template, replacing `{path}` in every
+/// argument. Without a placeholder the path is appended when `append_path`.
+fn command_from_template(template: &str, path: &Path, append_path: bool) -> io::Result<Command> {
+    command_from_args(&template_args(template, path, append_path))
+}
+
+fn template_args(template: &str, path: &Path, append_path: bool) -> Vec<String> {
+    let path = path.to_string_lossy();
+    let mut args = split_args(template);
+    let has_placeholder = args.iter().any(|arg| arg.contains("{path}"));
+
+    for arg in &mut args {
+        *arg = arg.replace("{path}", &path);
+    }
+    if append_path && !has_placeholder {
+        args.push(path.into_owned());
+    }
+    args
+}
+
+/// Builds `template` for `path`, filling in `{query}` as well.
+/// Both are substituted after the template is split into arguments, so the
+/// query always stays inside the argument it was written in and needs no
+/// quoting, whatever characters it holds.
+fn command_with_query(template: &str, path: &Path, query: &str) -> io::Result<Command> {
+    let path = path.to_string_lossy();
+    let args: Vec<String> = split_args(template)
+        .into_iter()
+        .map(|arg| arg.replace("{path}", &path).replace("{query}", query))
+        .collect();
+    command_from_args(&args)
+}
+
+fn command_from_args(args: &[String]) -> io::Result<Command> {
+    let (program, args) = args
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "empty command"))?;
+
+    let mut command = editor_process(program);
+    command.args(args);
+    Ok(command)
+}
+
+/// Splits a command line into arguments, honoring single and double quotes.
+/// A backslash escapes a quote, a space or another backslash outside single
+/// quotes (only a `"` or backslash inside double ones) and is kept as
+/// written anywhere else, so Windows paths like `C:\Tools\nvim.exe` survive.
+fn split_args(line: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => current.push(ch),
+            (_, '\\') => {
+                let escapes = |next: &char| match quote {
+                    Some(_) => matches!(next, '"' | '\\'),
+                    None => matches!(next, '"' | '\'' | '\\') || next.is_whitespace(),
+                };
+                current.push(chars.next_if(escapes).unwrap_or('\\'));
+                in_arg = true;
+            }
+            (Some(_), _) => current.push(ch),
+            (None, '\'' | '"') => {
+                quote = Some(ch);
+                in_arg = true;
+            }
+            (None, ch) if ch.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, _) => {
+                current.push(ch);
+                in_arg = true;
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Most Windows editors are installed as `.cmd` shims (e.g. `code.cmd`),
+/// which `Command` won't find by their bare name, so a bare name is looked
+/// up on `PATH` with each `PATHEXT` extension and the file is started
+/// directly. The standard library quotes arguments to batch files itself,
+/// so a project path holding `&` or `|` is never read by `cmd` as syntax.
+#[cfg(windows)]
+fn editor_process(program: &str) -> Command {
+    Command::new(resolve_program(program).unwrap_or_else(|| PathBuf::from(program)))
+}
+
+/// The first `PATH` entry holding `program` with one of the `PATHEXT`
+/// extensions. `None` for names that already have an extension or a
+/// directory, which `Command` handles as they are.
+#[cfg(windows)]
+fn resolve_program(program: &str) -> Option<PathBuf> {
+    let name = Path::new(program);
+    if name.extension().is_some() || name.components().count() > 1 {
+        return None;
+    }
+
+    let extensions = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".to_string());
+    std::env::split_paths(&std::env::var_os("PATH")?).find_map(|dir| {
+        extensions
+            .split(';')
+            .filter(|extension| !extension.is_empty())
+            .map(|extension| dir.join(format!("{program}{extension}")))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[cfg(not(windows))]
+fn editor_process(program: &str) -> Command {
+    Command::new(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_with_quotes() {
+        assert_eq!(
+            split_args(r#"nvim -c 'Telescope find_files' "a b" c\ d"#),
+            ["nvim", "-c", "Telescope find_files", "a b", "c d"]
+        );
+        assert_eq!(split_args(r#"echo "say \"hi\"""#), ["echo", r#"say "hi""#]);
+        assert_eq!(split_args("  "), Vec::<String>::new());
+    }
+
+    #[test]
+    fn keeps_backslashes_in_windows_paths() {
+        assert_eq!(
+            split_args(r"C:\Tools\nvim.exe --clean"),
+            [r"C:\Tools\nvim.exe", "--clean"]
+        );
+        assert_eq!(
+            split_args(r#""C:\Program Files\x""#),
+            [r"C:\Program Files\x"]
+        );
+    }
+
+    #[test]
+    fn appends_the_path_without_a_placeholder() {
+        let args = template_args("code --wait", Path::new("/src/app"), true);
+        assert_eq!(args, ["code", "--wait", "/src/app"]);
+        assert_eq!(
+            template_args("make", Path::new("/src/app"), false),
+            ["make"]
+        );
+    }
+
+    #[test]
+    fn substitutes_the_path_placeholder() {
+        let args = template_args(
+            "nvim -c 'cd {path}' {path}/README.md",
+            Path::new("/src/my app"),
+            true,
+        );
+        assert_eq!(
+            args,
+            ["nvim", "-c", "cd /src/my app", "/src/my app/README.md"]
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn only_bare_names_are_looked_up() {
+        assert_eq!(resolve_program("code.cmd"), None);
+        assert_eq!(resolve_program(r"C:\Tools\nvim"), None);
+        assert_eq!(resolve_program("pl-no-such-editor"), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn finds_cmd_on_path() {
+        let cmd = resolve_program("cmd").expect("cmd.exe is on PATH");
+        assert!(cmd.is_file());
+    }
+}
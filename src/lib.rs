@@ -0,0 +1,17 @@
+pub mod ansi;
+pub mod app;
+pub mod cli;
+pub mod clipboard;
+pub mod config;
+pub mod git;
+pub mod glob;
+pub mod input;
+pub mod launch;
+pub mod logging;
+pub mod markdown;
+pub mod matcher;
+pub mod project;
+pub mod state;
+pub mod tui;
+pub mod ui;
+pub mod worker;
@@ -0,0 +1,80 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Appends log records to a file, since stdout and stderr belong to the TUI.
+struct FileLogger {
+    level: LevelFilter,
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{time} {:<5} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// `pl.log` in the state directory, or the data directory on platforms
+/// without one.
+pub fn log_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::data_dir)
+        .map(|dir| dir.join("pl").join("pl.log"))
+}
+
+/// Starts logging at the level named by `PL_LOG` (`error`, `warn`, `info`,
+/// `debug` or `trace`). Does nothing when it is unset, and gives up quietly on
+/// an unknown level or a log file that can't be opened.
+pub fn init() {
+    let Some(level) = std::env::var("PL_LOG")
+        .ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+    else {
+        return;
+    };
+    let Some(path) = log_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let Ok(file) = OpenOptions::new().create(true).append(true).open(&path) else {
+        return;
+    };
+
+    let logger = FileLogger {
+        level,
+        file: Mutex::new(file),
+    };
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+}
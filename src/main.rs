@@ -1,22 +1,106 @@
-mod app;
-mod config;
-mod input;
-mod project;
-mod tui;
-mod ui;
-
-use crate::app::App;
-use std::io;
+use pl::app::App;
+use pl::{cli, launch, logging, state, tui};
+use std::io::{self, Write};
 
 fn main() -> io::Result<()> {
-    let mut app = App::new()?;
-    let editor_command = app.editor_command().to_string();
+    let cli = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("pl: {err}");
+            std::process::exit(2);
+        }
+    };
+
+    if cli.help {
+        println!("{}", cli::help());
+        return Ok(());
+    }
+
+    if cli.version {
+        println!("{}", cli::version());
+        return Ok(());
+    }
+
+    if cli.unignore {
+        let mut state = state::load_state();
+        for path in state.ignored.drain(..) {
+            println!("{}", path.display());
+        }
+        return state::save_state(&state);
+    }
+
+    logging::init();
+
+    let mut app = App::new(&cli)?;
+
+    if cli.list {
+        return list_projects(&app, cli.json);
+    }
 
-    tui::run(&mut app)?;
+    if !(cli.select_one && app.select_only_match()) {
+        app.run_start_hook();
+        tui::run(&mut app)?;
+        app.run_quit_hook();
+    }
 
-    if let Some(path) = app.take_open_target() {
-        let _ = std::process::Command::new(&editor_command).arg(path).status();
+    let targets = app.take_open_targets();
+    if cli.print {
+        // Nothing chosen is a failure, so `dir=$(pl --print) && cd "$dir"`
+        // stays put when pl is quit.
+        if targets.is_empty() {
+            std::process::exit(1);
+        }
+        let mut out = io::stdout().lock();
+        for path in targets {
+            let path = std::path::absolute(&path).unwrap_or(path);
+            writeln!(out, "{}", path.display())?;
+        }
+        return Ok(());
     }
 
+    let (config, mode, query) = (app.config(), app.launch_mode(), app.launch_query());
+    launch::open_projects(&targets, config, mode, query, |path, result| {
+        match result {
+            Ok(Some(status)) if !status.success() => {
+                eprintln!("{}: {}", path.display(), launch::describe_exit(status));
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("failed to open '{}': {err}", path.display()),
+        }
+        true
+    });
+
+    Ok(())
+}
+
+/// Prints the projects for other tools to consume: `name<TAB>path` lines, or
+/// a JSON array of `{"name": ..., "path": ...}` objects, one per line.
+fn list_projects(app: &App, json: bool) -> io::Result<()> {
+    let mut out = io::stdout().lock();
+    let projects = app.projects();
+
+    if json {
+        writeln!(out, "[")?;
+    }
+    for (index, project) in projects.iter().enumerate() {
+        let name = project.project_name.to_string_lossy();
+        let path = std::path::absolute(&project.project_path)
+            .unwrap_or_else(|_| project.project_path.clone());
+        let path = path.to_string_lossy();
+        if json {
+            let separator = if index + 1 < projects.len() { "," } else { "" };
+            writeln!(
+                out,
+                "  {{\"name\": {}, \"path\": {}}}{separator}",
+                serde_json::to_string(&name)?,
+                serde_json::to_string(&path)?
+            )?;
+        } else {
+            writeln!(out, "{name}\t{path}")?;
+        }
+    }
+    if json {
+        writeln!(out, "]")?;
+    }
     Ok(())
 }
@@ -0,0 +1,345 @@
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
+use std::collections::HashSet;
+
+const CODE_STYLE: Style = Style::new().bg(Color::Indexed(236));
+const LINK_STYLE: Style = Style::new().cyan().underlined();
+
+/// An ATX heading (`## Usage`) and the line it sits on.
+#[derive(Debug, Clone, Copy)]
+pub struct Heading {
+    pub line: usize,
+    pub level: usize,
+}
+
+pub fn is_markdown(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".md") || name.ends_with(".markdown")
+}
+
+/// What a line of markdown is, going by the code fences before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    /// A ```` ``` ```` or `~~~` line opening or closing a code block.
+    Fence,
+    /// A line inside a code block.
+    Code,
+    /// Anything else.
+    Text,
+}
+
+/// Pairs each line of `text` with its `Block`. As in CommonMark, a fence
+/// may be indented by up to three spaces and closes on the marker it opened
+/// with; a line indented further is never a fence.
+fn blocks(text: &str) -> impl Iterator<Item = (&str, Block)> {
+    let mut fence: Option<&str> = None;
+    text.lines().map(move |raw| {
+        let trimmed = raw.trim_start();
+        let marker = ["```", "~~~"]
+            .into_iter()
+            .find(|marker| trimmed.starts_with(marker))
+            .filter(|_| raw.len() - trimmed.len() <= 3);
+        let block = match (fence, marker) {
+            (Some(open), Some(marker)) if marker == open => {
+                fence = None;
+                Block::Fence
+            }
+            (Some(_), _) => Block::Code,
+            (None, Some(marker)) => {
+                fence = Some(marker);
+                Block::Fence
+            }
+            (None, None) => Block::Text,
+        };
+        (raw, block)
+    })
+}
+
+/// The level and text of an ATX heading line (`## Usage`), which may be
+/// indented by up to three spaces.
+fn heading(raw: &str) -> Option<(usize, &str)> {
+    let trimmed = raw.trim_start();
+    if raw.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let level = trimmed.chars().take_while(|&ch| ch == '#').count();
+    let rest = &trimmed[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with([' ', '\t'])))
+        .then_some((level, rest))
+}
+
+/// The headings in `text`, in order. Lines inside fenced code blocks are
+/// skipped, so a `# comment` in a shell example isn't taken for one.
+pub fn headings(text: &str) -> Vec<Heading> {
+    blocks(text)
+        .enumerate()
+        .filter(|(_, (_, block))| *block == Block::Text)
+        .filter_map(|(line, (raw, _))| heading(raw).map(|(level, _)| Heading { line, level }))
+        .collect()
+}
+
+/// The innermost heading whose section contains `line`, which is simply the
+/// last heading at or above it.
+pub fn section_of(headings: &[Heading], line: usize) -> Option<Heading> {
+    headings
+        .iter()
+        .take_while(|heading| heading.line <= line)
+        .last()
+        .copied()
+}
+
+/// The indices of the lines left on screen when the sections under the
+/// `collapsed` headings are folded away. A section runs until the next
+/// heading of the same or a higher level.
+pub fn visible_lines(
+    line_count: usize,
+    headings: &[Heading],
+    collapsed: &HashSet<usize>,
+) -> Vec<usize> {
+    let mut visible = Vec::with_capacity(line_count);
+    let mut headings = headings.iter().peekable();
+    // The level of the collapsed heading currently hiding lines.
+    let mut hidden_under: Option<usize> = None;
+
+    for line in 0..line_count {
+        let heading = headings.next_if(|heading| heading.line == line);
+        match heading {
+            Some(heading) if hidden_under.is_some_and(|level| heading.level > level) => {}
+            Some(heading) => {
+                visible.push(line);
+                hidden_under = collapsed.contains(&line).then_some(heading.level);
+            }
+            None if hidden_under.is_some() => {}
+            None => visible.push(line),
+        }
+    }
+    visible
+}
+
+/// Styles markdown for the preview: headings without their `#`s, bullets,
+/// quotes, fenced code on a dim background, and `**bold**`, `*italic*`,
+/// `` `code` `` and `[links](...)` inline. Each line of `text` becomes
+/// exactly one line, so line numbers still match for scrolling, folding and
+/// the cursor; long lines are left for the widget to wrap.
+pub fn to_text(text: &str) -> Text<'_> {
+    blocks(text)
+        .map(|(raw, block)| match block {
+            Block::Fence => Line::styled(raw, Style::new().dim()),
+            Block::Code => Line::styled(raw, CODE_STYLE),
+            Block::Text => block_line(raw),
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Styles a line outside code fences by what kind of block it starts.
+fn block_line(raw: &str) -> Line<'_> {
+    let trimmed = raw.trim_start();
+    let indent = &raw[..raw.len() - trimmed.len()];
+
+    if let Some((level, rest)) = heading(raw) {
+        let style = match level {
+            1 => Style::new().bold().cyan().underlined(),
+            2 => Style::new().bold().cyan(),
+            _ => Style::new().bold(),
+        };
+        return Line::from(inline_spans(rest.trim())).style(style);
+    }
+
+    if trimmed.len() >= 3
+        && ['-', '*', '_']
+            .into_iter()
+            .any(|ch| trimmed.chars().all(|c| c == ch || c == ' '))
+    {
+        return Line::styled("─".repeat(3.max(trimmed.len())), Style::new().dim());
+    }
+
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        let mut spans = vec![Span::raw(indent), Span::styled("│ ", Style::new().dim())];
+        spans.extend(inline_spans(quote.trim_start()));
+        return Line::from(spans).style(Style::new().italic());
+    }
+
+    for bullet in ["- ", "* ", "+ "] {
+        if let Some(item) = trimmed.strip_prefix(bullet) {
+            let mut spans = vec![Span::raw(indent), Span::styled("• ", Style::new().cyan())];
+            spans.extend(inline_spans(item));
+            return Line::from(spans);
+        }
+    }
+
+    let mut spans = vec![Span::raw(indent)];
+    spans.extend(inline_spans(trimmed));
+    Line::from(spans)
+}
+
+/// Splits `text` into plain and styled runs for inline markup. A marker
+/// without a closing partner is kept as written.
+fn inline_spans(text: &str) -> Vec<Span<'_>> {
+    let mut spans = Vec::new();
+    let mut plain_start = 0;
+    let mut index = 0;
+
+    while index < text.len() {
+        let rest = &text[index..];
+        let found = if rest.starts_with('`') {
+            delimited(rest, "`").map(|(inner, len)| (Span::styled(inner, CODE_STYLE), len))
+        } else if rest.starts_with("**") || rest.starts_with("__") {
+            delimited(rest, &rest[..2])
+                .map(|(inner, len)| (Span::styled(inner, Style::new().bold()), len))
+        } else if rest.starts_with(['*', '_'])
+            && text[..index]
+                .chars()
+                .next_back()
+                .is_none_or(|before| !before.is_alphanumeric())
+        {
+            delimited(rest, &rest[..1])
+                .map(|(inner, len)| (Span::styled(inner, Style::new().italic()), len))
+        } else if rest.starts_with('[') {
+            link(rest).map(|(label, len)| (Span::styled(label, LINK_STYLE), len))
+        } else {
+            None
+        };
+
+        match found {
+            Some((span, len)) => {
+                if plain_start < index {
+                    spans.push(Span::raw(&text[plain_start..index]));
+                }
+                spans.push(span);
+                index += len;
+                plain_start = index;
+            }
+            None => index += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    if plain_start < text.len() {
+        spans.push(Span::raw(&text[plain_start..]));
+    }
+    spans
+}
+
+/// The text between `marker` at the start of `text` and its next
+/// occurrence, and the length of the whole run. Empty runs and ones that
+/// start with a space don't count, so `2 * 3 * 4` stays as it is.
+fn delimited<'a>(text: &'a str, marker: &str) -> Option<(&'a str, usize)> {
+    let after = &text[marker.len()..];
+    let end = after.find(marker)?;
+    let inner = &after[..end];
+    if inner.is_empty() || inner.starts_with(' ') {
+        return None;
+    }
+    Some((inner, marker.len() * 2 + end))
+}
+
+/// The label of a `[label](target)` link at the start of `text`, and the
+/// length of the whole link.
+fn link(text: &str) -> Option<(&str, usize)> {
+    let close = text.find("](")?;
+    let label = &text[1..close];
+    let target_end = text[close + 2..].find(')')?;
+    (!label.is_empty() && !label.contains('[')).then_some((label, close + 3 + target_end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Title\nintro\n## Install\n```sh\n# not a heading\n```\n### From source\nsteps\n## Usage\nrun it\n#nospace";
+
+    fn levels(headings: &[Heading]) -> Vec<(usize, usize)> {
+        headings
+            .iter()
+            .map(|heading| (heading.line, heading.level))
+            .collect()
+    }
+
+    fn plain(line: &Line) -> String {
+        line.spans
+            .iter()
+            .map(|span| span.content.as_ref())
+            .collect()
+    }
+
+    #[test]
+    fn headings_skip_fenced_code_and_missing_spaces() {
+        assert_eq!(levels(&headings(DOC)), [(0, 1), (2, 2), (6, 3), (8, 2)]);
+        assert!(headings("    # indented code").is_empty());
+    }
+
+    #[test]
+    fn section_of_is_the_last_heading_at_or_above() {
+        let headings = headings(DOC);
+        assert_eq!(
+            section_of(&headings, 5).map(|heading| heading.line),
+            Some(2)
+        );
+        assert_eq!(
+            section_of(&headings, 6).map(|heading| heading.line),
+            Some(6)
+        );
+        assert_eq!(
+            section_of(&headings, 9).map(|heading| heading.line),
+            Some(8)
+        );
+        assert!(section_of(&super::headings("intro\n# Later"), 0).is_none());
+    }
+
+    #[test]
+    fn collapsing_hides_a_section_until_the_next_peer() {
+        let headings = headings(DOC);
+        let line_count = DOC.lines().count();
+        assert_eq!(
+            visible_lines(line_count, &headings, &HashSet::new()),
+            (0..line_count).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            visible_lines(line_count, &headings, &HashSet::from([2])),
+            [0, 1, 2, 8, 9, 10]
+        );
+        assert_eq!(
+            visible_lines(line_count, &headings, &HashSet::from([6])),
+            [0, 1, 2, 3, 4, 5, 6, 8, 9, 10]
+        );
+        assert_eq!(
+            visible_lines(line_count, &headings, &HashSet::from([0])),
+            [0]
+        );
+    }
+
+    #[test]
+    fn to_text_keeps_one_line_per_source_line() {
+        let text = to_text(DOC);
+        assert_eq!(text.lines.len(), DOC.lines().count());
+        assert_eq!(plain(&text.lines[0]), "Title");
+        assert_eq!(plain(&text.lines[4]), "# not a heading");
+        assert_eq!(text.lines[4].style, CODE_STYLE);
+    }
+
+    #[test]
+    fn to_text_styles_inline_markup() {
+        let text =
+            to_text("- a **bold** [link](https://example.com) and `code`\n2 * 3 * 4\n> quoted");
+        assert_eq!(plain(&text.lines[0]), "• a bold link and code");
+        let bold = &text.lines[0].spans[3];
+        assert_eq!(
+            (bold.content.as_ref(), bold.style),
+            ("bold", Style::new().bold())
+        );
+        assert_eq!(plain(&text.lines[1]), "2 * 3 * 4");
+        assert_eq!(plain(&text.lines[2]), "│ quoted");
+    }
+
+    #[test]
+    fn headings_and_styling_agree_on_indented_fences() {
+        let doc = "intro\n    ```\n# Real\n```\n    ```\n# code\n```\n## After";
+        assert_eq!(levels(&headings(doc)), [(2, 1), (7, 2)]);
+
+        let text = to_text(doc);
+        assert_eq!(plain(&text.lines[1]), "    ```");
+        assert_eq!(plain(&text.lines[2]), "Real");
+        assert_eq!(text.lines[4].style, CODE_STYLE);
+        assert_eq!(text.lines[5].style, CODE_STYLE);
+        assert_eq!(plain(&text.lines[7]), "After");
+    }
+}
@@ -0,0 +1,487 @@
+use crate::config::{Case, MatchBackend};
+use std::fmt::Debug;
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+use unicode_properties::{GeneralCategory, UnicodeGeneralCategory};
+
+const BASE_SCORE: i64 = 100;
+const PREFIX_BONUS: i64 = 50;
+const BOUNDARY_BONUS: i64 = 25;
+
+/// A ranking strategy for the search query.
+pub trait Matcher: Debug {
+    /// Scores `candidate` against `query`. Higher is better, `None` means no
+    /// match. Also returns the character indices of `candidate` that matched.
+    /// With `ignore_case`, `query` is already lowercased and `candidate` is
+    /// lowercased as it is compared.
+    fn score(&self, query: &str, candidate: &str, ignore_case: bool) -> Option<(i64, Vec<usize>)>;
+}
+
+pub fn backend(kind: MatchBackend) -> Box<dyn Matcher> {
+    match kind {
+        MatchBackend::Substring => Box::new(Substring),
+        MatchBackend::Fuzzy => Box::new(Fuzzy),
+        MatchBackend::Layered => Box::new(Layered),
+        #[cfg(feature = "nucleo")]
+        MatchBackend::Nucleo => Box::new(Nucleo::default()),
+        #[cfg(feature = "skim")]
+        MatchBackend::Skim => Box::new(Skim::default()),
+        #[allow(unreachable_patterns)]
+        kind => {
+            log::warn!("match_backend {kind:?} is not built in, using fuzzy");
+            Box::new(Fuzzy)
+        }
+    }
+}
+
+fn is_boundary(ch: char) -> bool {
+    matches!(ch, '-' | '_' | '.' | ' ' | '/')
+}
+
+/// Lowercases `text` one character at a time, pairing each lowercase
+/// character with the byte range of the original character it came from.
+/// Lowercasing can change the number of characters (`İ` becomes two), so
+/// positions in `text.to_lowercase()` don't line up with `text` itself.
+/// Without `ignore_case` the characters are paired up unchanged.
+fn lowercase_chars(text: &str, ignore_case: bool) -> Vec<(Range<usize>, char)> {
+    text.char_indices()
+        .flat_map(|(start, ch)| {
+            let range = start..start + ch.len_utf8();
+            let lower: Vec<char> = if ignore_case {
+                ch.to_lowercase().collect()
+            } else {
+                vec![ch]
+            };
+            lower.into_iter().map(move |lower| (range.clone(), lower))
+        })
+        .collect()
+}
+
+/// Whether a query should match regardless of case under `case`: always,
+/// never, or with smart case only while it has no uppercase letters.
+pub fn ignores_case(case: Case, query: &str) -> bool {
+    match case {
+        Case::Insensitive => true,
+        Case::Sensitive => false,
+        Case::Smart => !query.chars().any(char::is_uppercase),
+    }
+}
+
+/// The character indices in `text` of the original characters behind
+/// `matched`, a run of `lowercase_chars` entries.
+fn char_indices_of(text: &str, matched: &[&Range<usize>]) -> Vec<usize> {
+    let starts: Vec<usize> = text.char_indices().map(|(start, _)| start).collect();
+    let mut indices: Vec<usize> = matched
+        .iter()
+        .filter_map(|range| starts.binary_search(&range.start).ok())
+        .collect();
+    indices.dedup();
+    indices
+}
+
+/// Finds the already-lowercased `term` in `text` ignoring case, returning
+/// the byte range it covers in `text`, which always falls on character
+/// boundaries.
+pub fn find_ignoring_case(text: &str, term: &str) -> Option<Range<usize>> {
+    let term: Vec<char> = term.chars().collect();
+    if term.is_empty() {
+        return None;
+    }
+
+    let chars = lowercase_chars(text, true);
+    let start = chars
+        .windows(term.len())
+        .position(|window| window.iter().map(|(_, ch)| *ch).eq(term.iter().copied()))?;
+    Some(chars[start].0.start..chars[start + term.len() - 1].0.end)
+}
+
+/// Points taken off a match that only worked with the separators removed, so
+/// it ranks below a literal match.
+const SEPARATOR_PENALTY: i64 = 10;
+
+/// Scores with `matcher`, retrying with `-`, `_`, `.` and spaces removed from
+/// both sides so `myapp` still finds `my_app` and `project-management` finds
+/// `project_management`.
+pub fn score_ignoring_separators(
+    matcher: &dyn Matcher,
+    query: &str,
+    candidate: &str,
+    ignore_case: bool,
+) -> Option<i64> {
+    if let Some((score, _)) = matcher.score(query, candidate, ignore_case) {
+        return Some(score);
+    }
+
+    let strip = |text: &str| -> String {
+        text.chars()
+            .filter(|&ch| !matches!(ch, '-' | '_' | '.' | ' '))
+            .collect()
+    };
+    let query = strip(query);
+    if query.is_empty() {
+        return None;
+    }
+    let (score, _) = matcher.score(&query, &strip(candidate), ignore_case)?;
+    Some(score - SEPARATOR_PENALTY)
+}
+
+/// Matches the query as one contiguous piece. Matches at the start of the
+/// name or of a word (after `-`, `_`, `.`, `/` or a space) score higher, as
+/// do earlier matches.
+#[derive(Debug)]
+pub struct Substring;
+
+impl Matcher for Substring {
+    fn score(&self, query: &str, candidate: &str, ignore_case: bool) -> Option<(i64, Vec<usize>)> {
+        let query: Vec<char> = query.chars().collect();
+        if query.is_empty() {
+            return Some((BASE_SCORE + PREFIX_BONUS, Vec::new()));
+        }
+
+        let lowered = lowercase_chars(candidate, ignore_case);
+        let start = lowered
+            .windows(query.len())
+            .position(|window| window.iter().map(|(_, ch)| *ch).eq(query.iter().copied()))?;
+
+        let bonus = match start.checked_sub(1).map(|before| lowered[before].1) {
+            None => PREFIX_BONUS,
+            Some(ch) if is_boundary(ch) => BOUNDARY_BONUS,
+            Some(_) => 0,
+        };
+
+        let matched: Vec<&Range<usize>> = lowered[start..start + query.len()]
+            .iter()
+            .map(|(range, _)| range)
+            .collect();
+        let indices = char_indices_of(candidate, &matched);
+        Some((BASE_SCORE + bonus - start as i64, indices))
+    }
+}
+
+const FUZZY_MATCH: i64 = 16;
+const FUZZY_BOUNDARY_BONUS: i64 = 8;
+const FUZZY_CONSECUTIVE_BONUS: i64 = 4;
+
+/// fzf-style matching: the query's characters must appear in order but may
+/// be spread out. Runs of consecutive characters and characters at word
+/// starts score higher; gaps between matched characters cost a point each.
+#[derive(Debug)]
+pub struct Fuzzy;
+
+impl Fuzzy {
+    /// Greedily matches `query` from `start`, where its first character sits.
+    fn score_from(query: &[char], candidate: &[char], start: usize) -> Option<(i64, Vec<usize>)> {
+        let mut indices = Vec::with_capacity(query.len());
+        let mut position = start;
+        for &ch in query {
+            position += candidate[position..].iter().position(|&c| c == ch)?;
+            indices.push(position);
+            position += 1;
+        }
+
+        let mut score = -(start as i64);
+        for (i, &index) in indices.iter().enumerate() {
+            score += FUZZY_MATCH;
+            if index == 0 || is_boundary(candidate[index - 1]) {
+                score += FUZZY_BOUNDARY_BONUS;
+            }
+            if i > 0 {
+                let gap = index - indices[i - 1] - 1;
+                if gap == 0 {
+                    score += FUZZY_CONSECUTIVE_BONUS;
+                }
+                score -= gap as i64;
+            }
+        }
+        Some((score, indices))
+    }
+}
+
+impl Matcher for Fuzzy {
+    fn score(&self, query: &str, candidate: &str, ignore_case: bool) -> Option<(i64, Vec<usize>)> {
+        let query: Vec<char> = query.chars().collect();
+        let lowered = lowercase_chars(candidate, ignore_case);
+        let chars: Vec<char> = lowered.iter().map(|(_, ch)| *ch).collect();
+        let first = *query.first()?;
+
+        // Try every occurrence of the first character and keep the best run.
+        let (score, positions) = (0..chars.len())
+            .filter(|&start| chars[start] == first)
+            .filter_map(|start| Self::score_from(&query, &chars, start))
+            .max_by_key(|(score, _)| *score)?;
+
+        let matched: Vec<&Range<usize>> = positions
+            .iter()
+            .map(|&position| &lowered[position].0)
+            .collect();
+        Some((score, char_indices_of(candidate, &matched)))
+    }
+}
+
+/// Fuzzy matching by the `nucleo-matcher` crate, as in the Helix editor.
+/// Its matcher keeps scratch space between calls, hence the `RefCell`.
+#[cfg(feature = "nucleo")]
+#[derive(Debug, Default)]
+pub struct Nucleo {
+    matcher: std::cell::RefCell<nucleo_matcher::Matcher>,
+}
+
+#[cfg(feature = "nucleo")]
+impl Matcher for Nucleo {
+    fn score(&self, query: &str, candidate: &str, ignore_case: bool) -> Option<(i64, Vec<usize>)> {
+        use nucleo_matcher::Utf32Str;
+
+        let mut matcher = self.matcher.borrow_mut();
+        matcher.config.ignore_case = ignore_case;
+        // `normalize_diacritics` already decides whether accents matter.
+        matcher.config.normalize = false;
+        let (mut haystack, mut needle) = (Vec::new(), Vec::new());
+        let mut indices = Vec::new();
+        let score = matcher.fuzzy_indices(
+            Utf32Str::new(candidate, &mut haystack),
+            Utf32Str::new(query, &mut needle),
+            &mut indices,
+        )?;
+        indices.sort_unstable();
+        indices.dedup();
+        Some((
+            i64::from(score),
+            indices.into_iter().map(|index| index as usize).collect(),
+        ))
+    }
+}
+
+/// Fuzzy matching by the `fuzzy-matcher` crate's skim algorithm.
+#[cfg(feature = "skim")]
+pub struct Skim {
+    ignoring_case: fuzzy_matcher::skim::SkimMatcherV2,
+    respecting_case: fuzzy_matcher::skim::SkimMatcherV2,
+}
+
+#[cfg(feature = "skim")]
+impl Default for Skim {
+    fn default() -> Self {
+        use fuzzy_matcher::skim::SkimMatcherV2;
+
+        Self {
+            ignoring_case: SkimMatcherV2::default().ignore_case(),
+            respecting_case: SkimMatcherV2::default().respect_case(),
+        }
+    }
+}
+
+#[cfg(feature = "skim")]
+impl Debug for Skim {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Skim").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "skim")]
+impl Matcher for Skim {
+    fn score(&self, query: &str, candidate: &str, ignore_case: bool) -> Option<(i64, Vec<usize>)> {
+        use fuzzy_matcher::FuzzyMatcher;
+
+        let matcher = if ignore_case {
+            &self.ignoring_case
+        } else {
+            &self.respecting_case
+        };
+        matcher.fuzzy_indices(candidate, query)
+    }
+}
+
+/// The score gap between `Layered` tiers, larger than any score one tier
+/// can produce on its own.
+const LAYER: i64 = 1_000_000;
+
+/// Tries the query as a prefix, then as a substring anywhere, then fuzzily.
+/// Each tier outranks the next outright, so an exact prefix always beats a
+/// substring match, which always beats a fuzzy one; within a tier the usual
+/// scores decide. A candidate is only ever counted in its best tier.
+#[derive(Debug)]
+pub struct Layered;
+
+impl Matcher for Layered {
+    fn score(&self, query: &str, candidate: &str, ignore_case: bool) -> Option<(i64, Vec<usize>)> {
+        if let Some((score, indices)) = Substring.score(query, candidate, ignore_case) {
+            // The first occurrence sits at the start exactly when the
+            // candidate starts with the query.
+            let tier = if indices.first().is_none_or(|&index| index == 0) {
+                2
+            } else {
+                1
+            };
+            return Some((tier * LAYER + score, indices));
+        }
+        Fuzzy.score(query, candidate, ignore_case)
+    }
+}
+
+/// Folds accented letters to their base, keeping their case (`café` →
+/// `cafe`, `Ñandú` → `Nandu`, `straße` → `strasse`): the text is decomposed
+/// to NFD and the nonspacing marks that leaves are dropped.
+pub fn fold_diacritics(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for ch in text.nfd() {
+        if ch.general_category() == GeneralCategory::NonspacingMark {
+            continue;
+        }
+        let mut lower = ch.to_lowercase();
+        match (lower.next(), lower.next()) {
+            (Some(lower), None) if lower != ch => match ascii_spelling(lower) {
+                Some(base) => folded.push_str(&base.to_uppercase()),
+                None => folded.push(ch),
+            },
+            _ => match ascii_spelling(ch) {
+                Some(base) => folded.push_str(base),
+                None => folded.push(ch),
+            },
+        }
+    }
+    folded
+}
+
+/// The ASCII spelling of a lowercase letter that NFD leaves whole because
+/// its stroke or ligature isn't a separate mark.
+fn ascii_spelling(ch: char) -> Option<&'static str> {
+    let base = match ch {
+        'đ' | 'ð' => "d",
+        'ħ' => "h",
+        'ı' => "i",
+        'ŀ' | 'ł' => "l",
+        'ø' => "o",
+        'ŧ' => "t",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'þ' => "th",
+        _ => return None,
+    };
+    Some(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators_are_optional() {
+        for name in ["my-app", "my_app", "my.app", "my app"] {
+            for matcher in [&Substring as &dyn Matcher, &Fuzzy, &Layered] {
+                assert!(
+                    score_ignoring_separators(matcher, "myapp", name, true).is_some(),
+                    "{matcher:?} should find {name}"
+                );
+            }
+        }
+        assert!(
+            score_ignoring_separators(&Substring, "projectmanagement", "project-management", true)
+                .is_some()
+        );
+        assert!(
+            score_ignoring_separators(&Substring, "project_management", "project-management", true)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn a_literal_match_beats_one_without_separators() {
+        let literal = score_ignoring_separators(&Substring, "myapp", "myapp", true).unwrap();
+        let stripped = score_ignoring_separators(&Substring, "myapp", "my-app", true).unwrap();
+        assert!(literal > stripped);
+    }
+
+    #[test]
+    fn a_query_of_only_separators_matches_nothing_extra() {
+        assert_eq!(
+            score_ignoring_separators(&Substring, "--", "myapp", true),
+            None
+        );
+    }
+
+    #[test]
+    fn smart_case_ignores_case_until_an_uppercase_letter() {
+        assert!(ignores_case(Case::Smart, "api"));
+        assert!(!ignores_case(Case::Smart, "Api"));
+        assert!(!ignores_case(Case::Sensitive, "api"));
+        assert!(ignores_case(Case::Insensitive, "Api"));
+    }
+
+    #[test]
+    fn layered_ranks_prefix_then_substring_then_fuzzy() {
+        let score = |name| Layered.score("api", name, true).map(|(score, _)| score);
+        let prefix = score("apiary-long-name-here").unwrap();
+        let substring = score("my-api").unwrap();
+        let fuzzy = score("a-plain-idea").unwrap();
+        assert!(prefix > substring, "{prefix} <= {substring}");
+        assert!(substring > fuzzy, "{substring} <= {fuzzy}");
+        assert_eq!(score("nothing"), None);
+    }
+
+    #[test]
+    fn layered_counts_a_name_in_its_best_tier_only() {
+        // `api` is both a prefix and a fuzzy match; only the prefix counts.
+        let (score, indices) = Layered.score("api", "api", true).unwrap();
+        assert!(score > 2 * LAYER);
+        assert_eq!(indices, [0, 1, 2]);
+    }
+
+    #[test]
+    fn indices_are_characters_not_bytes() {
+        let name = "日本語プロジェクト";
+        for matcher in [&Substring as &dyn Matcher, &Fuzzy, &Layered] {
+            let (_, indices) = matcher.score("プロ", name, true).unwrap();
+            assert_eq!(indices, [3, 4], "{matcher:?}");
+        }
+        let (_, indices) = Fuzzy.score("日ジ", name, true).unwrap();
+        assert_eq!(indices, [0, 5]);
+    }
+
+    #[test]
+    fn lowercasing_that_grows_keeps_indices_in_place() {
+        // `İ` lowercases to two characters; the match after it must still
+        // point at the original positions.
+        let (_, indices) = Substring.score("stanbul", "İstanbul", true).unwrap();
+        assert_eq!(indices, [1, 2, 3, 4, 5, 6, 7]);
+        let range = find_ignoring_case("Café Ürün", "ürün").unwrap();
+        assert_eq!(&"Café Ürün"[range], "Ürün");
+    }
+
+    #[test]
+    fn folds_diacritics_keeping_case() {
+        assert_eq!(fold_diacritics("café-api"), "cafe-api");
+        assert_eq!(fold_diacritics("Élan Ñandú Öl"), "Elan Nandu Ol");
+        assert_eq!(fold_diacritics("straße Æther"), "strasse AEther");
+        assert_eq!(fold_diacritics("cafe\u{0301}"), "cafe");
+        assert_eq!(fold_diacritics("Tiếng Việt Łódź"), "Tieng Viet Lodz");
+        assert_eq!(fold_diacritics("日本 plain"), "日本 plain");
+    }
+
+    #[test]
+    fn folded_uppercase_matches_case_sensitively() {
+        let folded = fold_diacritics("Économie");
+        assert!(Substring.score("Eco", &folded, false).is_some());
+        assert!(Substring.score("eco", &folded, false).is_none());
+    }
+
+    #[cfg(feature = "nucleo")]
+    #[test]
+    fn nucleo_matches_in_order_and_minds_case() {
+        let nucleo = Nucleo::default();
+        let (_, indices) = nucleo.score("plr", "pl-rust", true).unwrap();
+        assert_eq!(indices, [0, 1, 3]);
+        assert!(nucleo.score("rlp", "pl-rust", true).is_none());
+        assert!(nucleo.score("PL", "pl-rust", false).is_none());
+    }
+
+    #[cfg(feature = "skim")]
+    #[test]
+    fn skim_matches_in_order_and_minds_case() {
+        let skim = Skim::default();
+        let (_, indices) = skim.score("plr", "pl-rust", true).unwrap();
+        assert_eq!(indices, [0, 1, 3]);
+        assert!(skim.score("rlp", "pl-rust", true).is_none());
+        assert!(skim.score("PL", "pl-rust", false).is_none());
+    }
+}
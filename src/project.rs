@@ -1,39 +1,553 @@
-use std::ffi::OsString;
-use std::fs::{self, DirEntry};
+use crate::config::{SearchScope, UserConfig};
+use crate::glob::glob_match;
+use crate::{git, launch};
+use ignore::WalkBuilder;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::cmp::{Ordering, Reverse};
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(Debug, Clone)]
 pub struct Project {
     pub project_name: OsString,
     pub project_path: PathBuf,
     pub project_remote: String,
+    /// The `project_dirs` entry the project was found under, or `None` for
+    /// one listed in `projects`.
+    pub project_root: Option<PathBuf>,
+    /// A bare repository, which usually has worktrees to open instead.
+    pub bare: bool,
+    /// The checked-out branch when this is a linked worktree of another
+    /// repository.
+    pub worktree: Option<String>,
+    /// The `project_markers` entry that made the directory a project, like
+    /// `Cargo.toml`; `None` when it was found some other way.
+    pub marker: Option<String>,
 }
 
-pub fn get_all_projects(proj_dirs: &[String]) -> Vec<Project> {
-    let mut projects: Vec<Project> = proj_dirs
+impl Project {
+    /// The `org/repo` part of the remote URL, or an empty string without a remote.
+    pub fn remote_slug(&self) -> &str {
+        let remote = self.project_remote.trim_end_matches('/');
+        let without_scheme = remote.split_once("://").map_or(remote, |(_, rest)| rest);
+
+        without_scheme.split_once('/').map_or("", |(_, path)| path)
+    }
+
+    /// The text the search query is matched against for the given scope.
+    pub fn search_text(&self, scope: SearchScope) -> String {
+        let name = self.project_name.to_string_lossy();
+        match scope {
+            SearchScope::Name => name.into_owned(),
+            SearchScope::Remote => self.remote_slug().to_string(),
+            SearchScope::All => format!("{name} {}", self.remote_slug()),
+        }
+    }
+}
+
+/// A non-fatal problem found while discovering projects.
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+impl ScanError {
+    fn new(path: PathBuf, reason: impl Into<String>) -> Self {
+        Self {
+            path,
+            reason: reason.into(),
+        }
+    }
+}
+
+pub fn get_all_projects(config: &UserConfig) -> (Vec<Project>, Vec<ScanError>) {
+    discover_projects(config, &mut |_| {})
+}
+
+/// Like `get_all_projects`, but hands each batch of projects to `found` as
+/// soon as it is known, one per `project_dirs` root, so they can be shown
+/// while slower roots are still being read. The returned list is the whole,
+/// sorted result.
+pub fn discover_projects(
+    config: &UserConfig,
+    found: &mut dyn FnMut(&[Project]),
+) -> (Vec<Project>, Vec<ScanError>) {
+    let mut projects = Vec::new();
+    let mut errors = Vec::new();
+
+    if let Some(command) = &config.project_source_command {
+        log::debug!("listing projects with `{command}`");
+        projects_from_command(command, &mut projects, &mut errors);
+        found(&projects);
+    } else if config.use_ghq && projects_from_ghq(&mut projects, &mut errors) {
+        found(&projects);
+    } else {
+        scan_project_dirs(config, &mut projects, &mut errors, found);
+    }
+    let listed = projects.len();
+
+    let mut seen: HashSet<PathBuf> = projects
         .iter()
-        .map(|dir| parse_dir(dir))
-        .flat_map(|dir| {
-            fs::read_dir(&dir)
-                .ok()
-                .into_iter()
-                .flat_map(|read_dir| read_dir.filter_map(Result::ok))
-        })
-        .filter(|entry| entry.path().join(".git").exists())
-        .map(|entry| Project {
-            project_name: entry.file_name(),
-            project_path: entry.path(),
-            project_remote: get_remote(&entry).unwrap_or("".to_string()),
+        .map(|project| project.project_path.clone())
+        .collect();
+    for path in config.projects.iter().map(|path| parse_dir(path)) {
+        if !path.is_dir() {
+            errors.push(ScanError::new(path, "listed in `projects` but not found"));
+        } else if seen.insert(path.clone()) {
+            projects.push(project_from_path(path, None, &mut errors));
+        }
+    }
+    // `git worktree add` often puts worktrees beside the repository rather
+    // than anywhere the scan looks.
+    if config.include_worktrees {
+        for index in 0..projects.len() {
+            for path in git::linked_worktrees(&projects[index].project_path) {
+                if seen.insert(path.clone()) {
+                    let root = projects[index].project_root.clone();
+                    projects.push(project_from_path(path, root, &mut errors));
+                }
+            }
+        }
+    }
+    found(&projects[listed..]);
+
+    projects.sort_by(|a, b| natural_cmp(&a.project_name, &b.project_name));
+    log::info!(
+        "found {} projects with {} scan errors",
+        projects.len(),
+        errors.len()
+    );
+    (projects, errors)
+}
+
+/// Orders names the way people read them: runs of digits compare by their
+/// value, so `v2` comes before `v10`, and everything else compares as text.
+/// Equal values with more leading zeros go after, keeping the order total.
+pub fn natural_cmp(a: &OsStr, b: &OsStr) -> Ordering {
+    let (a, b) = (a.to_string_lossy(), b.to_string_lossy());
+    let (mut a, mut b) = (a.as_ref(), b.as_ref());
+
+    loop {
+        let (Some(a_first), Some(b_first)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if a_first.is_ascii_digit() && b_first.is_ascii_digit() {
+            let a_end = a.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(a.len());
+            let b_end = b.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(b.len());
+            let (a_digits, b_digits) = (&a[..a_end], &b[..b_end]);
+            let (a_value, b_value) = (
+                a_digits.trim_start_matches('0'),
+                b_digits.trim_start_matches('0'),
+            );
+            let ordering = a_value
+                .len()
+                .cmp(&b_value.len())
+                .then_with(|| a_value.cmp(b_value))
+                .then_with(|| a_digits.len().cmp(&b_digits.len()));
+            if ordering.is_ne() {
+                return ordering;
+            }
+            (a, b) = (&a[a_end..], &b[b_end..]);
+        } else {
+            if a_first != b_first {
+                return a_first.cmp(&b_first);
+            }
+            (a, b) = (&a[a_first.len_utf8()..], &b[b_first.len_utf8()..]);
+        }
+    }
+}
+
+/// Takes the project list from the stdout of `project_source_command`, one
+/// path per line, instead of scanning `project_dirs`.
+fn projects_from_command(command: &str, projects: &mut Vec<Project>, errors: &mut Vec<ScanError>) {
+    let source = PathBuf::from(format!("project_source_command `{command}`"));
+
+    #[cfg(windows)]
+    let output = std::process::Command::new("cmd")
+        .args(["/C", command])
+        .output();
+    #[cfg(not(windows))]
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .output();
+
+    let output = match output {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let reason = match stderr.lines().next() {
+                Some(line) => format!("failed ({}): {line}", output.status),
+                None => format!("failed ({})", output.status),
+            };
+            errors.push(ScanError::new(source, reason));
+            return;
+        }
+        Err(err) => {
+            errors.push(ScanError::new(source, err.to_string()));
+            return;
+        }
+    };
+
+    let mut seen = HashSet::new();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let path = parse_dir(line);
+        if !path.is_dir() {
+            errors.push(ScanError::new(
+                path,
+                "printed by project_source_command but not found",
+            ));
+        } else if seen.insert(path.clone()) {
+            projects.push(project_from_path(path, None, errors));
+        }
+    }
+}
+
+/// Lists the repositories managed by `ghq`, named by their `org/repo` path
+/// under the ghq root. Returns `false` when `ghq` can't be run, so the
+/// regular scan is used instead.
+fn projects_from_ghq(projects: &mut Vec<Project>, errors: &mut Vec<ScanError>) -> bool {
+    let run = |args: &[&str]| -> Option<String> {
+        let output = std::process::Command::new("ghq").args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    let (Some(roots), Some(paths)) = (run(&["root", "--all"]), run(&["list", "--full-path"]))
+    else {
+        errors.push(ScanError::new(
+            PathBuf::from("ghq"),
+            "use_ghq is set but `ghq` could not be run; scanning project_dirs instead",
+        ));
+        return false;
+    };
+    let roots: Vec<PathBuf> = roots.lines().map(PathBuf::from).collect();
+
+    for path in paths.lines().map(PathBuf::from) {
+        let mut project = project_from_path(path.clone(), None, errors);
+        // `<root>/github.com/org/repo` is shown as `org/repo`.
+        if let Some(relative) = roots.iter().find_map(|root| path.strip_prefix(root).ok()) {
+            let mut components = relative.components();
+            components.next();
+            if !components.as_path().as_os_str().is_empty() {
+                project.project_name = components.as_path().as_os_str().to_os_string();
+            }
+        }
+        projects.push(project);
+    }
+    true
+}
+
+fn scan_project_dirs(
+    config: &UserConfig,
+    projects: &mut Vec<Project>,
+    errors: &mut Vec<ScanError>,
+    found: &mut dyn FnMut(&[Project]),
+) {
+    let roots = config.project_dirs.iter().map(|project_dir| RootScan {
+        root: parse_dir(project_dir.path()),
+        max_depth: project_dir.max_depth(config.max_depth),
+        require_git: project_dir.require_git(),
+        markers: config.project_markers.clone(),
+        exclude: config
+            .exclude
+            .iter()
+            .map(|pattern| {
+                if pattern.contains('/') {
+                    parse_dir(pattern).to_string_lossy().into_owned()
+                } else {
+                    pattern.clone()
+                }
+            })
+            .collect(),
+        detect_bare: config.detect_bare,
+        include_worktrees: config.include_worktrees,
+    });
+
+    let Some(timeout) = config.scan_timeout_ms.map(Duration::from_millis) else {
+        for root in roots {
+            let before = projects.len();
+            root.run(projects, errors);
+            found(&projects[before..]);
+        }
+        return;
+    };
+
+    // Each root gets its own thread, and whatever hasn't finished by the
+    // deadline is left behind: a `read_dir` stuck on a dead mount can't be
+    // interrupted, only abandoned.
+    let (tx, rx) = mpsc::channel();
+    let roots: Vec<PathBuf> = roots
+        .enumerate()
+        .map(|(index, root)| {
+            let path = root.root.clone();
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let (mut projects, mut errors) = (Vec::new(), Vec::new());
+                root.run(&mut projects, &mut errors);
+                let _ = tx.send((index, projects, errors));
+            });
+            path
         })
         .collect();
+    drop(tx);
+
+    let deadline = Instant::now() + timeout;
+    let mut results: Vec<Option<(Vec<Project>, Vec<ScanError>)>> = vec![None; roots.len()];
+    while results.iter().any(Option::is_none) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        match rx.recv_timeout(left) {
+            Ok((index, projects, failed)) => {
+                found(&projects);
+                results[index] = Some((projects, failed));
+            }
+            Err(_) => break,
+        }
+    }
+
+    for (root, result) in roots.into_iter().zip(results) {
+        match result {
+            Some((found, failed)) => {
+                projects.extend(found);
+                errors.extend(failed);
+            }
+            None => {
+                log::warn!("scanning {} timed out", root.display());
+                errors.push(ScanError::new(
+                    root,
+                    format!("timed out after {} ms", timeout.as_millis()),
+                ));
+            }
+        }
+    }
+}
+
+/// One `project_dirs` root to scan, owning what it needs so it can run on
+/// its own thread.
+struct RootScan {
+    root: PathBuf,
+    max_depth: usize,
+    require_git: bool,
+    markers: Vec<String>,
+    /// `exclude`, with `~` expanded in the patterns that have a `/`.
+    exclude: Vec<String>,
+    detect_bare: bool,
+    include_worktrees: bool,
+}
+
+impl RootScan {
+    fn run(self, projects: &mut Vec<Project>, errors: &mut Vec<ScanError>) {
+        log::debug!("scanning {}", self.root.display());
+        let scan = Scan {
+            root: &self.root,
+            require_git: self.require_git,
+            markers: &self.markers,
+            exclude: &self.exclude,
+            detect_bare: self.detect_bare,
+            include_worktrees: self.include_worktrees,
+            ignore: load_pl_ignore(&self.root),
+        };
+        let before = projects.len();
+        scan.dir(&self.root, self.max_depth, projects, errors);
 
-    projects.sort_by(|a, b| a.project_name.cmp(&b.project_name));
-    projects
+        // A root that is a repository itself, like `~/dotfiles`, has no
+        // projects below it, so it is listed as one.
+        if projects.len() == before
+            && let Ok(Some(marker)) = scan.marker_of(&self.root)
+        {
+            let marker = marker.to_string();
+            let root = self.root.clone();
+            let mut project = project_from_path(self.root, Some(root), errors);
+            project.marker = Some(marker);
+            projects.push(project);
+        }
+    }
 }
 
-fn get_remote(entry: &DirEntry) -> Result<String, io::Error> {
-    let path = entry.path();
+/// Settings for walking one `project_dirs` root.
+struct Scan<'a> {
+    root: &'a Path,
+    require_git: bool,
+    markers: &'a [String],
+    exclude: &'a [String],
+    detect_bare: bool,
+    include_worktrees: bool,
+    ignore: Gitignore,
+}
+
+/// Reads the gitignore patterns in a root's `.pl-ignore`, naming
+/// directories under it to leave out. A missing file ignores nothing, and
+/// lines that don't parse are skipped with a warning.
+fn load_pl_ignore(root: &Path) -> Gitignore {
+    let path = root.join(".pl-ignore");
+    if !path.is_file() {
+        return Gitignore::empty();
+    }
+
+    let mut builder = GitignoreBuilder::new(root);
+    if let Some(err) = builder.add(&path) {
+        log::warn!("{}: {err}", path.display());
+    }
+    builder.build().unwrap_or_else(|err| {
+        log::warn!("{}: {err}", path.display());
+        Gitignore::empty()
+    })
+}
+
+impl Scan<'_> {
+    /// Whether `path` matches an `exclude` glob: the whole path for a
+    /// pattern with a `/`, otherwise just the directory name.
+    fn is_excluded(&self, path: &Path) -> bool {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let full = path.to_string_lossy();
+        self.exclude.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern, &full)
+            } else {
+                glob_match(pattern, &name)
+            }
+        })
+    }
+
+    /// The first of the `project_markers` that `path` holds.
+    fn marker_of(&self, path: &Path) -> io::Result<Option<&str>> {
+        for marker in self.markers {
+            if path.join(marker).try_exists()? {
+                return Ok(Some(marker));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Collects the projects in `dir`, descending into directories that
+    /// aren't projects while `depth` allows; a project's own subdirectories
+    /// are never searched. Without `require_git`, any directory at the last
+    /// level counts as a project.
+    fn dir(
+        &self,
+        dir: &Path,
+        depth: usize,
+        projects: &mut Vec<Project>,
+        errors: &mut Vec<ScanError>,
+    ) {
+        let read_dir = match fs::read_dir(dir) {
+            Ok(read_dir) => read_dir,
+            Err(err) => {
+                log::warn!("can't read {}: {err}", dir.display());
+                errors.push(ScanError::new(dir.to_path_buf(), err.to_string()));
+                return;
+            }
+        };
+
+        for entry in read_dir {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    errors.push(ScanError::new(dir.to_path_buf(), err.to_string()));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            // Everything listed might be a project, so it's matched as a
+            // directory.
+            if self.ignore.matched(&path, true).is_ignore() {
+                log::debug!("{} is ignored by .pl-ignore", path.display());
+                continue;
+            }
+            if self.is_excluded(&path) {
+                log::debug!("{} is excluded", path.display());
+                continue;
+            }
+
+            let (is_project, marker) = match self.marker_of(&path) {
+                Ok(Some(_)) if !self.include_worktrees && git::worktree_branch(&path).is_some() => {
+                    log::debug!("skipping worktree {}", path.display());
+                    continue;
+                }
+                Ok(Some(marker)) => (true, Some(marker)),
+                Ok(None) => (self.detect_bare && is_bare_repo(&path), None),
+                Err(err) => {
+                    errors.push(ScanError::new(path, err.to_string()));
+                    continue;
+                }
+            };
+
+            if is_project {
+                let mut project = project_from_path(path, Some(self.root.to_path_buf()), errors);
+                project.marker = marker.map(str::to_string);
+                projects.push(project);
+                continue;
+            }
+            if !path.is_dir() || entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if depth > 1 {
+                self.dir(&path, depth - 1, projects, errors);
+            } else if !self.require_git {
+                projects.push(project_from_path(
+                    path,
+                    Some(self.root.to_path_buf()),
+                    errors,
+                ));
+            }
+        }
+    }
+}
+
+fn project_from_path(path: PathBuf, root: Option<PathBuf>, errors: &mut Vec<ScanError>) -> Project {
+    let project_name = path.file_name().unwrap_or(path.as_os_str()).to_os_string();
+    if project_name.to_str().is_none() {
+        errors.push(ScanError::new(path.clone(), "name is not valid UTF-8"));
+    }
+
+    Project {
+        project_name,
+        project_remote: get_remote(&path).unwrap_or("".to_string()),
+        bare: is_bare_repo(&path),
+        worktree: git::worktree_branch(&path),
+        marker: None,
+        project_path: path,
+        project_root: root,
+    }
+}
+
+/// A bare repository keeps `HEAD`, `objects` and `refs` at its top level
+/// instead of in a `.git` directory.
+fn is_bare_repo(path: &Path) -> bool {
+    !path.join(".git").exists()
+        && path.join("HEAD").is_file()
+        && path.join("objects").is_dir()
+        && path.join("refs").is_dir()
+}
+
+/// A project from the discovery history, which may no longer exist. Its
+/// remote isn't read, as that would mean running git for every entry.
+pub fn history_project(path: PathBuf) -> Project {
+    Project {
+        project_name: path.file_name().unwrap_or(path.as_os_str()).to_os_string(),
+        project_remote: String::new(),
+        project_root: None,
+        bare: false,
+        worktree: None,
+        marker: None,
+        project_path: path,
+    }
+}
+
+fn get_remote(path: &Path) -> Result<String, io::Error> {
     let mut command = std::process::Command::new("git");
     command
         .args(["config", "--get", "remote.origin.url"])
@@ -84,18 +598,992 @@ fn convert_git_remote_to_url(remote: String) -> String {
     remote.trim_end_matches(".git").to_string()
 }
 
-fn parse_dir(proj_dir: &str) -> PathBuf {
+/// Expands `$VAR` / `${VAR}` and a leading `~` in a configured path.
+pub fn parse_dir(proj_dir: &str) -> PathBuf {
+    let proj_dir = expand_env(proj_dir);
     if proj_dir == "~" {
         return home_dir();
     }
 
-    if let Some(stripped) = proj_dir.strip_prefix("~/") {
+    // `is_separator` also accepts `\` on Windows.
+    if let Some(stripped) = proj_dir
+        .strip_prefix('~')
+        .and_then(|rest| rest.strip_prefix(std::path::is_separator))
+    {
         return home_dir().join(stripped);
     }
 
     PathBuf::from(proj_dir)
 }
 
+/// Replaces `$VAR` and `${VAR}` with their values. Unset variables expand to
+/// nothing; a `$` not followed by a name is kept.
+fn expand_env(value: &str) -> String {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            match braced.split_once('}') {
+                Some((name, remainder)) => (name, remainder),
+                None => ("", after),
+            }
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+
+        if name.is_empty() {
+            expanded.push('$');
+            rest = after;
+        } else {
+            expanded.push_str(&std::env::var(name).unwrap_or_default());
+            rest = remainder;
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Creates `root/name` as a new git repository. `template` is copied into it
+/// first, with `{{name}}` in text files replaced by the project name, then
+/// the `commands` run in it in order, with `input` for `{input}`. `git init`
+/// is skipped when they already made a repository, as `git clone` does.
+/// Nothing is left behind if any step fails.
+pub fn create_project(
+    root: &Path,
+    name: &str,
+    template: Option<&Path>,
+    commands: &[String],
+    input: &str,
+) -> io::Result<Project> {
+    check_project_name(name)?;
+    if let Some(template) = template
+        && !template.is_dir()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("template {} not found", template.display()),
+        ));
+    }
+
+    let path = root.join(name);
+    fs::create_dir(&path)?;
+
+    let result = template
+        .map_or(Ok(()), |template| copy_template(template, &path, name))
+        .and_then(|()| {
+            commands
+                .iter()
+                .try_for_each(|command| run_setup_command(command, &path, name, input))
+        })
+        .and_then(|()| {
+            if path.join(".git").exists() {
+                return Ok(());
+            }
+            let status = std::process::Command::new("git")
+                .args(["init", "--quiet"])
+                .current_dir(&path)
+                .status()?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(io::Error::other(format!("git init failed ({status})")))
+            }
+        });
+    if let Err(err) = result {
+        let _ = fs::remove_dir_all(&path);
+        return Err(err);
+    }
+
+    let mut errors = Vec::new();
+    Ok(project_from_path(
+        path,
+        Some(root.to_path_buf()),
+        &mut errors,
+    ))
+}
+
+/// Runs one of a template's `commands`, its output captured so it doesn't
+/// draw over the TUI. A failure reports the last line it printed.
+fn run_setup_command(template: &str, path: &Path, name: &str, input: &str) -> io::Result<()> {
+    let mut command = launch::setup_command(template, path, name, input)?;
+    log::info!("running template command {command:?}");
+    let output = command.stdin(std::process::Stdio::null()).output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let reason = stderr
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .map_or_else(|| output.status.to_string(), |line| line.trim().to_string());
+    Err(io::Error::other(format!("`{template}` failed: {reason}")))
+}
+
+fn copy_template(from: &Path, to: &Path, name: &str) -> io::Result<()> {
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            // A template that is itself a repo shouldn't bring its history.
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            fs::create_dir(&target)?;
+            copy_template(&entry.path(), &target, name)?;
+        } else {
+            match String::from_utf8(fs::read(entry.path())?) {
+                Ok(text) => fs::write(&target, text.replace("{{name}}", name))?,
+                Err(err) => fs::write(&target, err.into_bytes())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Refuses names that aren't a single path component.
+fn check_project_name(name: &str) -> io::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(std::path::is_separator) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid project name '{name}'"),
+        ));
+    }
+    Ok(())
+}
+
+/// Renames the project directory at `path` to `name` within the same parent,
+/// refusing to replace anything already there. Returns the new path.
+pub fn rename_project(path: &Path, name: &str) -> io::Result<PathBuf> {
+    check_project_name(name)?;
+    let parent = path
+        .parent()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "project has no parent"))?;
+
+    let target = parent.join(name);
+    let taken = || {
+        io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", target.display()),
+        )
+    };
+    if target.try_exists()? {
+        return Err(taken());
+    }
+
+    // Something can still appear at `target` after the check. A non-empty
+    // directory or, on some platforms, any entry makes the rename fail,
+    // reported the same way; an empty directory created in between is
+    // replaced on Unix, which has no portable way to refuse it.
+    match fs::rename(path, &target) {
+        Ok(()) => Ok(target),
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::AlreadyExists | io::ErrorKind::DirectoryNotEmpty
+            ) =>
+        {
+            Err(taken())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Moves the project at `path` into `archive_dir`, appending `-1`, `-2`, …
+/// to the name if it is taken. Returns where the project ended up.
+pub fn archive_project(path: &Path, archive_dir: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "project has no name"))?;
+
+    fs::create_dir_all(archive_dir)?;
+
+    let mut target = archive_dir.join(name);
+    let mut suffix = 1;
+    while target.exists() {
+        let mut candidate = name.to_os_string();
+        candidate.push(format!("-{suffix}"));
+        target = archive_dir.join(candidate);
+        suffix += 1;
+    }
+
+    fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Formats `path` for display, shortening the home directory to `~` unless
+/// `absolute` is set.
+pub fn display_path(path: &Path, absolute: bool) -> String {
+    if !absolute
+        && let Some(home) = dirs::home_dir()
+        && let Ok(relative) = path.strip_prefix(&home)
+    {
+        return Path::new("~").join(relative).display().to_string();
+    }
+
+    path.display().to_string()
+}
+
+/// Guesses the main language from well-known manifest files in the root.
+pub fn detect_language(path: &Path) -> Option<&'static str> {
+    const MARKERS: [(&str, &str); 10] = [
+        ("Cargo.toml", "Rust"),
+        ("go.mod", "Go"),
+        ("package.json", "JavaScript"),
+        ("pyproject.toml", "Python"),
+        ("setup.py", "Python"),
+        ("Gemfile", "Ruby"),
+        ("pom.xml", "Java"),
+        ("build.gradle", "Java"),
+        ("mix.exs", "Elixir"),
+        ("CMakeLists.txt", "C/C++"),
+    ];
+
+    MARKERS
+        .iter()
+        .find(|(marker, _)| path.join(marker).exists())
+        .map(|(_, language)| *language)
+}
+
 fn home_dir() -> PathBuf {
     dirs::home_dir().expect("could not determine home directory")
 }
+
+/// The files under `path` that git wouldn't ignore, hidden ones included
+/// but not `.git` itself. Symlinks are not followed.
+fn project_files(path: &Path) -> impl Iterator<Item = ignore::DirEntry> {
+    WalkBuilder::new(path)
+        .hidden(false)
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry
+                .file_type()
+                .is_some_and(|file_type| file_type.is_file())
+        })
+}
+
+/// Sums the size of the project's files, leaving out what git ignores,
+/// like build output and dependencies.
+pub fn dir_size(path: &Path) -> u64 {
+    project_files(path)
+        .filter_map(|entry| entry.metadata().ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+const TREE_ENTRY_CAP: usize = 200;
+
+/// The file called `name` in `dir`, or failing that one whose name differs
+/// only in ASCII case, so `README.md` also finds `readme.md` and `Readme.md`
+/// on case-sensitive file systems.
+pub fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.is_file() {
+        return Some(exact);
+    }
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .find(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .is_some_and(|entry_name| entry_name.eq_ignore_ascii_case(name))
+                && entry.path().is_file()
+        })
+        .map(|entry| entry.path())
+}
+
+/// Lists the top-level entries of `path`, directories first, for projects
+/// without a preview file. Hidden entries are skipped unless `show_hidden`.
+pub fn tree_preview(path: &Path, show_hidden: bool) -> String {
+    let Ok(read_dir) = fs::read_dir(path) else {
+        return String::new();
+    };
+
+    let mut entries: Vec<(bool, String)> = read_dir
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            (is_dir, entry.file_name().to_string_lossy().into_owned())
+        })
+        .filter(|(_, name)| show_hidden || !name.starts_with('.'))
+        .collect();
+
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut lines: Vec<String> = entries
+        .iter()
+        .take(TREE_ENTRY_CAP)
+        .map(|(is_dir, name)| {
+            if *is_dir {
+                format!("{name}/")
+            } else {
+                name.clone()
+            }
+        })
+        .collect();
+
+    if entries.len() > TREE_ENTRY_CAP {
+        lines.push(format!("… {} more", entries.len() - TREE_ENTRY_CAP));
+    }
+
+    lines.join("\n")
+}
+
+/// Summarizes the project's manifest for projects without a preview file:
+/// `Cargo.toml`'s `[package]`, `package.json`'s name and scripts, or
+/// `pyproject.toml`'s `[project]`. Returns the manifest's file name and the
+/// summary, or `None` when there is no manifest or it doesn't parse.
+pub fn manifest_summary(path: &Path) -> Option<(&'static str, String)> {
+    type Summarize = fn(&toml::Value) -> Option<String>;
+    const MANIFESTS: [(&str, Summarize); 3] = [
+        ("Cargo.toml", cargo_summary),
+        ("package.json", package_json_summary),
+        ("pyproject.toml", pyproject_summary),
+    ];
+
+    MANIFESTS.iter().find_map(|(file, summarize)| {
+        let raw = fs::read_to_string(path.join(file)).ok()?;
+        let manifest = if file.ends_with(".json") {
+            let mut value = serde_json::from_str(&raw).ok()?;
+            crate::config::drop_json_nulls(&mut value);
+            serde_json::from_value(value).ok()?
+        } else {
+            toml::from_str(&raw).ok()?
+        };
+        Some((*file, summarize(&manifest)?))
+    })
+}
+
+fn cargo_summary(manifest: &toml::Value) -> Option<String> {
+    let Some(package) = manifest.get("package") else {
+        let members = manifest.get("workspace")?.get("members")?.as_array()?;
+        return Some(format_manifest(
+            &[("workspace", Some(format!("{} members", members.len())))],
+            ("members", list_values(members)),
+        ));
+    };
+
+    let fields = [
+        "name",
+        "version",
+        "description",
+        "edition",
+        "license",
+        "repository",
+    ]
+    .map(|key| (key, package.get(key).and_then(display_value)));
+    let dependencies = manifest
+        .get("dependencies")
+        .and_then(toml::Value::as_table)
+        .map(|dependencies| dependencies.keys().cloned().collect())
+        .unwrap_or_default();
+    Some(format_manifest(&fields, ("dependencies", dependencies)))
+}
+
+fn package_json_summary(manifest: &toml::Value) -> Option<String> {
+    let fields = ["name", "version", "description", "license"]
+        .map(|key| (key, manifest.get(key).and_then(display_value)));
+    let scripts = manifest
+        .get("scripts")
+        .and_then(toml::Value::as_table)
+        .map(|scripts| {
+            let width = scripts.keys().map(String::len).max().unwrap_or(0);
+            scripts
+                .iter()
+                .filter_map(|(name, command)| {
+                    Some(format!("{name:<width$}  {}", command.as_str()?))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(format_manifest(&fields, ("scripts", scripts)))
+}
+
+fn pyproject_summary(manifest: &toml::Value) -> Option<String> {
+    let project = manifest
+        .get("project")
+        .or_else(|| manifest.get("tool").and_then(|tool| tool.get("poetry")))?;
+    let fields = [
+        "name",
+        "version",
+        "description",
+        "requires-python",
+        "license",
+    ]
+    .map(|key| (key, project.get(key).and_then(display_value)));
+    let dependencies = match project.get("dependencies") {
+        Some(toml::Value::Array(dependencies)) => list_values(dependencies),
+        Some(toml::Value::Table(dependencies)) => dependencies.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    Some(format_manifest(&fields, ("dependencies", dependencies)))
+}
+
+/// A manifest value as one line: strings as-is, `{ text = ... }` and
+/// `{ workspace = true }` tables by their contents, and lists joined.
+fn display_value(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(value) => Some(value.clone()),
+        toml::Value::Array(values) => Some(list_values(values).join(", ")),
+        toml::Value::Table(table) => table.get("text").and_then(display_value).or_else(|| {
+            table
+                .contains_key("workspace")
+                .then(|| "(workspace)".to_string())
+        }),
+        value => Some(value.to_string()),
+    }
+}
+
+fn list_values(values: &[toml::Value]) -> Vec<String> {
+    values.iter().filter_map(display_value).collect()
+}
+
+/// Lays out the set `fields` as aligned `key  value` rows, followed by the
+/// `section` list, if it has entries, under its own heading.
+fn format_manifest(fields: &[(&str, Option<String>)], section: (&str, Vec<String>)) -> String {
+    let width = fields
+        .iter()
+        .filter(|(_, value)| value.is_some())
+        .map(|(key, _)| key.len())
+        .max()
+        .unwrap_or(0);
+    let mut lines: Vec<String> = fields
+        .iter()
+        .filter_map(|(key, value)| Some(format!("{key:<width$}  {}", value.as_ref()?)))
+        .collect();
+
+    let (heading, entries) = section;
+    if !entries.is_empty() {
+        lines.push(String::new());
+        lines.push(format!("{heading} ({})", entries.len()));
+        lines.extend(entries.iter().map(|entry| format!("  {entry}")));
+    }
+
+    lines.join("\n")
+}
+
+const RECENT_FILES_LIMIT: usize = 20;
+
+/// Lists the most recently modified files in the project, newest first,
+/// skipping anything git ignores.
+pub fn recent_files_preview(path: &Path) -> String {
+    let mut files: Vec<(SystemTime, PathBuf)> = project_files(path)
+        .filter_map(|entry| {
+            let modified = entry.metadata().ok()?.modified().ok()?;
+            let file = entry.path().strip_prefix(path).ok()?.to_path_buf();
+            Some((modified, file))
+        })
+        .collect();
+
+    files.sort_by_key(|(modified, _)| Reverse(*modified));
+
+    files
+        .iter()
+        .take(RECENT_FILES_LIMIT)
+        .map(|(modified, file)| format!("{:>8}  {}", format_age(*modified), file.display()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Bytes of source per language, largest first, counted over the files git
+/// doesn't ignore. Files with an unrecognized extension are left out.
+pub fn language_breakdown(path: &Path) -> Vec<(&'static str, u64)> {
+    let mut totals: Vec<(&'static str, u64)> = Vec::new();
+    for entry in project_files(path) {
+        let Some(language) = entry
+            .path()
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(language_for_extension)
+        else {
+            continue;
+        };
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+
+        match totals.iter_mut().find(|(name, _)| *name == language) {
+            Some((_, bytes)) => *bytes += metadata.len(),
+            None => totals.push((language, metadata.len())),
+        }
+    }
+
+    totals.sort_by_key(|(_, bytes)| Reverse(*bytes));
+    totals
+}
+
+fn language_for_extension(extension: &str) -> Option<&'static str> {
+    let language = match extension.to_ascii_lowercase().as_str() {
+        "rs" => "Rust",
+        "go" => "Go",
+        "py" => "Python",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "rb" => "Ruby",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "ex" | "exs" => "Elixir",
+        "hs" => "Haskell",
+        "lua" => "Lua",
+        "php" => "PHP",
+        "sh" | "bash" | "zsh" => "Shell",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" => "CSS",
+        "nix" => "Nix",
+        "zig" => "Zig",
+        _ => return None,
+    };
+    Some(language)
+}
+
+pub fn format_age(time: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |age| age.as_secs());
+
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        86400..2592000 => format!("{}d ago", secs / 86400),
+        2592000..31536000 => format!("{}mo ago", secs / 2592000),
+        _ => format!("{}y ago", secs / 31536000),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory for one test, removed when it is dropped.
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(test: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("pl-scan-{test}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+
+        fn dir(&self, relative: &str) -> PathBuf {
+            let path = self.0.join(relative);
+            fs::create_dir_all(&path).unwrap();
+            path
+        }
+
+        fn file(&self, relative: &str, contents: &str) {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn markers(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn scan<'a>(root: &'a Path, markers: &'a [String], include_worktrees: bool) -> Scan<'a> {
+        Scan {
+            root,
+            require_git: true,
+            markers,
+            exclude: &[],
+            detect_bare: false,
+            include_worktrees,
+            ignore: Gitignore::empty(),
+        }
+    }
+
+    /// What `scan` finds from its root down to `depth`, as paths relative
+    /// to the root in order, and the errors on the way.
+    fn found(scan: &Scan, depth: usize) -> (Vec<String>, Vec<ScanError>) {
+        let (mut projects, mut errors) = (Vec::new(), Vec::new());
+        scan.dir(scan.root, depth, &mut projects, &mut errors);
+        let mut paths: Vec<String> = projects
+            .iter()
+            .map(|project| {
+                let relative = project.project_path.strip_prefix(scan.root).unwrap();
+                relative.to_string_lossy().replace('\\', "/")
+            })
+            .collect();
+        paths.sort();
+        (paths, errors)
+    }
+
+    #[test]
+    fn stops_descending_at_a_marker() {
+        let fixture = Fixture::new("marker-stop");
+        fixture.dir("app/.git");
+        fixture.dir("app/vendor/lib/.git");
+        fixture.dir("client/api/.git");
+
+        let git = markers(&[".git"]);
+        let (paths, errors) = found(&scan(&fixture.0, &git, true), 3);
+        assert_eq!(paths, ["app", "client/api"]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn pl_ignore_hides_directories_gitignore_style() {
+        let fixture = Fixture::new("pl-ignore");
+        for repo in [
+            "scratch-one",
+            "scratch-keep",
+            "work/old",
+            "work/api",
+            "tmp1",
+        ] {
+            fixture.dir(&format!("{repo}/.git"));
+        }
+        fixture.file(
+            ".pl-ignore",
+            "# old experiments\nscratch-*\n!scratch-keep\n/work/old/\ntmp[0-9]\n",
+        );
+
+        let git = markers(&[".git"]);
+        let scan = Scan {
+            ignore: load_pl_ignore(&fixture.0),
+            ..scan(&fixture.0, &git, true)
+        };
+        assert_eq!(found(&scan, 2).0, ["scratch-keep", "work/api"]);
+    }
+
+    #[test]
+    fn dir_size_skips_git_and_ignored_files() {
+        let fixture = Fixture::new("dir-size");
+        fixture.file(".git/HEAD", "ref: refs/heads/main\n");
+        fixture.file(".gitignore", "target/\n");
+        fixture.file(".github/ci.yml", "on: push\n");
+        fixture.file("src/main.rs", "fn main() {}\n");
+        fixture.file("target/debug/pl", &"x".repeat(1000));
+
+        assert_eq!(dir_size(&fixture.0), 8 + 9 + 13);
+    }
+
+    #[test]
+    fn recent_files_leave_out_ignored_ones() {
+        let fixture = Fixture::new("recent-files");
+        fixture.file(".git/HEAD", "ref: refs/heads/main\n");
+        fixture.file(".gitignore", "*.log\n");
+        fixture.file("src/main.rs", "fn main() {}\n");
+        fixture.file("debug.log", "noise\n");
+
+        let preview = recent_files_preview(&fixture.0);
+        let files: Vec<&str> = preview
+            .lines()
+            .map(|line| line.rsplit("  ").next().unwrap())
+            .collect();
+        assert_eq!(files.len(), 2, "{preview}");
+        assert!(files.contains(&".gitignore"));
+        assert!(files.contains(&Path::new("src").join("main.rs").to_str().unwrap()));
+    }
+
+    #[test]
+    fn language_breakdown_counts_only_unignored_source() {
+        let fixture = Fixture::new("languages");
+        fixture.file(".git/HEAD", "ref: refs/heads/main\n");
+        fixture.file(".gitignore", "node_modules/\n");
+        fixture.file("src/main.rs", "fn main() {}\n");
+        fixture.file("web/app.ts", "export {};\n");
+        fixture.file("node_modules/dep/index.js", &"x".repeat(1000));
+
+        assert_eq!(
+            language_breakdown(&fixture.0),
+            [("Rust", 13), ("TypeScript", 11)]
+        );
+    }
+
+    #[test]
+    fn descends_no_deeper_than_max_depth() {
+        let fixture = Fixture::new("max-depth");
+        fixture.dir("top/.git");
+        fixture.dir("client/repo/.git");
+        fixture.dir("a/b/deep/.git");
+
+        let git = markers(&[".git"]);
+        let scan = scan(&fixture.0, &git, true);
+        assert_eq!(found(&scan, 1).0, ["top"]);
+        assert_eq!(found(&scan, 2).0, ["client/repo", "top"]);
+        assert_eq!(found(&scan, 3).0, ["a/b/deep", "client/repo", "top"]);
+    }
+
+    #[test]
+    fn any_marker_makes_a_project() {
+        let fixture = Fixture::new("markers");
+        fixture.file("crate/Cargo.toml", "[package]\n");
+        fixture.dir("hg/.hg");
+        fixture.dir("plain/src");
+
+        let all = markers(&[".git", ".hg", "Cargo.toml"]);
+        let scan = scan(&fixture.0, &all, true);
+        let (mut projects, mut errors) = (Vec::new(), Vec::new());
+        scan.dir(&fixture.0, 1, &mut projects, &mut errors);
+        projects.sort_by(|a, b| a.project_path.cmp(&b.project_path));
+
+        let markers: Vec<_> = projects
+            .iter()
+            .map(|project| project.marker.as_deref())
+            .collect();
+        assert_eq!(markers, [Some("Cargo.toml"), Some(".hg")]);
+    }
+
+    #[test]
+    fn skips_hidden_directories() {
+        let fixture = Fixture::new("hidden");
+        fixture.dir(".cache/repo/.git");
+        fixture.dir("repo/.git");
+
+        let git = markers(&[".git"]);
+        assert_eq!(found(&scan(&fixture.0, &git, true), 2).0, ["repo"]);
+    }
+
+    #[test]
+    fn reports_a_root_it_cannot_read() {
+        let fixture = Fixture::new("missing-root");
+        let missing = fixture.0.join("missing");
+
+        let git = markers(&[".git"]);
+        let scan = scan(&missing, &git, true);
+        let (paths, errors) = found(&scan, 2);
+        assert!(paths.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, missing);
+    }
+
+    /// `main` with a linked worktree of its `feature` branch beside it, the
+    /// way `git worktree add ../feature-wt feature` lays them out.
+    fn worktree_fixture(test: &str) -> Fixture {
+        let fixture = Fixture::new(test);
+        fixture.file("main/.git/HEAD", "ref: refs/heads/main\n");
+        fixture.file(
+            "main/.git/worktrees/feature-wt/HEAD",
+            "ref: refs/heads/feature\n",
+        );
+        fixture.file(
+            "feature-wt/.git",
+            "gitdir: ../main/.git/worktrees/feature-wt\n",
+        );
+        fixture
+    }
+
+    #[test]
+    fn lists_worktrees_labeled_with_their_branch() {
+        let fixture = worktree_fixture("worktrees-on");
+        let git = markers(&[".git"]);
+        let scan = scan(&fixture.0, &git, true);
+        let (mut projects, mut errors) = (Vec::new(), Vec::new());
+        scan.dir(&fixture.0, 1, &mut projects, &mut errors);
+        projects.sort_by(|a, b| a.project_path.cmp(&b.project_path));
+
+        let found: Vec<_> = projects
+            .iter()
+            .map(|project| {
+                (
+                    project.project_name.to_str().unwrap(),
+                    project.worktree.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(found, [("feature-wt", Some("feature")), ("main", None)]);
+    }
+
+    #[test]
+    fn skips_worktrees_without_include_worktrees() {
+        let fixture = worktree_fixture("worktrees-off");
+        let git = markers(&[".git"]);
+        assert_eq!(found(&scan(&fixture.0, &git, false), 1).0, ["main"]);
+    }
+
+    #[test]
+    fn a_submodule_is_not_a_worktree() {
+        let fixture = Fixture::new("submodule");
+        fixture.file("parent/.git/modules/lib/HEAD", "ref: refs/heads/main\n");
+        fixture.file("parent/lib/.git", "gitdir: ../.git/modules/lib\n");
+        assert_eq!(git::worktree_branch(&fixture.0.join("parent/lib")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skips_unreadable_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = Fixture::new("unreadable");
+        fixture.dir("ok/.git");
+        let locked = fixture.dir("locked");
+        fixture.dir("locked/inner/.git");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root reads it anyway, leaving nothing to test.
+        let readable = fs::read_dir(&locked).is_ok();
+
+        let git = markers(&[".git"]);
+        let (paths, errors) = found(&scan(&fixture.0, &git, true), 3);
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        if readable {
+            return;
+        }
+        assert_eq!(paths, ["ok"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, locked);
+    }
+
+    #[test]
+    fn expands_a_leading_tilde() {
+        assert_eq!(parse_dir("~"), home_dir());
+        assert_eq!(parse_dir("~/Projects"), home_dir().join("Projects"));
+        assert_eq!(parse_dir("~user/Projects"), PathBuf::from("~user/Projects"));
+    }
+
+    #[test]
+    fn keeps_a_dollar_without_a_name() {
+        assert_eq!(parse_dir("/srv/$/x"), PathBuf::from("/srv/$/x"));
+        assert_eq!(
+            parse_dir("/srv/${unclosed"),
+            PathBuf::from("/srv/${unclosed")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn expands_a_tilde_before_a_backslash() {
+        assert_eq!(parse_dir(r"~\Projects"), home_dir().join("Projects"));
+        assert_eq!(
+            parse_dir(r"~\work\client"),
+            home_dir().join("work").join("client")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn keeps_drive_paths_as_they_are() {
+        assert_eq!(
+            parse_dir(r"C:\Users\me\src"),
+            PathBuf::from(r"C:\Users\me\src")
+        );
+        assert_eq!(
+            parse_dir(r"\\server\share\repos"),
+            PathBuf::from(r"\\server\share\repos")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn expands_userprofile() {
+        let profile = std::env::var("USERPROFILE").unwrap();
+        assert_eq!(
+            parse_dir(r"$USERPROFILE\src"),
+            PathBuf::from(format!(r"{profile}\src"))
+        );
+    }
+
+    fn root_scan(root: &Path) -> Vec<Project> {
+        let (mut projects, mut errors) = (Vec::new(), Vec::new());
+        RootScan {
+            root: root.to_path_buf(),
+            max_depth: 2,
+            require_git: true,
+            markers: markers(&[".git"]),
+            exclude: Vec::new(),
+            detect_bare: false,
+            include_worktrees: false,
+        }
+        .run(&mut projects, &mut errors);
+        projects
+    }
+
+    #[test]
+    fn a_root_that_is_a_repository_is_listed() {
+        let fixture = Fixture::new("root-repo");
+        fixture.dir(".git");
+
+        let projects = root_scan(&fixture.0);
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].project_path, fixture.0);
+        assert_eq!(projects[0].marker.as_deref(), Some(".git"));
+    }
+
+    #[test]
+    fn a_root_repository_with_projects_below_lists_only_those() {
+        let fixture = Fixture::new("root-repo-nested");
+        fixture.dir(".git");
+        fixture.dir("app/.git");
+
+        let projects = root_scan(&fixture.0);
+        assert_eq!(projects.len(), 1);
+        assert_eq!(projects[0].project_path, fixture.0.join("app"));
+    }
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names: Vec<OsString> = [
+            "v10",
+            "v2",
+            "v1",
+            "v01",
+            "app",
+            "v100000000000000000000",
+            "v2a",
+        ]
+        .into_iter()
+        .map(OsString::from)
+        .collect();
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            [
+                "app",
+                "v1",
+                "v01",
+                "v2",
+                "v2a",
+                "v10",
+                "v100000000000000000000"
+            ]
+        );
+        assert_eq!(
+            natural_cmp(OsStr::new("v1"), OsStr::new("v1")),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use crate::project::Project;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct State {
+    pub last_opened: Option<PathBuf>,
+    /// Projects opened from `pl`, most recent first, shown by the `M` list.
+    pub recently_opened: Vec<PathBuf>,
+    pub search_history: Vec<String>,
+    /// Projects hidden from the list with `x`, restored by `pl --unignore`.
+    pub ignored: Vec<PathBuf>,
+    /// Every project path ever discovered, oldest first, searched with `H`.
+    pub seen_projects: Vec<PathBuf>,
+    /// Projects pinned to the top of the list with `m`, in display order.
+    pub pinned: Vec<PathBuf>,
+    /// When each project was last opened from `pl`, in seconds since the
+    /// Unix epoch.
+    pub opened_at: BTreeMap<PathBuf, u64>,
+    /// How many times each project has been opened from `pl`, for frecency.
+    pub open_counts: BTreeMap<PathBuf, u32>,
+    /// Tags given to each project with `#`, on top of its `pl.tags`.
+    pub tags: BTreeMap<PathBuf, Vec<String>>,
+}
+
+/// Where `pl` keeps its files. Tests get a directory of their own so they
+/// never read or overwrite the real state.
+fn data_dir() -> Option<PathBuf> {
+    if cfg!(test) {
+        return Some(std::env::temp_dir().join("pl-test-data"));
+    }
+    dirs::data_dir()
+}
+
+fn state_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("pl").join("state.toml"))
+}
+
+/// The projects the last scan found, listed at startup while the background
+/// scan runs. `key` describes the settings they were found with, so a cache
+/// from other settings goes unused.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScanCache {
+    pub key: String,
+    pub projects: Vec<CachedProject>,
+}
+
+/// A `Project` as the scan cache stores it.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CachedProject {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default)]
+    pub remote: String,
+    pub root: Option<PathBuf>,
+    #[serde(default)]
+    pub bare: bool,
+    pub worktree: Option<String>,
+    pub marker: Option<String>,
+}
+
+impl From<&Project> for CachedProject {
+    fn from(project: &Project) -> Self {
+        Self {
+            name: project.project_name.to_string_lossy().into_owned(),
+            path: project.project_path.clone(),
+            remote: project.project_remote.clone(),
+            root: project.project_root.clone(),
+            bare: project.bare,
+            worktree: project.worktree.clone(),
+            marker: project.marker.clone(),
+        }
+    }
+}
+
+impl From<CachedProject> for Project {
+    fn from(cached: CachedProject) -> Self {
+        Self {
+            project_name: cached.name.into(),
+            project_path: cached.path,
+            project_remote: cached.remote,
+            project_root: cached.root,
+            bare: cached.bare,
+            worktree: cached.worktree,
+            marker: cached.marker,
+        }
+    }
+}
+
+fn scan_cache_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("pl").join("projects.toml"))
+}
+
+/// The cached projects for the scan settings `key`, or `None` when there is
+/// no cache for them, or it can't be read.
+pub fn load_scan_cache(key: &str) -> Option<Vec<Project>> {
+    let raw = fs::read_to_string(scan_cache_path()?).ok()?;
+    let cache: ScanCache = toml::from_str(&raw).ok()?;
+    (cache.key == key).then(|| cache.projects.into_iter().map(Project::from).collect())
+}
+
+pub fn save_scan_cache(key: &str, projects: &[Project]) -> io::Result<()> {
+    let path = scan_cache_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "data directory not found"))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let cache = ScanCache {
+        key: key.to_string(),
+        projects: projects.iter().map(CachedProject::from).collect(),
+    };
+    let raw = toml::to_string(&cache).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    save_atomic(&path, raw.as_bytes())
+}
+
+/// Loads the persisted state, treating a missing or corrupt file as empty.
+pub fn load_state() -> State {
+    state_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| toml::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+pub fn save_state(state: &State) -> io::Result<()> {
+    let path = state_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "data directory not found"))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let raw = toml::to_string(state).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    save_atomic(&path, raw.as_bytes())
+}
+
+/// Writes `bytes` to `path` without ever leaving it half-written: they go to
+/// a temporary file beside it first, which is then renamed over `path`. A
+/// crash mid-write leaves the old file in place, and when two instances save
+/// at once the last rename wins. Each process uses its own temporary file so
+/// they can't interleave writes.
+pub fn save_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    let temp = path.with_file_name(temp_name);
+
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pl-state-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn replaces_an_existing_file() {
+        let dir = scratch("replace");
+        let path = dir.join("state.toml");
+        fs::write(&path, "old contents that are longer").unwrap();
+
+        save_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(entries(&dir), ["state.toml"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cleans_up_when_the_rename_fails() {
+        let dir = scratch("rename-fails");
+        // A file can't be renamed over a directory that has something in it.
+        let path = dir.join("state.toml");
+        fs::create_dir_all(path.join("inside")).unwrap();
+
+        assert!(save_atomic(&path, b"new").is_err());
+        assert_eq!(entries(&dir), ["state.toml"]);
+        assert!(path.join("inside").is_dir());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn fails_cleanly_without_a_directory() {
+        let dir = scratch("no-parent");
+        let path = dir.join("missing").join("state.toml");
+
+        assert!(save_atomic(&path, b"new").is_err());
+        assert!(entries(&dir).is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn the_last_save_wins() {
+        let dir = scratch("last-wins");
+        let path = dir.join("state.toml");
+        let mut state = State::default();
+        for query in ["first", "second"] {
+            state.search_history = vec![query.to_string()];
+            let raw = toml::to_string(&state).unwrap();
+            save_atomic(&path, raw.as_bytes()).unwrap();
+        }
+
+        let saved: State = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved.search_history, ["second"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
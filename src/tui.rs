@@ -1,21 +1,166 @@
 use crate::app::App;
-use crate::{input, ui};
-use ratatui::DefaultTerminal;
-use std::io;
+use crate::input::InputEvent;
+use crate::{input, launch, ui};
+use crossterm::cursor::Show;
+use crossterm::event::{DisableBracketedPaste, EnableBracketedPaste};
+use crossterm::execute;
+use crossterm::style::Print;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen, SetTitle};
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use std::io::{self, Write};
+
+/// xterm control sequences that save and restore the window title, since
+/// the current title can't be read back.
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
 
 pub fn run(app: &mut App) -> io::Result<()> {
-    ratatui::run(|terminal| run_loop(terminal, app))?;
-    Ok(())
+    if app.prints_selection() {
+        // stdout is for the chosen path, and is usually captured by the
+        // shell, so draw on stderr instead the way fzf does.
+        run_on_stderr(app)
+    } else {
+        ratatui::run(|terminal| session(terminal, app))
+    }
+}
+
+/// `ratatui::run`, but drawing on stderr. Like it, the terminal is put back
+/// however the session ends, panics included, so the shell isn't left in
+/// raw mode on the alternate screen.
+fn run_on_stderr(app: &mut App) -> io::Result<()> {
+    set_restoring_panic_hook();
+    terminal::enable_raw_mode()?;
+
+    let result = execute!(io::stderr(), EnterAlternateScreen)
+        .and_then(|()| Terminal::new(CrosstermBackend::new(io::stderr())))
+        .and_then(|mut terminal| session(&mut terminal, app));
+
+    let restored = restore_stderr();
+    result.and(restored)
+}
+
+/// Undoes everything `run_on_stderr` and `session` set up, whichever of it
+/// actually was.
+fn restore_stderr() -> io::Result<()> {
+    let screen = execute!(
+        io::stderr(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen,
+        Show
+    );
+    let raw = terminal::disable_raw_mode();
+    screen.and(raw)
+}
+
+/// Restores the terminal before the panic message is printed, so it is
+/// readable and the shell is usable afterwards.
+fn set_restoring_panic_hook() {
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let _ = restore_stderr();
+        hook(info);
+    }));
+}
+
+fn session<W: Write>(
+    terminal: &mut Terminal<CrosstermBackend<W>>,
+    app: &mut App,
+) -> io::Result<()> {
+    let title = app.config().title.clone();
+    execute!(terminal.backend_mut(), EnableBracketedPaste)?;
+    if let Some(title) = &title {
+        execute!(terminal.backend_mut(), Print(PUSH_TITLE), SetTitle(title))?;
+    }
+
+    let result = run_loop(terminal, app);
+
+    if title.is_some() {
+        execute!(terminal.backend_mut(), Print(POP_TITLE))?;
+    }
+    execute!(terminal.backend_mut(), DisableBracketedPaste)?;
+    result
 }
 
-fn run_loop(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+fn run_loop<W: Write>(
+    terminal: &mut Terminal<CrosstermBackend<W>>,
+    app: &mut App,
+) -> io::Result<()> {
+    let mut needs_redraw = true;
+
     while !app.should_exit() {
-        terminal.draw(|frame| ui::draw(frame, app))?;
+        if needs_redraw {
+            terminal.draw(|frame| ui::draw(frame, app))?;
+            needs_redraw = false;
+        }
 
-        if let Some(key_event) = input::next_key_event()? {
-            input::handle_key_event(app, key_event);
+        match input::next_event(app.tick_rate())? {
+            InputEvent::Key(key_event) => {
+                input::handle_key_event(app, key_event);
+                needs_redraw = true;
+            }
+            InputEvent::Paste(text) => {
+                app.paste(&text);
+                needs_redraw = true;
+            }
+            InputEvent::Other => needs_redraw = true,
+            InputEvent::Tick => {}
+        }
+
+        needs_redraw |= app.on_tick();
+
+        let launches = app.take_foreground_launches();
+        if !launches.is_empty() {
+            suspended(terminal, || {
+                let config = app.config().clone();
+                let (mode, query) = (app.launch_mode(), app.launch_query().map(str::to_string));
+                launch::open_projects(
+                    &launches,
+                    &config,
+                    mode,
+                    query.as_deref(),
+                    |path, result| app.editor_finished(path, result),
+                );
+            })?;
+            needs_redraw = true;
+        }
+
+        if let Some(path) = app.take_shell_request() {
+            suspended(terminal, || app.shell_finished(launch::shell(&path)))?;
+            needs_redraw = true;
+        }
+
+        if let Some((description, mut command)) = app.take_action_request() {
+            suspended(terminal, || {
+                app.action_finished(&description, command.status());
+            })?;
+            needs_redraw = true;
         }
     }
 
     Ok(())
 }
+
+/// Hands the terminal back to the shell while `f` runs, e.g. so an editor can
+/// use it, then restores the TUI.
+fn suspended<W: Write>(
+    terminal: &mut Terminal<CrosstermBackend<W>>,
+    f: impl FnOnce(),
+) -> io::Result<()> {
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
+    terminal::disable_raw_mode()?;
+
+    f();
+
+    terminal::enable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        EnterAlternateScreen,
+        EnableBracketedPaste
+    )?;
+    terminal.clear()
+}
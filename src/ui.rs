@@ -1,84 +1,528 @@
-use crate::app::{App, InputMode};
+use crate::app::{App, BranchPicker, InputMode, LEADER_ACTIONS, NewProject, PendingAction};
+use crate::config::{Region, StatusTarget, ThemeConfig, config_path};
+use crate::{ansi, markdown, matcher};
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Layout, Rect};
-use ratatui::style::Style;
-use ratatui::text::{Line, Span};
+use ratatui::layout::{Constraint, Layout, Margin, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span, Text};
 use ratatui::widgets::{
-    Block, Borders, Clear, HighlightSpacing, List, ListItem, Padding, Paragraph,
+    Block, Clear, HighlightSpacing, List, ListItem, Padding, Paragraph, Scrollbar,
+    ScrollbarOrientation, ScrollbarState, Tabs, Wrap,
 };
+use std::borrow::Cow;
+use tui_input::Input;
 
 const KEYBIND_STYLE: Style = Style::new().bold().blue();
-const TITLE_STYLE: Style = Style::new().bold().red();
+const CONTENT_MATCH_CONTEXT: usize = 3;
+const PREVIEW_CURSOR_STYLE: Style = Style::new().reversed();
+const PREVIEW_SELECTION_STYLE: Style = Style::new().bg(Color::DarkGray);
+/// Frames of the spinner shown while the startup scan runs, a tenth of a
+/// second each.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
 
 pub fn draw(frame: &mut Frame, app: &mut App) {
+    let theme = Theme::new(&app.config().theme);
     let [left_area, right_area] =
         Layout::horizontal([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]).areas(frame.area());
-    let [input_area, project_area] =
-        Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(left_area);
 
-    render_input(frame, app, input_area);
-    render_project_list(frame, app, project_area);
-    render_readme(frame, app, right_area);
+    let regions = app.config().layout.regions();
+    let constraints = regions.iter().map(|region| match region {
+        Region::Input if app.input_collapsed() => Constraint::Length(0),
+        Region::Input => Constraint::Length(app.config().layout.input_height),
+        Region::List => Constraint::Fill(1),
+        Region::Status => Constraint::Length(1),
+    });
+    let areas = Layout::vertical(constraints).split(left_area);
+    let mut input_area = Rect::default();
+    for (region, &area) in regions.iter().zip(areas.iter()) {
+        match region {
+            Region::Input => {
+                input_area = area;
+                render_input(frame, app, &theme, area);
+            }
+            Region::List => render_project_list(frame, app, &theme, area),
+            Region::Status => frame.render_widget(status_bar(app), area),
+        }
+    }
+    render_readme(frame, app, &theme, right_area);
 
     if app.show_help {
-        render_help_popup(frame);
+        render_help_popup(frame, app, &theme);
+    }
+
+    if app.show_scan_errors {
+        render_scan_errors_popup(frame, app, &theme);
+    }
+
+    if let Some(info) = &app.info {
+        render_info_popup(frame, &theme, info);
+    }
+
+    if let Some(action) = &app.pending_action {
+        render_confirm_popup(frame, &theme, action);
+    }
+
+    if app.new_project.is_some() {
+        render_new_project_popup(frame, app, &theme);
+    }
+
+    if let Some(input) = &app.rename {
+        render_input_popup(frame, &theme, "Rename project", input);
+    }
+
+    if let Some(input) = &app.tag_editor {
+        render_input_popup(frame, &theme, "Tags (comma-separated)", input);
+    }
+
+    if let Some(input) = &app.add_dir {
+        render_add_dir_popup(frame, &theme, input);
+    }
+
+    if let Some(selected) = app.recent_selection {
+        render_recent_popup(frame, app, &theme, selected);
+    }
+
+    if let Some(picker) = &app.branch_picker {
+        render_branch_popup(frame, app, &theme, picker);
+    }
+
+    if app.leader_pending {
+        render_leader_popup(frame, app, &theme);
     }
 
-    if app.input_mode == InputMode::Editing {
+    if app.input_mode == InputMode::Editing
+        && app.new_project.is_none()
+        && app.rename.is_none()
+        && app.tag_editor.is_none()
+        && app.add_dir.is_none()
+    {
         let cursor_x = input_area.x + 1 + app.input.visual_cursor() as u16;
         let cursor_y = input_area.y + 1;
         frame.set_cursor_position((cursor_x, cursor_y));
     }
 }
 
-fn render_input(frame: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+fn render_input(frame: &mut Frame, app: &App, theme: &Theme, area: ratatui::layout::Rect) {
     let style = match app.input_mode {
         InputMode::Normal => Style::default(),
-        InputMode::Editing => Style::new().cyan(),
+        InputMode::Editing => theme.input,
     };
 
-    let widget = Paragraph::new(app.input.value())
-        .style(style)
-        .block(Block::bordered().title(Span::styled("Search", TITLE_STYLE)));
+    let mut title = vec![Span::styled("Search", theme.title)];
+    let style = if app.show_all {
+        title.push(Span::raw(" [showing all]"));
+        style.dim()
+    } else {
+        style
+    };
+
+    let block = theme.block().title(Line::from(title));
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let (matched, total) = app.match_counts();
+    let count = app
+        .config()
+        .layout
+        .count_format
+        .replace("{matched}", &matched.to_string())
+        .replace("{total}", &total.to_string());
+    let [query_area, count_area] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Length(count.chars().count() as u16),
+    ])
+    .spacing(1)
+    .areas(inner);
+
+    frame.render_widget(Paragraph::new(app.input.value()).style(style), query_area);
+    frame.render_widget(
+        Paragraph::new(Line::from(count).right_aligned()).style(style.dim()),
+        count_area,
+    );
+}
+
+/// Explains where projects come from when the scan found none.
+fn render_no_projects(frame: &mut Frame, theme: &Theme, area: Rect) {
+    let config = config_path().map_or_else(
+        || "the config file".to_string(),
+        |path| path.display().to_string(),
+    );
+    let lines = vec![
+        Line::raw("No projects found."),
+        Line::raw(""),
+        Line::raw("Add directories to project_dirs in"),
+        Line::styled(config, KEYBIND_STYLE),
+        Line::raw("or scan one for this run with `pl <dir>`."),
+    ];
+
+    let inner = theme.block().inner(area);
+    let widget = Paragraph::new(lines)
+        .style(Style::new().dim())
+        .wrap(Wrap { trim: false })
+        .block(Block::default().padding(Padding::horizontal(1)));
+    frame.render_widget(widget, inner);
+}
+
+/// Parses a `[theme]` color, treating an empty or unknown name as none.
+fn theme_color(name: &str) -> Option<Color> {
+    name.parse().ok().filter(|_| !name.is_empty())
+}
 
-    frame.render_widget(widget, area);
+/// The `[theme]` colors as styles, worked out once per frame and handed to
+/// everything that draws.
+struct Theme {
+    highlight: Style,
+    focus: Style,
+    border: Style,
+    title: Style,
+    input: Style,
+    preview: Style,
+    matched: Style,
 }
 
-fn render_project_list(frame: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
-    let items: Vec<ListItem> = app
-        .filtered_projects
+impl Theme {
+    fn new(config: &ThemeConfig) -> Self {
+        let fg = |name: &str| match theme_color(name) {
+            Some(color) => Style::new().fg(color),
+            None => Style::new(),
+        };
+        let mut highlight = fg(&config.highlight).bold();
+        if let Some(color) = theme_color(&config.highlight_bg) {
+            highlight = highlight.bg(color);
+        }
+        Self {
+            highlight,
+            focus: fg(&config.highlight),
+            border: fg(&config.border),
+            title: fg(&config.title).bold(),
+            input: fg(&config.input),
+            preview: fg(&config.preview),
+            matched: fg(&config.matched).bold(),
+        }
+    }
+
+    /// A bordered block in the border color.
+    fn block(&self) -> Block<'static> {
+        Block::bordered().border_style(self.border)
+    }
+}
+
+fn render_project_list(
+    frame: &mut Frame,
+    app: &mut App,
+    theme: &Theme,
+    area: ratatui::layout::Rect,
+) {
+    app.load_visible_git_status(area.height.saturating_sub(2) as usize);
+
+    // The selected row gets `highlight_symbol` from the list itself, so only
+    // the other rows carry `item_prefix`.
+    let selected = app.state.selected();
+    let item_prefix = app.config().item_prefix.clone();
+    let colorize_by_root = app.config().colorize_by_root;
+    let show_detached_indicator = app.config().show_detached_indicator;
+    let statuses = &app.config().theme;
+    let dirty_color = theme_color(&statuses.dirty);
+    let clean_color = theme_color(&statuses.clean);
+    let color_row = statuses.git_status_target == StatusTarget::Row;
+    let mut items: Vec<ListItem> = app
+        .visible_projects()
         .iter()
-        .map(|project| ListItem::new(project.project_name.to_string_lossy()))
+        .enumerate()
+        .map(|(index, project)| {
+            let prefix = if Some(index) == selected {
+                String::new()
+            } else {
+                item_prefix.clone()
+            };
+            let name = project.project_name.to_string_lossy().into_owned();
+            let name_style = match &project.project_root {
+                Some(root) if colorize_by_root => Style::new().fg(root_color(root)),
+                _ => Style::new(),
+            };
+            let indent = "  ".repeat(app.hierarchy_depth(project));
+            let mut spans = vec![Span::raw(prefix), Span::raw(indent)];
+            if app.is_marked(project) {
+                spans.push(Span::styled("+ ", theme.highlight));
+            }
+            // The selection's own colors cover the match color, but not an
+            // underline.
+            let match_style = if Some(index) == selected {
+                theme.matched.underlined()
+            } else {
+                theme.matched
+            };
+            spans.extend(highlight_chars(
+                &name,
+                app.match_indices(project),
+                name_style,
+                match_style,
+            ));
+            if project.bare {
+                spans.push(Span::styled(" [bare]", Style::new().dim()));
+            }
+            // Most projects are git repositories, so only other kinds get a
+            // tag.
+            if let Some(marker) = project.marker.as_deref().filter(|&marker| marker != ".git") {
+                spans.push(Span::styled(format!(" [{marker}]"), Style::new().dim()));
+            }
+            if let Some(branch) = &project.worktree {
+                spans.push(Span::styled(
+                    format!(" [worktree: {branch}]"),
+                    Style::new().dim(),
+                ));
+            }
+            if app.is_pinned(project) {
+                spans.push(Span::styled(" [pinned]", Style::new().cyan()));
+            }
+            let mut row_color = None;
+            if let Some(status) = app.git_status(project) {
+                let status_color = if status.dirty {
+                    dirty_color
+                } else {
+                    clean_color
+                };
+                if color_row {
+                    row_color = status_color;
+                }
+
+                let branch = status.branch.as_deref().unwrap_or("detached");
+                let mut branch_style = Style::new().dim();
+                if let Some(color) = status_color.filter(|_| !color_row && !status.dirty) {
+                    branch_style = branch_style.fg(color);
+                }
+                spans.push(Span::styled(format!(" {branch}"), branch_style));
+                if status.dirty {
+                    let style = match status_color.filter(|_| !color_row) {
+                        Some(color) => Style::new().fg(color),
+                        None => Style::new(),
+                    };
+                    spans.push(Span::styled("*", style));
+                }
+                if status.stashed {
+                    spans.push(Span::styled("$", Style::new().magenta()));
+                }
+                if status.branch.is_none() && show_detached_indicator {
+                    spans.push(Span::styled("!", Style::new().red()));
+                }
+            } else if app.git_status_loading(project) {
+                spans.push(Span::styled(" …", Style::new().dim()));
+            }
+            if app.is_last_opened(project) {
+                spans.push(Span::styled(" • last", Style::new().dim()));
+            }
+            if let Some(age) = app.last_opened_label(project) {
+                spans.push(Span::styled(format!(" opened {age}"), Style::new().dim()));
+            }
+            if let Some(score) = app.score(project) {
+                spans.push(Span::styled(format!(" ({score})"), Style::new().dim()));
+            }
+            let mut item = ListItem::new(Line::from(spans));
+            if let Some(color) = row_color {
+                item = item.style(Style::new().fg(color));
+            }
+            if app.is_missing(project) {
+                item.style(Style::new().dim())
+            } else {
+                item
+            }
+        })
         .collect();
+    // Not a project, so selection never reaches it.
+    if let Some(total) = app.truncated_from() {
+        let more = total - app.visible_projects().len();
+        items.push(ListItem::new(Line::from(vec![
+            Span::styled(format!("… {more} more ("), Style::new().dim()),
+            Span::styled("Ctrl-l", KEYBIND_STYLE),
+            Span::styled(" to expand)", Style::new().dim()),
+        ])));
+    }
 
-    let title = Line::from(vec![
-        Span::styled("Projects", TITLE_STYLE),
+    let mut title = Line::from(vec![
+        Span::styled(
+            if app.history_mode() {
+                "History"
+            } else {
+                "Projects"
+            },
+            theme.title,
+        ),
+        Span::styled(
+            if app.dirty_loading() {
+                " [dirty, loading…]"
+            } else if app.dirty_only() {
+                " [dirty]"
+            } else {
+                ""
+            },
+            Style::new().yellow(),
+        ),
+        Span::styled(
+            if app.no_readme_only() {
+                " [no-readme]"
+            } else {
+                ""
+            },
+            Style::new().yellow(),
+        ),
         Span::raw(" ["),
-        Span::raw(format!("{}]", app.sort_label())),
+        Span::raw(format!(
+            "{}{}]",
+            app.sort_label(),
+            if app.sorting() { ", sorting…" } else { "" }
+        )),
         Span::styled("s", KEYBIND_STYLE),
     ]);
+    if let Some(root) = app.current_root_label() {
+        title.push_span(Span::raw(format!(" {root}")));
+    }
+    if app.marked_count() > 0 {
+        title.push_span(Span::styled(
+            format!(" [{} marked]", app.marked_count()),
+            theme.highlight,
+        ));
+    }
+    if app.scanning() {
+        let frame = SPINNER[(app.scan_elapsed().as_millis() / 100) as usize % SPINNER.len()];
+        title.push_span(Span::styled(
+            format!(" {frame} scanning…"),
+            Style::new().dim(),
+        ));
+    } else if let Some(elapsed) = app.content_search_elapsed() {
+        let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+        title.push_span(Span::styled(
+            format!(" {frame} searching…"),
+            Style::new().dim(),
+        ));
+    }
+    if let Some(total) = app.truncated_from() {
+        title.push_span(Span::raw(format!(
+            " (showing {} of {total})",
+            app.visible_projects().len()
+        )));
+    }
+    // Keep a kept query visible while its search box is collapsed.
+    if app.input_collapsed() && !app.input.value().is_empty() {
+        title.push_span(Span::raw(format!(" /{}", app.input.value())));
+    }
 
-    let title_bottom = Line::from(vec![
-        Span::default().content("Keybinds: "),
-        Span::styled("?", KEYBIND_STYLE),
-    ])
-    .centered();
+    let mut block = theme.block().title(title);
+    if !app.config().layout.regions().contains(&Region::Status) {
+        block = block.title_bottom(status_line(app));
+    }
 
     let widget = List::new(items)
-        .block(
-            Block::default()
-                .title(title)
-                .borders(Borders::ALL)
-                .title_bottom(title_bottom),
-        )
-        .highlight_symbol("> ")
-        .highlight_style(Style::new().bold().cyan())
-        .highlight_spacing(HighlightSpacing::Always);
+        .block(block)
+        .highlight_symbol(Line::raw(app.config().highlight_symbol.clone()))
+        .highlight_style(theme.highlight)
+        .highlight_spacing(HighlightSpacing::Never);
 
     frame.render_stateful_widget(widget, area, &mut app.state);
+
+    if app.projects().is_empty() && !app.history_mode() && !app.scanning() {
+        render_no_projects(frame, theme, area);
+    }
+
+    let total = app.visible_projects().len();
+    if total > area.height.saturating_sub(2) as usize {
+        let mut scrollbar_state =
+            ScrollbarState::new(total).position(app.state.selected().unwrap_or(0));
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area.inner(Margin::new(0, 1)),
+            &mut scrollbar_state,
+        );
+    }
+}
+
+const ROOT_COLORS: [Color; 6] = [
+    Color::LightBlue,
+    Color::LightGreen,
+    Color::LightMagenta,
+    Color::LightYellow,
+    Color::LightCyan,
+    Color::LightRed,
+];
+
+/// Picks a color for a `project_dirs` root from a hash of its path, so a root
+/// keeps its color across runs and config reorderings.
+fn root_color(root: &std::path::Path) -> Color {
+    // FNV-1a, which unlike `DefaultHasher` is fixed across Rust releases.
+    let hash = root
+        .as_os_str()
+        .as_encoded_bytes()
+        .iter()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+    ROOT_COLORS[(hash % ROOT_COLORS.len() as u64) as usize]
+}
+
+/// The current status message, or a pointer to the keybindings.
+fn status_line(app: &App) -> Line<'static> {
+    match app.status() {
+        Some((message, is_error)) => {
+            let style = if is_error {
+                Style::new().red()
+            } else {
+                Style::new().green()
+            };
+            Line::from(Span::styled(message.to_string(), style))
+        }
+        None => Line::from(vec![
+            Span::default().content("Keybinds: "),
+            Span::styled("?", KEYBIND_STYLE),
+        ])
+        .centered(),
+    }
+}
+
+/// The status bar: the mode, the match count, and the status message or,
+/// without one, the keys to get going.
+fn status_bar(app: &App) -> Line<'static> {
+    let mode = if app.show_help {
+        "HELP"
+    } else if app.preview_cursor.is_some() {
+        "PREVIEW"
+    } else {
+        match app.input_mode {
+            InputMode::Normal => "NORMAL",
+            InputMode::Editing => "EDITING",
+        }
+    };
+    let (matched, total) = app.match_counts();
+    let mut spans = vec![
+        Span::styled(format!(" {mode} "), Style::new().bold().reversed()),
+        Span::styled(format!(" {matched}/{total} "), Style::new().dim()),
+    ];
+
+    if app.status().is_some() {
+        spans.extend(status_line(app).spans);
+        return Line::from(spans);
+    }
+    let hints: &[(&str, &str)] = match app.input_mode {
+        InputMode::Normal => &[
+            ("/", "search"),
+            ("Enter", "open"),
+            ("Space", "actions"),
+            ("?", "help"),
+            ("q", "quit"),
+        ],
+        InputMode::Editing => &[
+            ("Esc", "done"),
+            ("Enter", "open"),
+            ("Ctrl-g", "contents"),
+            ("↑↓", "move"),
+        ],
+    };
+    for (key, description) in hints {
+        spans.push(Span::styled(format!(" {key}"), KEYBIND_STYLE));
+        spans.push(Span::styled(format!(" {description}"), Style::new().dim()));
+    }
+    Line::from(spans)
 }
 
-fn help_line<'a>(key: &'a str, desc: &'a str) -> Line<'a> {
+fn help_line<'a>(key: impl Into<Cow<'a, str>>, desc: &str) -> Line<'a> {
     Line::from(vec![
         Span::styled(key, KEYBIND_STYLE),
         Span::raw(format!("  {desc}")),
@@ -98,54 +542,878 @@ fn centered_rect(area: Rect, max_width: u16, max_height: u16) -> Rect {
     }
 }
 
-fn render_help_popup(frame: &mut Frame) {
-    let help_rect = centered_rect(frame.area(), 50, 20);
+/// The keybindings over the whole screen, scrolled with `j` / `k`. Keys
+/// bound under `[keys]` and the leader menu, `[[actions]]` included, are
+/// listed after the defaults.
+fn render_help_popup(frame: &mut Frame, app: &mut App, theme: &Theme) {
+    let help_rect = frame.area().inner(Margin::new(2, 1));
     frame.render_widget(Clear, help_rect);
 
-    let lines = vec![
+    let mut lines = vec![
         help_line("j / ↓", "move down"),
         help_line("k / ↑", "move up"),
         help_line("g", "go to first"),
         help_line("G", "go to last"),
-        help_line("/", "search"),
+        help_line("/", "search (>term: contents, #tag: tags)"),
+        help_line("Ctrl-g", "toggle content search"),
         help_line("Enter", "open project"),
+        help_line("1-9", "open the Nth project on screen"),
+        help_line("Alt/Ctrl-Enter", "open all matching projects"),
         help_line("o", "open remote in browser"),
-        help_line("s", "cycle sort (A-Z / Recent)"),
+        help_line("O", "open the parent directory"),
+        help_line("t", "open in a tmux / zellij session"),
+        help_line("b", "pick a branch to open"),
+        help_line("v / V", "mark to open together / clear marks"),
+        help_line("F", "reveal in Finder / Explorer"),
+        help_line("!", "shell in the project, back on exit"),
+        help_line("Space", "leader: actions on the selected project"),
+        help_line("Tab / Shift-Tab", "cycle project_dirs root"),
+        help_line("s", "cycle sort (Frecent / A-Z / Recent / Committed)"),
+        help_line("S", "reverse sort direction"),
+        help_line("A", "toggle frecency / A-Z sort"),
+        help_line("D", "only repositories with uncommitted changes"),
+        help_line("N", "only projects without a README"),
+        help_line("Ctrl-j / Ctrl-k", "cycle preview file"),
+        help_line("Ctrl-d / Ctrl-u", "scroll the preview"),
+        help_line("Ctrl-f", "focus preview (j/k, J/K page, v select, y copy)"),
+        help_line("L", "lock the preview on this project / unlock"),
+        help_line("Ctrl-a", "toggle filtered / all (search)"),
+        help_line("Ctrl-l", "show every match past max_results"),
+        help_line("Ctrl-p / Ctrl-n", "search history (empty search)"),
+        help_line("e", "edit the previewed README"),
+        help_line("Y", "copy the previewed README"),
+        help_line(".", "toggle hidden files in file preview"),
+        help_line("Ctrl-e", "show scan errors"),
+        help_line("Ctrl-r", "rescan project_dirs"),
+        help_line("p", "cycle preview (README / recent / languages / changes)"),
+        help_line("P", "toggle absolute / ~ paths"),
+        help_line("r", "refresh git status and changes of selection"),
+        help_line("a / u", "archive project / undo"),
+        help_line("i", "show project info"),
+        help_line("n / Ctrl-n", "create a new project"),
+        help_line("R", "rename the project directory"),
+        help_line("#", "edit the project's tags"),
+        help_line("T", "cycle the #tag filter"),
+        help_line("H", "toggle all-time project history"),
+        help_line("M", "recently opened projects"),
+        help_line("x", "ignore project (pl --unignore restores)"),
+        help_line("m", "pin / unpin project"),
+        help_line("J / K", "move pinned project down / up"),
         help_line("?", "toggle this help"),
         help_line("q / Esc", "quit"),
     ];
 
+    let custom: Vec<Line> = app
+        .keymap
+        .describe()
+        .map(|(spec, action)| help_line(spec.to_string(), action.description()))
+        .collect();
+    if !custom.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::styled("From [keys]", theme.title));
+        lines.extend(custom);
+    }
+
+    let leader = match app.config().leader_key {
+        ' ' => "Space".to_string(),
+        key => key.to_string(),
+    };
+    let actions = &app.config().actions;
+    lines.push(Line::default());
+    lines.push(Line::styled(format!("After {leader}"), theme.title));
+    lines.extend(
+        LEADER_ACTIONS
+            .iter()
+            .filter(|action| !actions.iter().any(|custom| custom.key == action.key))
+            .map(|action| (action.key, action.description))
+            .chain(
+                actions
+                    .iter()
+                    .map(|action| (action.key, action.description.as_str())),
+            )
+            .map(|(key, description)| help_line(key.to_string(), description)),
+    );
+
+    // Borders and padding take two rows each.
+    let height = help_rect.height.saturating_sub(4) as usize;
+    let max_scroll = lines.len().saturating_sub(height);
+    app.help_scroll = app.help_scroll.min(max_scroll);
+    let total = lines.len();
+
+    let mut block = theme
+        .block()
+        .title(Line::from(vec![Span::styled("Keybinds", theme.title)]).centered())
+        .padding(Padding::new(2, 2, 1, 1));
+    if max_scroll > 0 {
+        block = block.title_bottom(
+            Line::from(vec![
+                Span::styled("j / k", KEYBIND_STYLE),
+                Span::raw(" scroll  "),
+                Span::styled("q", KEYBIND_STYLE),
+                Span::raw(" close"),
+            ])
+            .centered(),
+        );
+    }
+    let widget = Paragraph::new(lines)
+        .scroll((app.help_scroll as u16, 0))
+        .block(block);
+    frame.render_widget(widget, help_rect);
+
+    if max_scroll > 0 {
+        let mut scrollbar_state =
+            ScrollbarState::new(total.saturating_sub(height)).position(app.help_scroll);
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            help_rect.inner(Margin::new(0, 1)),
+            &mut scrollbar_state,
+        );
+    }
+}
+
+fn render_confirm_popup(frame: &mut Frame, theme: &Theme, action: &PendingAction) {
+    let question = match action {
+        PendingAction::Archive(project) => {
+            format!("Archive {}?", project.project_name.to_string_lossy())
+        }
+        PendingAction::OpenAll(paths) => format!("Open all {} projects?", paths.len()),
+        PendingAction::Forget(path) => {
+            format!(
+                "{} no longer exists. Remove it from history?",
+                path.display()
+            )
+        }
+        PendingAction::Rename(path, name) => format!(
+            "Rename {} to {name}?",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ),
+    };
+
+    let popup_rect = centered_rect(frame.area(), 60, 5);
+    frame.render_widget(Clear, popup_rect);
+
+    let widget = Paragraph::new(Line::from(vec![
+        Span::raw(format!("{question} ")),
+        Span::styled("y", KEYBIND_STYLE),
+        Span::raw(" / "),
+        Span::styled("n", KEYBIND_STYLE),
+    ]))
+    .centered()
+    .block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled("Confirm", theme.title)]).centered())
+            .padding(Padding::new(1, 1, 1, 0)),
+    );
+
+    frame.render_widget(widget, popup_rect);
+}
+
+fn render_info_popup(frame: &mut Frame, theme: &Theme, info: &[(&str, String)]) {
+    let popup_rect = centered_rect(frame.area(), 80, info.len() as u16 + 4);
+    frame.render_widget(Clear, popup_rect);
+
+    let lines: Vec<Line> = info
+        .iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(format!("{label:<12}"), KEYBIND_STYLE),
+                Span::raw(value.as_str()),
+            ])
+        })
+        .collect();
+
     let widget = Paragraph::new(lines).block(
-        Block::default()
-            .title(Line::from(vec![Span::styled("Keybinds", TITLE_STYLE)]).centered())
-            .borders(Borders::ALL)
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled("Info", theme.title)]).centered())
             .padding(Padding::new(2, 2, 1, 1)),
     );
 
-    frame.render_widget(widget, help_rect);
+    frame.render_widget(widget, popup_rect);
+}
+
+fn render_new_project_popup(frame: &mut Frame, app: &App, theme: &Theme) {
+    let choice_lines = |choices: Vec<String>, selected: usize| -> Vec<Line> {
+        choices
+            .into_iter()
+            .enumerate()
+            .map(|(index, choice)| {
+                if index == selected {
+                    Line::styled(format!("> {choice}"), theme.highlight)
+                } else {
+                    Line::raw(format!("  {choice}"))
+                }
+            })
+            .collect()
+    };
+    let (title, lines, cursor) = match &app.new_project {
+        Some(NewProject::Name(input)) => (
+            "New project".to_string(),
+            vec![Line::raw(input.value())],
+            Some(input.visual_cursor() as u16),
+        ),
+        Some(NewProject::Root { name, selected }) => (
+            format!("Create {name} in"),
+            choice_lines(app.root_choices(), *selected),
+            None,
+        ),
+        Some(NewProject::Template { name, selected, .. }) => {
+            let choices = app
+                .template_choices()
+                .into_iter()
+                .map(String::from)
+                .collect();
+            (
+                format!("Template for {name}"),
+                choice_lines(choices, *selected),
+                None,
+            )
+        }
+        Some(NewProject::Prompt {
+            template, input, ..
+        }) => (
+            app.template_prompt(template)
+                .unwrap_or(template)
+                .to_string(),
+            vec![Line::raw(input.value())],
+            Some(input.visual_cursor() as u16),
+        ),
+        None => return,
+    };
+
+    let popup_rect = centered_rect(frame.area(), 60, lines.len() as u16 + 2);
+    frame.render_widget(Clear, popup_rect);
+
+    let widget = Paragraph::new(lines).block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled(title, theme.title)]).centered()),
+    );
+    frame.render_widget(widget, popup_rect);
+
+    if let Some(cursor) = cursor {
+        frame.set_cursor_position((popup_rect.x + 1 + cursor, popup_rect.y + 1));
+    }
+}
+
+fn render_recent_popup(frame: &mut Frame, app: &App, theme: &Theme, selected: usize) {
+    let lines: Vec<Line> = app
+        .recent_projects()
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let path = app.display_path(path);
+            if index == selected {
+                Line::styled(format!("> {path}"), theme.highlight)
+            } else {
+                Line::raw(format!("  {path}"))
+            }
+        })
+        .collect();
+
+    let popup_rect = centered_rect(frame.area(), 80, lines.len() as u16 + 2);
+    frame.render_widget(Clear, popup_rect);
+
+    let widget = Paragraph::new(lines).block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled("Recently opened", theme.title)]).centered()),
+    );
+    frame.render_widget(widget, popup_rect);
+}
+
+/// Lists the branches to open, marking the one checked out now and the
+/// ones that open another worktree.
+fn render_branch_popup(frame: &mut Frame, app: &App, theme: &Theme, picker: &BranchPicker) {
+    let lines: Vec<Line> = picker
+        .branches
+        .iter()
+        .enumerate()
+        .map(|(index, branch)| {
+            let marker = if index == picker.selected { "> " } else { "  " };
+            let mut spans = vec![Span::raw(format!("{marker}{}", branch.name))];
+            if branch.current {
+                spans.push(Span::styled(" (current)", Style::new().dim()));
+            }
+            if let Some(worktree) = &branch.worktree {
+                spans.push(Span::styled(
+                    format!(" [worktree: {}]", app.display_path(worktree)),
+                    Style::new().dim(),
+                ));
+            }
+            let line = Line::from(spans);
+            if index == picker.selected {
+                line.style(theme.highlight)
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    let popup_rect = centered_rect(frame.area(), 80, lines.len() as u16 + 2);
+    frame.render_widget(Clear, popup_rect);
+
+    let name = picker
+        .path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let widget =
+        Paragraph::new(lines).block(theme.block().title(
+            Line::from(vec![Span::styled(format!("Open {name} on"), theme.title)]).centered(),
+        ));
+    frame.render_widget(widget, popup_rect);
+}
+
+/// Lists the leader actions, like which-key, until the next key is pressed.
+fn render_leader_popup(frame: &mut Frame, app: &App, theme: &Theme) {
+    let actions = &app.config().actions;
+    let builtin = LEADER_ACTIONS
+        .iter()
+        .filter(|action| !actions.iter().any(|custom| custom.key == action.key))
+        .map(|action| (action.key, action.description));
+    let custom = actions
+        .iter()
+        .map(|action| (action.key, action.description.as_str()));
+    let lines: Vec<Line> = builtin
+        .chain(custom)
+        .map(|(key, description)| {
+            Line::from(vec![
+                Span::styled(key.to_string(), KEYBIND_STYLE),
+                Span::raw(format!("  {description}")),
+            ])
+        })
+        .collect();
+
+    let leader = match app.config().leader_key {
+        ' ' => "Space".to_string(),
+        key => key.to_string(),
+    };
+    let area = frame.area();
+    let widest = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+    let width = (widest + 4).max(30).min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup_rect = Rect {
+        x: area.right().saturating_sub(width),
+        y: area.bottom().saturating_sub(height),
+        width,
+        height,
+    };
+    frame.render_widget(Clear, popup_rect);
+
+    let widget = Paragraph::new(lines).block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled(leader, theme.title)]).centered())
+            .padding(Padding::horizontal(1)),
+    );
+    frame.render_widget(widget, popup_rect);
+}
+
+fn render_add_dir_popup(frame: &mut Frame, theme: &Theme, input: &Input) {
+    let popup_rect = centered_rect(frame.area(), 60, 3);
+    frame.render_widget(Clear, popup_rect);
+
+    let title = "No projects found. Directory to scan:";
+    let widget = Paragraph::new(Line::raw(input.value())).block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled(title, theme.title)]).centered()),
+    );
+    frame.render_widget(widget, popup_rect);
+
+    let cursor = input.visual_cursor() as u16;
+    frame.set_cursor_position((popup_rect.x + 1 + cursor, popup_rect.y + 1));
+}
+
+/// A one-line prompt, like the one for renaming a project.
+fn render_input_popup(frame: &mut Frame, theme: &Theme, title: &str, input: &Input) {
+    let popup_rect = centered_rect(frame.area(), 60, 3);
+    frame.render_widget(Clear, popup_rect);
+
+    let widget = Paragraph::new(Line::raw(input.value())).block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled(title, theme.title)]).centered()),
+    );
+    frame.render_widget(widget, popup_rect);
+
+    let cursor = input.visual_cursor() as u16;
+    frame.set_cursor_position((popup_rect.x + 1 + cursor, popup_rect.y + 1));
+}
+
+fn render_scan_errors_popup(frame: &mut Frame, app: &App, theme: &Theme) {
+    let popup_rect = centered_rect(frame.area(), 100, 30);
+    frame.render_widget(Clear, popup_rect);
+
+    let lines: Vec<Line> = if app.scan_errors.is_empty() {
+        vec![Line::raw("No scan errors")]
+    } else {
+        app.scan_errors
+            .iter()
+            .map(|error| {
+                Line::from(vec![
+                    Span::styled(error.path.display().to_string(), KEYBIND_STYLE),
+                    Span::raw(format!("  {}", error.reason)),
+                ])
+            })
+            .collect()
+    };
+
+    let widget = Paragraph::new(lines).block(
+        theme
+            .block()
+            .title(Line::from(vec![Span::styled("Scan errors", theme.title)]).centered())
+            .padding(Padding::new(2, 2, 1, 1)),
+    );
+
+    frame.render_widget(widget, popup_rect);
 }
 
-fn render_readme(frame: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+/// Marks preview titles while the preview is locked on a project.
+fn locked_marker(app: &App) -> Option<String> {
+    app.preview_locked().then(|| "[locked]".to_string())
+}
+
+fn render_readme(frame: &mut Frame, app: &mut App, theme: &Theme, area: ratatui::layout::Rect) {
+    if app.previewed_project().is_none() {
+        let placeholder = if app.visible_projects().is_empty() && app.scanning() {
+            "Scanning for projects…"
+        } else if app.visible_projects().is_empty() {
+            "No projects to preview"
+        } else {
+            "Select a project to preview"
+        };
+        let widget = Paragraph::new(Line::styled(placeholder, Style::new().dim()).centered())
+            .block(theme.block().title(Span::styled("Preview", theme.title)));
+        frame.render_widget(widget, area);
+        return;
+    }
+
+    if app.selected_languages().is_some() {
+        render_languages(frame, app, theme, area);
+        return;
+    }
+
+    if app.selected_diff_stat().is_some() {
+        render_diff_stat(frame, app, theme, area);
+        return;
+    }
+
+    if app.preview_suppressed() {
+        let title = app
+            .selected_display_path()
+            .unwrap_or_else(|| "Preview".to_string());
+        let widget = Paragraph::new(
+            Line::styled("Preview hidden by no_preview_for", Style::new().dim()).centered(),
+        )
+        .block(theme.block().title(Span::styled(title, theme.title)));
+        frame.render_widget(widget, area);
+        return;
+    }
+
     let has_remote = app
         .selected_project()
         .is_some_and(|p| !p.project_remote.is_empty());
 
-    let contents = app.selected_readme().unwrap_or("No README");
-    let mut widget = Paragraph::new(contents)
-        .block(Block::bordered().title(Span::styled("README", TITLE_STYLE)));
-
-    if has_remote {
-        widget = Paragraph::new(contents).block(
-            Block::bordered()
-                .title(Span::styled("README", TITLE_STYLE))
-                .title_bottom(
-                    Line::from(vec![
-                        Span::styled("O", KEYBIND_STYLE),
-                        Span::raw("pen in Github"),
-                    ])
-                    .centered(),
-                ),
+    let locked = locked_marker(app);
+    let display_path = app.selected_display_path();
+    let size = app.selected_size();
+    let metadata = app.selected_metadata();
+    let content_match = app.content_match();
+    let count = app.preview_count();
+    let position = app.preview_position();
+    let reading_time = app.selected_reading_time();
+    let tabs = app.preview_tabs();
+    let tab_bar_height = if tabs.is_empty() { 0 } else { 1 };
+    let commit_header = app.selected_git_header();
+    let header_height = u16::from(commit_header.is_some());
+    let height = area
+        .height
+        .saturating_sub(2 + tab_bar_height + header_height) as usize;
+    let cursor = app.scroll_preview_cursor(height);
+    let scrolled = app.clamp_preview_scroll(height);
+    let rows = app.preview_rows();
+    let (title, contents, is_markdown) = match app.selected_preview() {
+        Some((name, contents)) if count > 1 && tabs.is_empty() => {
+            let is_markdown = markdown::is_markdown(&name);
+            let title = format!("{name} [{}/{count}]", position + 1);
+            (title, contents, is_markdown)
+        }
+        Some((name, contents)) => {
+            let is_markdown = markdown::is_markdown(&name);
+            (name, contents, is_markdown)
+        }
+        None => ("README".to_string(), "No README", false),
+    };
+    let title = [
+        locked,
+        display_path,
+        Some(title),
+        reading_time,
+        metadata,
+        size,
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+
+    let scroll = match (&cursor, &content_match) {
+        (Some(cursor), _) => cursor.scroll,
+        (None, _) if scrolled > 0 => scrolled,
+        (None, Some((line, _))) => line.saturating_sub(CONTENT_MATCH_CONTEXT),
+        (None, None) => 0,
+    };
+    let content_match = content_match
+        .as_ref()
+        .map(|(line, term)| (*line, term.as_str()));
+    let styled = is_markdown.then(|| markdown::to_text(contents));
+    let styled = styled.as_ref();
+    let mut text = match &rows {
+        Some(rows) => folded_text(
+            contents,
+            styled,
+            rows,
+            scroll,
+            height,
+            content_match,
+            theme.matched,
+        ),
+        None => visible_text(
+            contents,
+            styled,
+            scroll,
+            height,
+            content_match,
+            theme.matched,
+        ),
+    };
+    if let Some(cursor) = &cursor {
+        for (index, line) in text.lines.iter_mut().enumerate() {
+            let index = match &rows {
+                Some(rows) => rows[scroll + index].0,
+                None => scroll + index,
+            };
+            if index == cursor.line {
+                line.style = line.style.patch(PREVIEW_CURSOR_STYLE);
+            } else if cursor.anchor.is_some() && cursor.selection().contains(&index) {
+                line.style = line.style.patch(PREVIEW_SELECTION_STYLE);
+            }
+        }
+    }
+
+    let mut block = theme.block().title(Span::styled(title, theme.title));
+    if cursor.is_some() {
+        let mut hint = vec![
+            Span::styled("v", KEYBIND_STYLE),
+            Span::raw(" select  "),
+            Span::styled("y", KEYBIND_STYLE),
+            Span::raw(" copy  "),
+        ];
+        if rows.is_some() {
+            hint.push(Span::styled("Enter", KEYBIND_STYLE));
+            hint.push(Span::raw(" fold  "));
+        }
+        hint.push(Span::styled("Esc", KEYBIND_STYLE));
+        hint.push(Span::raw(" back to list"));
+        block = block
+            .border_style(theme.focus)
+            .title_bottom(Line::from(hint).centered());
+    } else if has_remote {
+        block = block.title_bottom(
+            Line::from(vec![
+                Span::styled("O", KEYBIND_STYLE),
+                Span::raw("pen in Github"),
+            ])
+            .centered(),
+        );
+    }
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let [header_area, tab_area, body_area] = Layout::vertical([
+        Constraint::Length(header_height),
+        Constraint::Length(tab_bar_height),
+        Constraint::Fill(1),
+    ])
+    .areas(inner);
+
+    if let Some(header) = commit_header {
+        frame.render_widget(Line::styled(header, Style::new().dim()), header_area);
+    }
+
+    if !tabs.is_empty() {
+        let tab_bar = Tabs::new(tabs)
+            .select(position)
+            .style(Style::new().dim())
+            .highlight_style(Style::new().not_dim().patch(theme.highlight))
+            .padding("", "")
+            .divider(" │ ");
+        frame.render_widget(tab_bar, tab_area);
+    }
+    frame.render_widget(
+        Paragraph::new(text)
+            .style(theme.preview)
+            .wrap(Wrap { trim: false }),
+        body_area,
+    );
+}
+
+/// Draws `git diff --stat` with the `+` and `-` bars colored.
+fn render_diff_stat(frame: &mut Frame, app: &mut App, theme: &Theme, area: Rect) {
+    let title = [
+        locked_marker(app),
+        app.selected_display_path(),
+        Some("Changes".to_string()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+    let block = theme.block().title(Span::styled(title, theme.title));
+
+    let text = match app.selected_diff_stat().flatten() {
+        None => Text::raw("Not a git repository with commits"),
+        Some("") => Text::raw("No uncommitted changes"),
+        Some(diff_stat) => {
+            // git colors the `+` and `-` bars; the summary line, the only
+            // one without a `|`, is dimmed.
+            let mut text = ansi::to_text(diff_stat);
+            for line in &mut text.lines {
+                if !line.spans.iter().any(|span| span.content.contains('|')) {
+                    line.style = Style::new().dim();
+                }
+            }
+            text
+        }
+    };
+
+    frame.render_widget(Paragraph::new(text).block(block), area);
+}
+
+/// Builds only the `height` lines of the preview starting at `scroll`, so
+/// large files cost no more to draw than a screenful. Lines come from
+/// `styled`, the rendered markdown, when there is one. A long line wraps
+/// and pushes the rest down, so scrolling still moves by source lines. The
+/// content-search match, if in view, is highlighted.
+fn visible_text<'a>(
+    contents: &'a str,
+    styled: Option<&Text<'a>>,
+    scroll: usize,
+    height: usize,
+    content_match: Option<(usize, &str)>,
+    match_style: Style,
+) -> Text<'a> {
+    contents
+        .lines()
+        .enumerate()
+        .skip(scroll)
+        .take(height)
+        .map(|(index, line)| match content_match {
+            Some((matched, term)) if matched == index => highlight_line(line, term, match_style),
+            _ => source_line(line, styled, index),
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+/// Line `index` of the preview: from the rendered markdown if there is
+/// one, otherwise `line` as it is.
+fn source_line<'a>(line: &'a str, styled: Option<&Text<'a>>, index: usize) -> Line<'a> {
+    styled
+        .and_then(|text| text.lines.get(index).cloned())
+        .unwrap_or_else(|| Line::raw(line))
+}
+
+/// Like `visible_text`, but only for the preview `rows` left after folding.
+/// Folded headings end in a dim `…`.
+fn folded_text<'a>(
+    contents: &'a str,
+    styled: Option<&Text<'a>>,
+    rows: &[(usize, bool)],
+    scroll: usize,
+    height: usize,
+    content_match: Option<(usize, &str)>,
+    match_style: Style,
+) -> Text<'a> {
+    let lines: Vec<&str> = contents.lines().collect();
+    rows.iter()
+        .skip(scroll)
+        .take(height)
+        .map(|&(index, folded)| {
+            let mut line = match content_match {
+                Some((matched, term)) if matched == index => {
+                    highlight_line(lines[index], term, match_style)
+                }
+                _ => source_line(lines[index], styled, index),
+            };
+            if folded {
+                line.push_span(Span::styled(" …", Style::new().dim()));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .into()
+}
+
+const LANGUAGE_COLORS: [Color; 6] = [
+    Color::Blue,
+    Color::Yellow,
+    Color::Green,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Red,
+];
+
+/// Draws the language breakdown as a stacked bar with a legend below it.
+fn render_languages(frame: &mut Frame, app: &mut App, theme: &Theme, area: Rect) {
+    let title = [
+        locked_marker(app),
+        app.selected_display_path(),
+        Some("Languages".to_string()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" · ");
+    let block = theme.block().title(Span::styled(title, theme.title));
+    let width = block.inner(area).width as u64;
+
+    let languages = app.selected_languages().unwrap_or_default();
+    let total: u64 = languages.iter().map(|(_, bytes)| bytes).sum();
+    if total == 0 {
+        frame.render_widget(
+            Paragraph::new("No recognized source files").block(block),
+            area,
+        );
+        return;
+    }
+
+    let mut bar = Vec::new();
+    let mut legend = Vec::new();
+    for (index, (language, bytes)) in languages.iter().enumerate() {
+        let color = LANGUAGE_COLORS[index % LANGUAGE_COLORS.len()];
+        let cells = (bytes * width / total) as usize;
+        bar.push(Span::styled("█".repeat(cells), Style::new().fg(color)));
+        legend.push(Line::from(vec![
+            Span::styled("■ ", Style::new().fg(color)),
+            Span::raw(format!(
+                "{language} {:.1}%",
+                *bytes as f64 * 100.0 / total as f64
+            )),
+        ]));
+    }
+
+    let mut lines = vec![Line::from(bar), Line::default()];
+    lines.extend(legend);
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Splits `text` into runs in `style`, with the characters at `indices`
+/// (character positions, not bytes) patched with `match_style`.
+fn highlight_chars(
+    text: &str,
+    indices: &[usize],
+    style: Style,
+    match_style: Style,
+) -> Vec<Span<'static>> {
+    if indices.is_empty() {
+        return vec![Span::styled(text.to_string(), style)];
+    }
+
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (index, ch) in text.chars().enumerate() {
+        let matched = indices.contains(&index);
+        if matched != run_matched && !run.is_empty() {
+            let run_style = if run_matched {
+                style.patch(match_style)
+            } else {
+                style
+            };
+            spans.push(Span::styled(std::mem::take(&mut run), run_style));
+        }
+        run_matched = matched;
+        run.push(ch);
+    }
+    if !run.is_empty() {
+        let run_style = if run_matched {
+            style.patch(match_style)
+        } else {
+            style
+        };
+        spans.push(Span::styled(run, run_style));
+    }
+    spans
+}
+
+/// Highlights the line that matched a content search, with the search term
+/// itself set off.
+fn highlight_line<'a>(matched: &'a str, term: &str, match_style: Style) -> Line<'a> {
+    let spans = match matcher::find_ignoring_case(matched, term) {
+        Some(range) => vec![
+            Span::raw(&matched[..range.start]),
+            Span::styled(&matched[range.clone()], match_style.reversed()),
+            Span::styled(&matched[range.end..], match_style),
+        ],
+        None => vec![Span::styled(matched, match_style)],
+    };
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runs(spans: &[Span]) -> Vec<(String, bool)> {
+        let matched = Style::new().bold();
+        spans
+            .iter()
+            .map(|span| (span.content.to_string(), span.style == matched))
+            .collect()
+    }
+
+    #[test]
+    fn highlights_runs_of_matched_characters() {
+        let spans = highlight_chars("my-app", &[0, 1, 3], Style::new(), Style::new().bold());
+        assert_eq!(
+            runs(&spans),
+            [
+                ("my".to_string(), true),
+                ("-".to_string(), false),
+                ("a".to_string(), true),
+                ("pp".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn indices_count_characters_in_multibyte_names() {
+        let spans = highlight_chars("café-ünï", &[3, 5], Style::new(), Style::new().bold());
+        assert_eq!(
+            runs(&spans),
+            [
+                ("caf".to_string(), false),
+                ("é".to_string(), true),
+                ("-".to_string(), false),
+                ("ü".to_string(), true),
+                ("nï".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_indices_is_one_plain_span() {
+        let style = Style::new().fg(Color::Red);
+        let spans = highlight_chars("プロジェクト", &[], style, Style::new().bold());
+        assert_eq!(spans.len(), 1);
+        assert_eq!(
+            (spans[0].content.as_ref(), spans[0].style),
+            ("プロジェクト", style)
         );
+        assert!(highlight_chars("", &[0], style, Style::new().bold()).is_empty());
     }
-    frame.render_widget(widget, area);
 }
@@ -0,0 +1,225 @@
+use crate::config::UserConfig;
+use crate::git::{self, GitStatus};
+use crate::project::{Project, ScanError, discover_projects, find_file};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Reads git status on a background thread so the list can draw before the
+/// annotations are known. Paths are handled in the order they are requested.
+#[derive(Debug)]
+pub struct GitStatusWorker {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Option<GitStatus>)>,
+}
+
+impl GitStatusWorker {
+    pub fn spawn(check_stashes: bool) -> Self {
+        let (requests, request_rx) = mpsc::channel::<PathBuf>();
+        let (result_tx, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for path in request_rx {
+                let status = git::status(&path, check_stashes);
+                if result_tx.send((path, status)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { requests, results }
+    }
+
+    pub fn request(&self, path: PathBuf) {
+        let _ = self.requests.send(path);
+    }
+
+    /// The statuses finished since the last call, without waiting.
+    pub fn finished(&self) -> impl Iterator<Item = (PathBuf, Option<GitStatus>)> + '_ {
+        self.results.try_iter()
+    }
+}
+
+/// Reads each project's last commit time on a background thread, for the
+/// `Committed` sort. Paths are handled in the order they are requested.
+#[derive(Debug)]
+pub struct CommitTimeWorker {
+    requests: Sender<PathBuf>,
+    results: Receiver<(PathBuf, Option<u64>)>,
+}
+
+impl CommitTimeWorker {
+    pub fn spawn() -> Self {
+        let (requests, request_rx) = mpsc::channel::<PathBuf>();
+        let (result_tx, results) = mpsc::channel();
+
+        thread::spawn(move || {
+            for path in request_rx {
+                let time = git::last_commit_time(&path);
+                if result_tx.send((path, time)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { requests, results }
+    }
+
+    pub fn request(&self, path: PathBuf) {
+        let _ = self.requests.send(path);
+    }
+
+    /// The commit times read since the last call, without waiting.
+    pub fn finished(&self) -> impl Iterator<Item = (PathBuf, Option<u64>)> + '_ {
+        self.results.try_iter()
+    }
+}
+
+/// What a background scan has to report.
+#[derive(Debug)]
+pub enum ScanUpdate {
+    /// Projects from a root that has just finished.
+    Found(Vec<Project>),
+    /// The complete, sorted list once every root is done.
+    Finished(Vec<Project>, Vec<ScanError>),
+}
+
+/// Discovers projects on a background thread so the TUI can draw at once,
+/// however slow the roots are to read.
+#[derive(Debug)]
+pub struct ScanWorker {
+    updates: Receiver<ScanUpdate>,
+}
+
+impl ScanWorker {
+    pub fn spawn(config: UserConfig) -> Self {
+        let (update_tx, updates) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (projects, errors) = discover_projects(&config, &mut |found| {
+                if !found.is_empty() {
+                    let _ = update_tx.send(ScanUpdate::Found(found.to_vec()));
+                }
+            });
+            let _ = update_tx.send(ScanUpdate::Finished(projects, errors));
+        });
+
+        Self { updates }
+    }
+
+    /// The updates sent since the last call, without waiting.
+    pub fn updates(&self) -> impl Iterator<Item = ScanUpdate> + '_ {
+        self.updates.try_iter()
+    }
+}
+
+/// Where a `>` content search found its term in a project.
+#[derive(Debug, Clone)]
+pub struct ContentMatch {
+    pub file: PathBuf,
+    pub line: usize,
+}
+
+/// Searches project contents for a `>` query on a background thread,
+/// sending each project's first match as soon as it is found so the list
+/// fills in while typing carries on. Dropping it stops the search.
+#[derive(Debug)]
+pub struct ContentSearchWorker {
+    matches: Receiver<(PathBuf, ContentMatch)>,
+    done: Arc<AtomicBool>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl ContentSearchWorker {
+    /// Searches the first of `preview_files` in each of `projects`, in
+    /// order, for the already-lowercased `term`, then with `search_source`
+    /// the files git tracks or would track, skipping ones over `max_bytes`.
+    pub fn spawn(
+        term: String,
+        projects: Vec<PathBuf>,
+        preview_files: Vec<String>,
+        search_source: bool,
+        max_bytes: u64,
+    ) -> Self {
+        let (match_tx, matches) = mpsc::channel();
+        let done = Arc::new(AtomicBool::new(false));
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let (finished, stop) = (done.clone(), cancelled.clone());
+        thread::spawn(move || {
+            for project in projects {
+                if stop.load(Ordering::Relaxed) {
+                    return;
+                }
+                let readme = preview_files
+                    .iter()
+                    .find_map(|file| find_file(&project, file));
+                let found = readme
+                    .iter()
+                    .find_map(|file| find_line(file, &term, u64::MAX))
+                    .or_else(|| {
+                        // Listing sources runs git, so only when the README
+                        // didn't match and the search is still wanted.
+                        if !search_source || stop.load(Ordering::Relaxed) {
+                            return None;
+                        }
+                        git::list_files(&project).iter().find_map(|file| {
+                            if stop.load(Ordering::Relaxed) {
+                                return None;
+                            }
+                            find_line(&project.join(file), &term, max_bytes)
+                        })
+                    });
+                if let Some(found) = found
+                    && match_tx.send((project, found)).is_err()
+                {
+                    return;
+                }
+            }
+            finished.store(true, Ordering::Release);
+        });
+
+        Self {
+            matches,
+            done,
+            cancelled,
+        }
+    }
+
+    /// The matches found since the last call, without waiting.
+    pub fn matches(&self) -> impl Iterator<Item = (PathBuf, ContentMatch)> + '_ {
+        self.matches.try_iter()
+    }
+
+    /// Whether every project has been searched.
+    pub fn done(&self) -> bool {
+        self.done.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for ContentSearchWorker {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The first line of `file` containing `term`, ignoring case. Files over
+/// `max_bytes`, and ones that aren't UTF-8 text, such as images, are
+/// skipped.
+fn find_line(file: &Path, term: &str, max_bytes: u64) -> Option<ContentMatch> {
+    let metadata = fs::metadata(file).ok()?;
+    if !metadata.is_file() || metadata.len() > max_bytes {
+        return None;
+    }
+    let contents = fs::read_to_string(file).ok()?;
+    let line = contents
+        .lines()
+        .position(|line| line.to_lowercase().contains(term))?;
+    Some(ContentMatch {
+        file: file.to_path_buf(),
+        line,
+    })
+}